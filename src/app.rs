@@ -1,12 +1,29 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
 use std::sync::mpsc;
 use crate::collections::{CollectionManager, ApiRequest};
+use crate::environments::EnvironmentManager;
+use crate::json_tree::JsonTree;
+use crate::response::{ApiResponse, Progress, ResponseView};
+use crate::scripting::ScriptOutput;
+use crate::http::PreparedRequest;
+use crate::assertions::AssertionResult;
+use crate::runner::{CollectionRun, RequestRun};
+use crate::codegen::Language;
+use crate::auth::{Auth, TokenStore};
+use crate::params::Params;
+use crate::headers::Header;
+use crate::form::Form;
+use crate::body::BodyType;
+use crate::graphql::{GraphQlBody, Schema};
+use crate::history::{HistoryEntry, HistoryFilter};
+use crate::clipboard::Clipboard;
+use crate::opener::{Opener, TempFile};
+use crate::img_preview::{Graphics, Placement, Preview, Protocol};
+use crate::sse::{SseEvent, SseParser};
+use chrono::Local;
 use std::time::{Duration, Instant};
-use std::collections::{HashMap, HashSet};
-use reqwest::Method;
 use tui_textarea::{TextArea, CursorMove};
-use std::process::{Command, Stdio};
-use std::io::Write;
+use std::process::Command;
 use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
 use sysinfo::{System, Pid};
@@ -17,32 +34,81 @@ use std::path::{PathBuf};
 pub enum ActivePanel { Collections, Editor, Response, AI }
 
 #[derive(Clone, Copy, PartialEq)]
-pub enum EditorFocus { Url, Headers, Body, Attachment }
-
-#[derive(Clone, Copy, PartialEq)]
-pub enum BodyType { Json, Text, Form }
+pub enum EditorFocus { Url, Params, Headers, Body, Variables, Auth, PreScript, PostScript, Tests, Description, Attachment }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum LeftPanelTab { Collections, History }
 
-pub enum ResponseData {
-    Text(String),
-    Binary(String, Vec<u8>), // Texto descriptivo + bytes
+/// Bytes de un body binario que se vuelcan en hex (el resto se guarda con `w`)
+const HEX_DUMP_LIMIT: usize = 64 * 1024;
+
+/// Eventos SSE que se guardan por pestaña; de un stream largo se quedan los últimos
+const SSE_EVENTS_LIMIT: usize = 1000;
+
+/// Envío en curso de una pestaña; soltarlo (parar, reenviar o cerrar la pestaña) lo cancela
+pub struct InFlight {
+    pub id: u64,
+    handle: tokio::task::AbortHandle,
+    /// Se completa y se guarda en el historial cuando termina el envío
+    entry: HistoryEntry,
+    started: Instant,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) { self.handle.abort(); }
 }
 
 pub struct RequestTab<'a> {
     pub name: String,
     pub url_area: TextArea<'a>,
+    pub params_area: TextArea<'a>,
     pub headers_area: TextArea<'a>,
     pub body_area: TextArea<'a>,
+    /// Variables JSON del body GraphQL (el query vive en `body_area`)
+    pub graphql_vars_area: TextArea<'a>,
+    pub graphql_operation: Option<String>,
+    /// Schema obtenido por introspección para autocompletar campos
+    pub graphql_schema: Option<Schema>,
+    pub auth_area: TextArea<'a>,
+    pub pre_script_area: TextArea<'a>,
+    pub post_script_area: TextArea<'a>,
+    pub tests_area: TextArea<'a>,
+    /// Documentación Markdown de la petición
+    pub description_area: TextArea<'a>,
+    pub test_results: Vec<AssertionResult>,
     pub file_path: String,
+    pub insecure: bool,
+    /// Carpeta de la petición cargada, de la que hereda la auth
+    pub group: Option<String>,
     pub method: String,
     pub response: String,
-    pub response_bytes: Option<Vec<u8>>,
+    pub api_response: Option<ApiResponse>,
+    /// Body de `api_response` ya escrito en disco para abrirlo con el sistema (`o`)
+    pub response_file: Option<TempFile>,
+    /// Vista previa de la imagen de `api_response` para el último tamaño de panel
+    pub image_preview: Option<Preview>,
+    /// Vista previa que pidió el último dibujado (protocolo y tamaño) y aún no existe
+    pub preview_wanted: Option<(Protocol, u16, u16)>,
+    /// La que se está calculando en segundo plano: (id del trabajo, lo pedido)
+    preview_job: Option<(u64, (Protocol, u16, u16))>,
+    /// Eventos de un body `text/event-stream`, según llegan (como mucho `SSE_EVENTS_LIMIT`)
+    pub sse_events: Vec<SseEvent>,
+    /// Eventos más antiguos descartados por el límite
+    pub sse_dropped: usize,
+    sse: SseParser,
+    /// Bytes del body ya pasados a `response` mientras llega (sin cortar caracteres UTF-8)
+    streamed: usize,
+    pub in_flight: Option<InFlight>,
+    pub response_view: ResponseView,
     pub editor_focus: EditorFocus,
     pub body_type: BodyType,
     pub response_scroll: u16,
     pub is_tree_mode: bool,
+    pub json_tree: Option<JsonTree>,
+    /// Petición guardada de la que viene la pestaña; `s` la sobrescribe
+    pub source: Option<ApiRequest>,
+    /// Estado del editor al cargar o guardar, para marcar cambios sin guardar
+    pub saved_snapshot: String,
 }
 
 impl<'a> RequestTab<'a> {
@@ -50,30 +116,353 @@ impl<'a> RequestTab<'a> {
         let mut url_area = TextArea::default(); url_area.insert_str("https://jsonplaceholder.typicode.com/posts");
         let mut headers_area = TextArea::default(); headers_area.insert_str("Content-Type: application/json");
         let mut body_area = TextArea::default(); body_area.insert_str("{\n  \"title\": \"Arthema Request\"\n}");
-        Self {
-            name, url_area, headers_area, body_area,
-            file_path: "".to_string(), method: "GET".to_string(),
-            response: "".to_string(), response_bytes: None, editor_focus: EditorFocus::Url,
-            body_type: BodyType::Json, response_scroll: 0, is_tree_mode: false,
+        let mut tab = Self {
+            name, url_area, params_area: TextArea::default(), headers_area, body_area, auth_area: TextArea::default(),
+            graphql_vars_area: TextArea::default(), graphql_operation: None, graphql_schema: None,
+            pre_script_area: TextArea::default(), post_script_area: TextArea::default(),
+            tests_area: TextArea::default(), description_area: TextArea::default(), test_results: Vec::new(),
+            file_path: "".to_string(), insecure: false, group: None, method: "GET".to_string(),
+            response: "".to_string(), api_response: None, response_file: None, image_preview: None, preview_wanted: None, preview_job: None, response_view: ResponseView::Body, editor_focus: EditorFocus::Url,
+            sse_events: Vec::new(), sse_dropped: 0, sse: SseParser::default(), streamed: 0, in_flight: None,
+            body_type: BodyType::Json, response_scroll: 0, is_tree_mode: false, json_tree: None,
+            source: None, saved_snapshot: String::new(),
+        };
+        tab.saved_snapshot = tab.snapshot();
+        tab
+    }
+
+    /// Área de texto correspondiente al foco actual (Attachment no es editable)
+    pub fn focused_area_mut(&mut self) -> Option<&mut TextArea<'a>> {
+        match self.editor_focus {
+            EditorFocus::Url => Some(&mut self.url_area),
+            EditorFocus::Params => Some(&mut self.params_area),
+            EditorFocus::Headers => Some(&mut self.headers_area),
+            EditorFocus::Body => Some(&mut self.body_area),
+            EditorFocus::Variables => Some(&mut self.graphql_vars_area),
+            EditorFocus::Auth => Some(&mut self.auth_area),
+            EditorFocus::PreScript => Some(&mut self.pre_script_area),
+            EditorFocus::PostScript => Some(&mut self.post_script_area),
+            EditorFocus::Tests => Some(&mut self.tests_area),
+            EditorFocus::Description => Some(&mut self.description_area),
+            EditorFocus::Attachment => None,
         }
     }
+
+    pub fn load_request(&mut self, req: &ApiRequest) {
+        self.url_area = TextArea::default(); self.url_area.insert_str(&req.url);
+        let saved = Params { query: req.disabled_params.iter().map(|(k, v)| (k.clone(), v.clone(), false)).collect(), path: req.path_params.clone() };
+        self.set_params(Params::from_url(&req.url, &saved));
+        self.headers_area = TextArea::default(); self.headers_area.insert_str(crate::headers::to_text(&req.headers));
+        self.body_type = req.body_type;
+        self.body_area = TextArea::default();
+        self.graphql_vars_area = TextArea::default();
+        self.graphql_operation = None;
+        match (&req.form, &req.graphql) {
+            (Some(form), _) if req.body_type.form_kind().is_some() => { self.body_area.insert_str(form.to_text()); }
+            (_, Some(gql)) if req.body_type == BodyType::GraphQl => {
+                self.body_area.insert_str(&gql.query);
+                self.graphql_vars_area.insert_str(&gql.variables);
+                self.graphql_operation = gql.operation_name.clone();
+            }
+            _ => { if let Some(b) = &req.body { self.body_area.insert_str(b); } }
+        }
+        self.file_path = req.file_path.clone().unwrap_or_default();
+        self.auth_area = TextArea::default(); self.auth_area.insert_str(req.auth.to_text());
+        self.pre_script_area = TextArea::default(); if let Some(s) = &req.pre_request_script { self.pre_script_area.insert_str(s); }
+        self.post_script_area = TextArea::default(); if let Some(s) = &req.post_response_script { self.post_script_area.insert_str(s); }
+        self.tests_area = TextArea::default(); self.tests_area.insert_str(crate::assertions::to_text(&req.assertions));
+        self.description_area = TextArea::default(); if let Some(d) = &req.description { self.description_area.insert_str(d); }
+        self.test_results.clear();
+        self.insecure = req.insecure;
+        self.group = req.group.clone();
+        self.method = req.method.clone();
+        // Quien carga desde la colección fija el origen con `mark_saved`
+        self.source = None;
+        self.saved_snapshot = self.snapshot();
+    }
+
+    /// Todo lo editable de la pestaña (auth y tests como texto, aunque no parseen)
+    fn snapshot(&self) -> String {
+        let req = crate::collections::to_json(&self.to_api_request()).unwrap_or_default();
+        format!("{}\n{}\n{}", req, self.auth_area.lines().join("\n"), self.tests_area.lines().join("\n"))
+    }
+
+    pub fn is_dirty(&self) -> bool { self.snapshot() != self.saved_snapshot }
+
+    /// La petición de origen se renombró o cambió de carpeta: la pestaña la sigue sin quedar
+    /// marcada como modificada (si ya lo estaba, lo sigue estando)
+    pub fn follow_source(&mut self, req: &ApiRequest) {
+        let dirty = self.is_dirty();
+        self.name = req.name.clone();
+        self.group = req.group.clone();
+        if let Some(src) = &mut self.source { src.name = req.name.clone(); src.group = req.group.clone(); }
+        if !dirty { self.saved_snapshot = self.snapshot(); }
+    }
+
+    /// La pestaña pasa a representar `req`, tal como está en disco
+    pub fn mark_saved(&mut self, req: ApiRequest) {
+        self.name = req.name.clone();
+        self.group = req.group.clone();
+        self.source = Some(req);
+        self.saved_snapshot = self.snapshot();
+    }
+
+    /// Contenido del editor como petición guardable. Auth y aserciones quedan por defecto:
+    /// quien las necesite las valida con `auth()` y `parse_assertions`.
+    pub fn to_api_request(&self) -> ApiRequest {
+        let (form, graphql) = (self.form(), self.graphql());
+        let params = self.params();
+        ApiRequest {
+            name: self.name.clone(),
+            url: self.url(),
+            method: self.method.clone(),
+            description: RequestTab::script_text(&self.description_area),
+            headers: self.headers(),
+            body: (form.is_none() && graphql.is_none()).then(|| self.body_area.lines().join("\n")),
+            body_type: self.body_type,
+            form,
+            graphql,
+            file_path: (!self.file_path.is_empty()).then(|| self.file_path.clone()),
+            group: self.group.clone(),
+            pre_request_script: RequestTab::script_text(&self.pre_script_area),
+            post_response_script: RequestTab::script_text(&self.post_script_area),
+            insecure: self.insecure,
+            path_params: params.path.clone(),
+            disabled_params: params.disabled(),
+            ..Default::default()
+        }
+    }
+
+    /// Petición tal como se enviaría (sin scripts ni variables aplicados)
+    pub fn to_prepared(&self) -> PreparedRequest { PreparedRequest::from_api_request(&self.to_api_request()) }
+
+    pub fn url(&self) -> String { self.url_area.lines()[0].clone() }
+
+    pub fn form(&self) -> Option<Form> { self.body_type.form_kind().map(|kind| Form::parse(&self.body_area.lines().join("\n"), kind)) }
+
+    pub fn graphql(&self) -> Option<GraphQlBody> {
+        (self.body_type == BodyType::GraphQl).then(|| GraphQlBody {
+            query: self.body_area.lines().join("\n"),
+            variables: self.graphql_vars_area.lines().join("\n"),
+            operation_name: self.graphql_operation.clone(),
+        })
+    }
+
+    /// `O`: pasa a la siguiente operación con nombre del documento (y luego a ninguna)
+    pub fn cycle_graphql_operation(&mut self) {
+        let names = crate::graphql::operation_names(&self.body_area.lines().join("\n"));
+        let next = match self.graphql_operation.as_ref().and_then(|op| names.iter().position(|n| n == op)) {
+            Some(i) => names.get(i + 1).cloned(),
+            None => names.first().cloned(),
+        };
+        self.graphql_operation = next;
+    }
+
+    /// Campos del schema que completan la palabra del cursor en el query
+    pub fn graphql_suggestions(&self) -> Vec<&str> {
+        let Some(schema) = &self.graphql_schema else { return Vec::new() };
+        let (row, col) = self.body_area.cursor();
+        let lines = self.body_area.lines();
+        let mut before = lines[..row].join("\n");
+        if row > 0 { before.push('\n'); }
+        before.extend(lines[row].chars().take(col));
+        schema.suggestions(&before)
+    }
+
+    /// Tab en el query GraphQL: acepta la primera sugerencia. `false` si no había ninguna.
+    pub fn complete_graphql(&mut self) -> bool {
+        let Some(first) = self.graphql_suggestions().first().map(|s| s.to_string()) else { return false };
+        let (row, col) = self.body_area.cursor();
+        let line: Vec<char> = self.body_area.lines()[row].chars().collect();
+        let start = line[..col].iter().rposition(|c| !(c.is_alphanumeric() || *c == '_')).map(|i| i + 1).unwrap_or(0);
+        let text = format!("{}{}{}", line[..start].iter().collect::<String>(), first, line[col..].iter().collect::<String>());
+        replace_line(&mut self.body_area, row, text, start + first.chars().count());
+        true
+    }
+
+    /// `F`: formatea el body JSON/XML, o las variables si es GraphQL
+    pub fn format_body(&mut self) -> Result<(), String> {
+        let (area, xml) = match self.body_type {
+            BodyType::GraphQl => (&mut self.graphql_vars_area, false),
+            BodyType::Json => (&mut self.body_area, false),
+            BodyType::Xml => (&mut self.body_area, true),
+            t => return Err(format!("{} bodies have no formatter", t.label())),
+        };
+        let text = area.lines().join("\n");
+        if text.trim().is_empty() { return Ok(()); }
+        let pretty = if xml { crate::xml::pretty(&text).ok_or("body is not well-formed XML")? } else { crate::body::pretty_json(&text) };
+        *area = TextArea::from(pretty.lines().map(str::to_string).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    /// Ajusta el Content-Type sin tocar el resto de headers; `None` lo quita (multipart lo pone el cliente)
+    pub fn set_content_type(&mut self, value: Option<&str>) {
+        let mut headers = self.headers();
+        match value {
+            Some(v) => crate::headers::set(&mut headers, "Content-Type", v.to_string()),
+            None => headers.retain(|h| !h.name.eq_ignore_ascii_case("content-type")),
+        }
+        self.headers_area = TextArea::default(); self.headers_area.insert_str(crate::headers::to_text(&headers));
+    }
+
+    pub fn headers(&self) -> Vec<Header> { crate::headers::parse_text(&self.headers_area.lines().join("\n")) }
+
+    pub fn params(&self) -> Params { Params::parse(&self.params_area.lines().join("\n")) }
+
+    fn set_params(&mut self, params: Params) {
+        self.params_area = TextArea::default();
+        self.params_area.insert_str(params.to_text());
+    }
+
+    /// La URL cambió: recalcula las filas conservando desactivadas y valores de ruta. Si la
+    /// query y la ruta dicen lo mismo las filas no se tocan (ni su orden ni su undo)
+    pub fn sync_params_from_url(&mut self) {
+        let current = self.params();
+        let params = Params::from_url(&self.url(), &current);
+        if params.url_parts() != current.url_parts() { replace_text(&mut self.params_area, &params.to_text()); }
+    }
+
+    /// Las filas cambiaron: reescribe la query de la URL, sólo si cambia lo que significa
+    /// (se respeta cómo la escribió el usuario: `%20` o `+`, orden de claves repetidas...)
+    pub fn sync_url_from_params(&mut self) {
+        let (url, params) = (self.url(), self.params());
+        if crate::params::parse_query(&url) != params.url_parts().0 { replace_text(&mut self.url_area, &crate::params::build_url(&url, &params)); }
+    }
+
+    /// Ctrl+O: activa/desactiva la fila del cursor en Headers o Params (los `:id` de ruta no se desactivan)
+    pub fn toggle_current_row(&mut self) {
+        let area = match self.editor_focus { EditorFocus::Headers => &mut self.headers_area, EditorFocus::Params => &mut self.params_area, _ => return };
+        let (row, col) = area.cursor();
+        let line = area.lines()[row].clone();
+        if self.editor_focus == EditorFocus::Params && line.trim_start().starts_with(':') { return; }
+        let toggled = crate::headers::toggle_line(&line);
+        let col = (col + toggled.chars().count()).saturating_sub(line.chars().count());
+        replace_line(area, row, toggled, col);
+        if self.editor_focus == EditorFocus::Params { self.sync_url_from_params(); }
+    }
+
+    /// Nombres o valores de header que completan la fila del cursor
+    pub fn header_suggestions(&self) -> Vec<&'static str> {
+        let (row, col) = self.headers_area.cursor();
+        crate::headers::suggestions(&self.headers_area.lines()[row], col)
+    }
+
+    /// Tab en Headers: acepta la primera sugerencia. `false` si no había ninguna.
+    pub fn complete_header(&mut self) -> bool {
+        let Some(first) = self.header_suggestions().first().copied() else { return false };
+        let row = self.headers_area.cursor().0;
+        let line = crate::headers::complete_line(&self.headers_area.lines()[row], first);
+        let col = line.chars().count();
+        replace_line(&mut self.headers_area, row, line, col);
+        true
+    }
+
+    pub fn auth(&self) -> Result<Auth, String> { Auth::parse(&self.auth_area.lines().join("\n")) }
+
+    /// Muestra una respuesta en la pestaña (árbol JSON si aplica, volcado hex si es binaria)
+    pub fn show_response(&mut self, resp: ApiResponse) {
+        self.json_tree = if resp.is_binary() { None } else { serde_json::from_slice(&resp.body).ok().map(JsonTree::new) };
+        if self.json_tree.is_none() { self.is_tree_mode = false; }
+        self.response = if resp.is_image() { String::new() } else if resp.is_binary() { crate::response::hex_dump(&resp.body, HEX_DUMP_LIMIT) } else { resp.pretty_body() };
+        self.response_scroll = 0;
+        (self.sse_events, self.sse_dropped) = (Vec::new(), 0);
+        if resp.is_event_stream() { self.sse_events = crate::sse::parse(&resp.body); self.sse_dropped = cap_sse_events(&mut self.sse_events); }
+        self.api_response = Some(resp);
+        self.response_file = None;
+        (self.image_preview, self.preview_wanted, self.preview_job) = (None, None, None);
+        (self.sse, self.streamed) = (SseParser::default(), 0);
+    }
+
+    /// Avance de la respuesta en curso: el panel se rellena según llegan los trozos
+    pub fn receive(&mut self, progress: Progress) {
+        match progress {
+            Progress::Headers(head) => { self.show_response(head); self.response.clear(); }
+            Progress::Chunk(bytes) => {
+                let Some(resp) = self.api_response.as_mut() else { return };
+                resp.body.extend_from_slice(&bytes);
+                if resp.is_binary() { self.response = format!("⏬ {} received...", crate::response::format_size(resp.body.len())); return; }
+                if resp.is_event_stream() { self.sse_events.extend(self.sse.push(&bytes)); self.sse_dropped += cap_sse_events(&mut self.sse_events); }
+                // Un carácter partido entre dos trozos espera al siguiente
+                let pending = &resp.body[self.streamed..];
+                let ready = match std::str::from_utf8(pending) { Err(e) if e.error_len().is_none() => e.valid_up_to(), _ => pending.len() };
+                self.response.push_str(&String::from_utf8_lossy(&pending[..ready]));
+                self.streamed += ready;
+            }
+        }
+    }
+
+    /// Texto de un script o descripción, o `None` si está vacío (para no ensuciar el JSON guardado)
+    pub fn script_text(area: &TextArea) -> Option<String> {
+        let s = area.lines().join("\n");
+        if s.trim().is_empty() { None } else { Some(s) }
+    }
+}
+
+/// Cambia todo el texto del editor como una edición más (se puede deshacer)
+fn replace_text(area: &mut TextArea<'_>, text: &str) {
+    area.select_all();
+    area.insert_str(text);
+}
+
+/// Deja los últimos `SSE_EVENTS_LIMIT` eventos y devuelve cuántos se descartaron
+fn cap_sse_events(events: &mut Vec<SseEvent>) -> usize {
+    let extra = events.len().saturating_sub(SSE_EVENTS_LIMIT);
+    events.drain(..extra);
+    extra
+}
+
+/// Sustituye una línea del editor dejando el cursor en `col`. Se edita con el API del
+/// TextArea (borrar + insertar) para no perder el undo; el texto cortado no pisa el yank
+fn replace_line(area: &mut TextArea<'_>, row: usize, text: String, col: usize) {
+    let (yank, len) = (area.yank_text(), area.lines()[row].chars().count());
+    area.cancel_selection();
+    area.move_cursor(CursorMove::Jump(row as u16, 0));
+    area.delete_str(len);
+    area.insert_str(text);
+    area.set_yank_text(yank);
+    area.move_cursor(CursorMove::Jump(row as u16, col as u16));
 }
 
 #[derive(Debug)]
 pub enum AppEvent {
-    ApiResponse(String, Option<Vec<u8>>),
+    /// (id del envío, respuesta completa)
+    ApiResponse(u64, Box<ApiResponse>),
+    /// Headers o trozo del body de un envío que sigue en curso
+    Stream(u64, Progress),
+    RequestFailed(u64, String),
     AiMessage(String),
     SystemMessage(String),
     SwaggerImported(Vec<ApiRequest>),
+    /// (id de ejecución, índice de la fila, resultado)
+    RunnerResult(u64, usize, Box<RequestRun>),
+    /// Schema de introspección para las pestañas con esa URL
+    GraphQlSchema(String, Result<Schema, String>),
+    /// (id del trabajo, vista previa de imagen ya codificada)
+    ImagePreview(u64, Box<Preview>),
 }
 
 pub enum CollectionItem {
+    /// Ruta completa de la carpeta ("Users/Admin")
     Folder(String),
     Request(usize),
 }
 
+/// Modal de carpetas: crear una subcarpeta o mover una petición/carpeta a otra
+#[derive(Clone, Debug, PartialEq)]
+pub enum FolderPrompt {
+    /// Carpeta nueva dentro de la indicada (`None` = primer nivel)
+    NewFolder(Option<String>),
+    /// `id` de la petición a mover
+    MoveRequest(String),
+    MoveFolder(String),
+}
+
 #[derive(Clone, PartialEq, Debug)]
-pub enum ImportType { None, Postman, Bruno }
+pub enum ImportType {
+    None, Postman, Bruno,
+    /// El selector elige dónde guardar el body de la respuesta (`save_file_name`)
+    SaveResponse,
+}
 
 pub struct App<'a> {
     pub tabs: Vec<RequestTab<'a>>,
@@ -81,21 +470,60 @@ pub struct App<'a> {
     pub ai_response: String,
     pub active_panel: ActivePanel,
     pub left_panel_tab: LeftPanelTab,
-    pub expanded_groups: HashSet<String>,
     pub input_mode: bool,
     pub is_ai_loading: bool,
     pub tx: mpsc::Sender<AppEvent>,
     pub rx: mpsc::Receiver<AppEvent>,
     pub collections: CollectionManager,
+    pub environments: EnvironmentManager,
+    pub tokens: TokenStore,
     pub config: crate::config::Config,
+    pub clipboard: Clipboard,
+    pub opener: Opener,
+    /// Protocolo de imágenes de la terminal, detectado al arrancar
+    pub graphics: Graphics,
+    /// Imagen que la UI pidió dibujar con el protocolo en este frame (la escribe `main`)
+    pub image_placement: Option<Placement>,
+    /// Id del último envío: las respuestas vuelven a la pestaña que lo tenga en `in_flight`
+    pub request_seq: u64,
     pub key_input: TextArea<'a>,
     pub show_key_input: bool,
     pub swagger_input: TextArea<'a>,
     pub show_swagger_input: bool,
     pub rename_input: TextArea<'a>,
     pub show_rename_input: bool,
+    /// Modal "guardar como": nombre y carpeta (existente o nueva)
+    pub show_save_as: bool,
+    pub save_as_name: TextArea<'a>,
+    pub save_as_folder: TextArea<'a>,
+    pub save_as_focus_folder: bool,
+    /// Modal de carpetas abierto si es `Some`
+    pub folder_prompt: Option<FolderPrompt>,
+    pub folder_prompt_input: TextArea<'a>,
+    /// Carpeta marcada con un primer `d`: el segundo la borra con su contenido
+    pub pending_folder_delete: Option<String>,
     pub show_import_menu: bool,
     pub show_help: bool,
+    /// Filtro del panel History; `show_history_filter` abre el modal para escribirlo
+    pub history_filter: HistoryFilter,
+    pub show_history_filter: bool,
+    pub history_filter_input: TextArea<'a>,
+    /// Selector de workspaces; con `workspace_adding` se escribe la ruta de uno nuevo
+    pub show_workspace_picker: bool,
+    pub workspace_selected: usize,
+    pub workspace_adding: bool,
+    pub workspace_input: TextArea<'a>,
+    pub show_runner: bool,
+    pub runner: Option<CollectionRun>,
+    pub runner_concurrency: usize,
+    /// Petición a exportar como código (modal abierto si es `Some`)
+    pub code_export: Option<PreparedRequest>,
+    pub code_language: Language,
+    /// El snippet lleva los valores del entorno en vez de los `{{var}}`; se pide con `r`
+    pub code_resolved: bool,
+    /// Carpeta cuya auth se está editando (modal abierto si es `Some`)
+    pub folder_auth_target: Option<String>,
+    pub folder_auth_input: TextArea<'a>,
     pub help_scroll: u16,
     pub current_import_type: ImportType,
     pub selected_idx: usize,
@@ -104,70 +532,138 @@ pub struct App<'a> {
     pub current_dir: PathBuf,
     pub file_entries: Vec<String>,
     pub file_picker_state: ListState,
+    pub save_file_name: TextArea<'a>,
     pub sys: System, pub cpu_usage: f32, pub mem_total: u64, pub mem_used: u64,
     pub proc_cpu: f32, pub proc_mem: u64, pub battery_level: String, pub last_sys_update: Instant,
 }
 
 impl<'a> App<'a> {
-    pub fn new() -> App<'a> {
+    /// Arranca en el workspace de `--workspace` o, sin él, en el que resuelva `workspace::resolve`
+    pub fn with_workspace(flag: Option<PathBuf>) -> App<'a> {
+        let config = crate::config::Config::load();
+        let workspace = crate::workspace::resolve(&config, flag.as_deref());
+        Self::build(config, workspace, Clipboard::detect(), Opener::System, Graphics::detect())
+    }
+
+    /// Para tests: el workspace indicado (nunca el del checkout), config sin archivo y
+    /// portapapeles, opener y gráficos que no salen del proceso
+    #[cfg(test)]
+    pub fn for_tests(workspace: &std::path::Path) -> App<'a> {
+        Self::build(crate::config::Config::default(), workspace.to_path_buf(), Clipboard::new(crate::clipboard::Backend::Memory), Opener::fake(), Graphics::default())
+    }
+
+    fn build(mut config: crate::config::Config, workspace: PathBuf, clipboard: Clipboard, opener: Opener, graphics: Graphics) -> App<'a> {
         let (tx, rx) = mpsc::channel();
         let mut sys = System::new_all(); sys.refresh_all();
+        let mut collections = CollectionManager::open(&workspace.to_string_lossy());
+        let _ = collections.history.apply_retention(config.history, Local::now());
+        if let Some(manifest) = &collections.manifest {
+            let before = (config.workspace.clone(), config.workspaces.clone());
+            crate::workspace::register(&mut config, &workspace, &manifest.name);
+            // Sólo si cambió algo, y nunca sobre un config.json que no se pudo leer
+            if config.load_error().is_none() && before != (config.workspace.clone(), config.workspaces.clone()) { let _ = config.save(); }
+        }
+        let environments = EnvironmentManager::new(&collections.base_path);
+        let tokens = TokenStore::new(&collections.base_path);
+        let config_report = config.load_error().map(|e| format!("SYSTEM ERROR: Config: {}. The file is left untouched and settings are not saved until it is fixed.", e));
+        let reports: Vec<String> = config_report.into_iter().chain(collections.load_report()).chain(environments.load_report()).collect();
+        let ai_response = if reports.is_empty() { "ARTHEMA SYSTEM READY".to_string() } else { reports.join("\n") };
         App {
             tabs: vec![RequestTab::new("Req 1".to_string())], active_tab: 0,
-            ai_response: "ARTHEMA SYSTEM READY".to_string(),
+            ai_response,
             active_panel: ActivePanel::Editor, left_panel_tab: LeftPanelTab::Collections,
-            expanded_groups: HashSet::new(),
-            input_mode: false, is_ai_loading: false, tx, rx, collections: CollectionManager::new(),
-            config: crate::config::Config::load(),
+            input_mode: false, is_ai_loading: false, tx, rx, collections, environments, tokens,
+            config,
+            clipboard, opener, graphics,
+            image_placement: None,
+            request_seq: 0,
             key_input: TextArea::default(),
             show_key_input: false,
             swagger_input: TextArea::default(),
             show_swagger_input: false,
             rename_input: TextArea::default(),
             show_rename_input: false,
+            show_save_as: false,
+            save_as_name: TextArea::default(),
+            save_as_folder: TextArea::default(),
+            save_as_focus_folder: false,
+            folder_prompt: None,
+            folder_prompt_input: TextArea::default(),
+            pending_folder_delete: None,
             show_import_menu: false,
             show_help: false,
+            history_filter: HistoryFilter::default(),
+            show_history_filter: false,
+            history_filter_input: TextArea::default(),
+            show_workspace_picker: false,
+            workspace_selected: 0,
+            workspace_adding: false,
+            workspace_input: TextArea::default(),
+            show_runner: false,
+            runner: None,
+            runner_concurrency: 1,
+            code_export: None,
+            code_language: Language::Curl,
+            code_resolved: false,
+            folder_auth_target: None,
+            folder_auth_input: TextArea::default(),
             help_scroll: 0,
             current_import_type: ImportType::None,
             selected_idx: 0,
             url_rect: Rect::default(), headers_rect: Rect::default(), body_rect: Rect::default(), attach_rect: Rect::default(), response_rect: Rect::default(),
-            show_file_picker: false, current_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")), file_entries: Vec::new(), file_picker_state: ListState::default(),
+            show_file_picker: false, current_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")), file_entries: Vec::new(), file_picker_state: ListState::default(), save_file_name: TextArea::default(),
             sys, cpu_usage: 0.0, mem_total: 0, mem_used: 0, proc_cpu: 0.0, proc_mem: 0,
             battery_level: "N/A".to_string(), last_sys_update: Instant::now(),
         }
     }
 
+    /// Secuencia de la imagen a dibujar en `image_placement`, si es de protocolo
+    pub fn image_sequence(&self) -> Option<&str> {
+        let p = self.image_placement.as_ref()?;
+        match self.tabs.get(p.tab)?.image_preview.as_ref().map(|i| &i.content) {
+            Some(crate::img_preview::Content::Sequence(s)) => Some(s),
+            _ => None,
+        }
+    }
+
     pub fn current_tab(&self) -> &RequestTab<'a> { &self.tabs[self.active_tab] }
     pub fn current_tab_mut(&mut self) -> &mut RequestTab<'a> { &mut self.tabs[self.active_tab] }
 
     pub fn is_input_active(&self) -> bool {
-        self.input_mode || self.show_rename_input || self.show_swagger_input || self.show_key_input || self.show_file_picker || self.show_import_menu || self.show_help
+        self.input_mode || self.show_rename_input || self.show_save_as || self.folder_prompt.is_some() || self.show_swagger_input || self.show_key_input || self.show_file_picker || self.show_import_menu || self.show_help || self.show_history_filter || self.show_workspace_picker || self.show_runner || self.code_export.is_some() || self.folder_auth_target.is_some()
+    }
+
+    /// Índices en `collections.history` de las entradas que pasan el filtro, en el orden del panel
+    pub fn visible_history(&self) -> Vec<usize> {
+        self.collections.history.entries.iter().enumerate().filter(|(_, e)| self.history_filter.matches(e)).map(|(i, _)| i).collect()
     }
 
+    fn selected_history(&self) -> Option<&HistoryEntry> {
+        self.visible_history().get(self.selected_idx).and_then(|&i| self.collections.history.get(i))
+    }
+
+    /// Árbol de Collections aplanado: cada carpeta desplegada muestra sus subcarpetas y
+    /// luego sus peticiones, a cualquier profundidad. UNGROUPED va al final.
     pub fn get_visible_items(&self) -> Vec<CollectionItem> {
         let mut items = Vec::new();
-        let mut groups: Vec<String> = self.collections.requests.iter()
-            .filter_map(|r| r.group.clone())
-            .collect::<HashSet<_>>().into_iter().collect();
-        groups.sort();
-        
-        // Agregar "UNGROUPED" si hay peticiones sin grupo
+        self.push_folder_items(&self.collections.folders(), None, &mut items);
         if self.collections.requests.iter().any(|r| r.group.is_none()) {
-            groups.push("UNGROUPED".to_string());
+            items.push(CollectionItem::Folder("UNGROUPED".to_string()));
+            if self.collections.tree.expanded.contains("UNGROUPED") {
+                items.extend(self.collections.requests.iter().enumerate().filter(|(_, r)| r.group.is_none()).map(|(idx, _)| CollectionItem::Request(idx)));
+            }
         }
+        items
+    }
 
-        for group in groups {
-            items.push(CollectionItem::Folder(group.clone()));
-            if self.expanded_groups.contains(&group) {
-                for (idx, req) in self.collections.requests.iter().enumerate() {
-                    let req_group = req.group.as_deref().unwrap_or("UNGROUPED");
-                    if req_group == group {
-                        items.push(CollectionItem::Request(idx));
-                    }
-                }
+    fn push_folder_items(&self, folders: &[String], parent: Option<&str>, items: &mut Vec<CollectionItem>) {
+        for folder in folders.iter().filter(|f| crate::collections::parent_folder(f) == parent) {
+            items.push(CollectionItem::Folder(folder.clone()));
+            if self.collections.tree.expanded.contains(folder) {
+                self.push_folder_items(folders, Some(folder), items);
+                items.extend(self.collections.requests.iter().enumerate().filter(|(_, r)| r.group.as_deref() == Some(folder.as_str())).map(|(idx, _)| CollectionItem::Request(idx)));
             }
         }
-        items
     }
 
     pub fn handle_mouse(&mut self, mouse: MouseEvent, _w: u16, _h: u16) {
@@ -185,10 +681,13 @@ impl<'a> App<'a> {
                     let rx = x.saturating_sub(self.headers_rect.x + 1); let ry = y.saturating_sub(self.headers_rect.y + 1);
                     self.current_tab_mut().headers_area.move_cursor(CursorMove::Jump(ry, rx));
                 } else if self.body_rect.contains(ratatui::layout::Position { x, y }) {
-                    self.active_panel = ActivePanel::Editor; self.current_tab_mut().editor_focus = EditorFocus::Body;
+                    // El hueco del body también muestra los scripts cuando están enfocados
+                    self.active_panel = ActivePanel::Editor;
+                    let t = self.current_tab_mut();
+                    if !matches!(t.editor_focus, EditorFocus::Params | EditorFocus::Variables | EditorFocus::Auth | EditorFocus::PreScript | EditorFocus::PostScript | EditorFocus::Tests | EditorFocus::Description) { t.editor_focus = EditorFocus::Body; }
                     self.input_mode = true;
                     let rx = x.saturating_sub(self.body_rect.x + 1); let ry = y.saturating_sub(self.body_rect.y + 1);
-                    self.current_tab_mut().body_area.move_cursor(CursorMove::Jump(ry, rx));
+                    if let Some(area) = self.current_tab_mut().focused_area_mut() { area.move_cursor(CursorMove::Jump(ry, rx)); }
                 } else if self.attach_rect.contains(ratatui::layout::Position { x, y }) {
                     self.active_panel = ActivePanel::Editor; self.current_tab_mut().editor_focus = EditorFocus::Attachment;
                     self.open_file_picker();
@@ -226,6 +725,33 @@ impl<'a> App<'a> {
             }
             return;
         }
+        if self.show_runner {
+            self.handle_runner_key(key);
+            return;
+        }
+        if self.code_export.is_some() {
+            self.handle_code_export_key(key);
+            return;
+        }
+        if let Some(folder) = self.folder_auth_target.clone() {
+            match key.code {
+                KeyCode::Esc => self.folder_auth_target = None,
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    match Auth::parse(&self.folder_auth_input.lines().join("\n")) {
+                        Ok(auth) => {
+                            self.ai_response = match self.collections.set_folder_auth(&folder, auth) {
+                                Ok(()) => format!("SYSTEM: Auth saved for folder {}.", folder),
+                                Err(e) => format!("SYSTEM ERROR: {}", e),
+                            };
+                            self.folder_auth_target = None;
+                        }
+                        Err(e) => self.ai_response = format!("SYSTEM ERROR: {}", e),
+                    }
+                }
+                _ => { self.folder_auth_input.input(key); }
+            }
+            return;
+        }
         if self.show_import_menu {
             match key.code {
                 KeyCode::Esc => self.show_import_menu = false,
@@ -243,15 +769,25 @@ impl<'a> App<'a> {
                     let new_name = self.rename_input.lines()[0].trim().to_string();
                     if !new_name.is_empty() && matches!(self.left_panel_tab, LeftPanelTab::Collections) {
                         let visible = self.get_visible_items();
-                        if let Some(CollectionItem::Request(real_idx)) = visible.get(self.selected_idx) {
-                            if let Some(req) = self.collections.requests.get(*real_idx).cloned() {
-                                let _ = self.collections.delete_request(*real_idx);
-                                let mut updated_req = req;
-                                updated_req.name = new_name;
-                                let _ = self.collections.save_request(&updated_req);
-                                let _ = self.collections.load_all();
-                                self.ai_response = "SYSTEM: Request renamed.".to_string();
+                        if let Some(CollectionItem::Folder(path)) = visible.get(self.selected_idx) {
+                            let leaf = crate::collections::folder_segment(&new_name);
+                            let to = match crate::collections::parent_folder(path) { Some(parent) => format!("{}/{}", parent, leaf), None => leaf };
+                            match self.collections.move_folder(path, &to) {
+                                Ok(()) => { self.sync_tabs_with_collections(); self.ai_response = format!("SYSTEM: Folder renamed to {}.", to); }
+                                Err(e) => { self.ai_response = format!("SYSTEM ERROR: {}", e); return; }
+                            }
+                        } else if let Some(CollectionItem::Request(real_idx)) = visible.get(self.selected_idx)
+                            && let Some(req) = self.collections.requests.get(*real_idx).cloned()
+                        {
+                            if self.collections.find_in_folder(&new_name, req.group.as_deref()).is_some_and(|i| i != *real_idx) {
+                                self.ai_response = format!("SYSTEM ERROR: A request named '{}' already exists in this folder.", new_name);
+                                return;
                             }
+                            // Mismo `id`: el archivo se mueve al nuevo slug en vez de borrarse y recrearse
+                            if let Err(e) = self.collections.save_request(&mut ApiRequest { name: new_name, ..req }) { self.ai_response = format!("SYSTEM ERROR: {}", e); return; }
+                            let _ = self.collections.load_all();
+                            self.sync_tabs_with_collections();
+                            self.ai_response = "SYSTEM: Request renamed.".to_string();
                         }
                     }
                     self.show_rename_input = false;
@@ -260,6 +796,106 @@ impl<'a> App<'a> {
             }
             return; // Bloqueo total de comandos globales
         }
+        if self.show_save_as {
+            match key.code {
+                KeyCode::Esc => self.show_save_as = false,
+                KeyCode::Tab | KeyCode::BackTab => self.save_as_focus_folder = !self.save_as_focus_folder,
+                KeyCode::Up | KeyCode::Down if self.save_as_focus_folder => {
+                    let next = self.next_folder(self.save_as_folder.lines()[0].trim(), key.code == KeyCode::Down);
+                    self.save_as_folder = TextArea::default();
+                    self.save_as_folder.insert_str(next);
+                }
+                KeyCode::Enter => {
+                    let name = self.save_as_name.lines()[0].trim().to_string();
+                    let folder = self.save_as_folder.lines()[0].trim().to_string();
+                    if name.is_empty() { self.ai_response = "SYSTEM ERROR: Request name is empty.".to_string(); return; }
+                    if self.write_request(name, crate::collections::normalize_folder(&folder)) { self.show_save_as = false; }
+                }
+                _ if self.save_as_focus_folder => { self.save_as_folder.input(key); }
+                _ => { self.save_as_name.input(key); }
+            }
+            return;
+        }
+        if self.show_workspace_picker {
+            if self.workspace_adding {
+                match key.code {
+                    KeyCode::Esc => self.workspace_adding = false,
+                    KeyCode::Enter => {
+                        let path = self.workspace_input.lines()[0].trim().to_string();
+                        if !path.is_empty() && self.switch_workspace(PathBuf::from(path)) { self.show_workspace_picker = false; }
+                        self.workspace_adding = false;
+                    }
+                    _ => { self.workspace_input.input(key); }
+                }
+                return;
+            }
+            let entries = self.workspace_entries();
+            match key.code {
+                KeyCode::Esc => self.show_workspace_picker = false,
+                KeyCode::Up => self.workspace_selected = self.workspace_selected.saturating_sub(1),
+                KeyCode::Down => self.workspace_selected = (self.workspace_selected + 1).min(entries.len().saturating_sub(1)),
+                KeyCode::Enter => {
+                    if let Some((_, path)) = entries.get(self.workspace_selected)
+                        && self.switch_workspace(path.clone())
+                    {
+                        self.show_workspace_picker = false;
+                    }
+                }
+                KeyCode::Char('a') => { self.workspace_input = TextArea::default(); self.workspace_adding = true; }
+                // Sólo se olvida el registro; el directorio no se toca
+                KeyCode::Char('d') => {
+                    if let Some((name, path)) = entries.get(self.workspace_selected) {
+                        if self.is_current_workspace(path) {
+                            self.ai_response = "SYSTEM ERROR: Cannot forget the open workspace.".to_string();
+                        } else {
+                            self.config.workspaces.remove(name);
+                            let _ = self.config.save();
+                            self.workspace_selected = self.workspace_selected.saturating_sub(1);
+                            self.ai_response = format!("SYSTEM: Workspace {} forgotten (files kept in {}).", name, path.display());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        if self.show_history_filter {
+            match key.code {
+                KeyCode::Esc => self.show_history_filter = false,
+                KeyCode::Enter => {
+                    match HistoryFilter::parse(&self.history_filter_input.lines()[0], Local::now().date_naive()) {
+                        Ok(filter) => {
+                            self.history_filter = filter;
+                            self.show_history_filter = false;
+                            self.selected_idx = 0;
+                            self.ai_response = format!("SYSTEM: {} of {} history entries shown.", self.visible_history().len(), self.collections.history.entries.len());
+                        }
+                        Err(e) => self.ai_response = format!("SYSTEM ERROR: {}", e),
+                    }
+                }
+                _ => { self.history_filter_input.input(key); }
+            }
+            return;
+        }
+        if let Some(prompt) = self.folder_prompt.clone() {
+            match key.code {
+                KeyCode::Esc => self.folder_prompt = None,
+                KeyCode::Up | KeyCode::Down if !matches!(prompt, FolderPrompt::NewFolder(_)) => {
+                    let next = self.next_folder(self.folder_prompt_input.lines()[0].trim(), key.code == KeyCode::Down);
+                    self.folder_prompt_input = TextArea::default();
+                    self.folder_prompt_input.insert_str(next);
+                }
+                KeyCode::Enter => {
+                    let input = self.folder_prompt_input.lines()[0].clone();
+                    match self.apply_folder_prompt(prompt, &input) {
+                        Ok(msg) => { self.ai_response = msg; self.folder_prompt = None; self.sync_tabs_with_collections(); }
+                        Err(e) => self.ai_response = format!("SYSTEM ERROR: {}", e),
+                    }
+                }
+                _ => { self.folder_prompt_input.input(key); }
+            }
+            return;
+        }
         if self.show_swagger_input {
             match key.code {
                 KeyCode::Esc => { self.show_swagger_input = false; }
@@ -275,8 +911,10 @@ impl<'a> App<'a> {
                     let key_str = self.key_input.lines()[0].trim().to_string();
                     if !key_str.is_empty() {
                         self.config.gemini_api_key = Some(key_str);
-                        let _ = self.config.save();
-                        self.ai_response = "SYSTEM: Gemini API Key updated.".to_string();
+                        self.ai_response = match self.config.save() {
+                            Ok(()) => "SYSTEM: Gemini API Key updated.".to_string(),
+                            Err(e) => format!("SYSTEM ERROR: Gemini API Key set for this session only: {}", e),
+                        };
                     }
                     self.show_key_input = false;
                 }
@@ -285,9 +923,27 @@ impl<'a> App<'a> {
             return; // Bloqueo total de comandos globales
         }
         if self.show_file_picker {
+            if matches!(self.current_import_type, ImportType::SaveResponse) {
+                match key.code {
+                    KeyCode::Esc => { self.show_file_picker = false; self.current_import_type = ImportType::None; return; }
+                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let name = self.save_file_name.lines()[0].trim().to_string();
+                        if name.is_empty() { self.ai_response = "SYSTEM ERROR: Type a file name first.".to_string(); } else { self.save_response_body(self.current_dir.join(name)); }
+                        return;
+                    }
+                    KeyCode::Enter => {
+                        let entry = self.file_picker_state.selected().and_then(|i| self.file_entries.get(i)).cloned().unwrap_or_default();
+                        let path = self.current_dir.join(&entry);
+                        // Enter sobre un archivo lo sobrescribe; sobre carpetas navega como siempre
+                        if entry != ".." && path.is_file() { self.save_response_body(path); return; }
+                    }
+                    KeyCode::Up | KeyCode::Down => {}
+                    _ => { self.save_file_name.input(key); return; }
+                }
+            }
             match key.code {
                 KeyCode::Up => { let i = match self.file_picker_state.selected() { Some(i) => if i > 0 { i - 1 } else { self.file_entries.len() - 1 }, None => 0 }; self.file_picker_state.select(Some(i)); }
-                KeyCode::Down => { let i = match self.file_picker_state.selected() { Some(i) => if i < self.file_entries.len() - 1 { i + 1 } else { 0 }, None => 0 }; self.file_picker_state.select(Some(i)); }
+                KeyCode::Down => { let i = match self.file_picker_state.selected() { Some(i) if i + 1 < self.file_entries.len() => i + 1, _ => 0 }; self.file_picker_state.select(Some(i)); }
                 KeyCode::Enter => {
                     if let Some(i) = self.file_picker_state.selected() {
                         let entry = self.file_entries[i].clone();
@@ -313,7 +969,14 @@ impl<'a> App<'a> {
                                     self.show_file_picker = false;
                                     self.current_import_type = ImportType::None;
                                 } else {
-                                    self.current_tab_mut().file_path = path.to_string_lossy().to_string(); self.show_file_picker = false;
+                                    // En FORM el fichero se añade como una parte más del formulario
+                                    let tab = self.current_tab_mut();
+                                    if tab.body_type == BodyType::Form {
+                                        tab.body_area.move_cursor(CursorMove::Bottom); tab.body_area.move_cursor(CursorMove::End);
+                                        let sep = if tab.body_area.lines().iter().all(|l| l.is_empty()) { "" } else { "\n" };
+                                        tab.body_area.insert_str(format!("{}file=@{}", sep, path.to_string_lossy()));
+                                    } else { tab.file_path = path.to_string_lossy().to_string(); }
+                                    self.show_file_picker = false;
                                 }
                             }
                         }
@@ -327,15 +990,18 @@ impl<'a> App<'a> {
         if key.modifiers.contains(KeyModifiers::CONTROL) {
             match key.code {
                 KeyCode::Char('c') => { self.copy_to_system(); return; }
-                KeyCode::Char('v') => { self.paste_from_pbpaste(); return; }
+                KeyCode::Char('v') => { self.paste_from_clipboard(); return; }
                 KeyCode::Char('p') => { self.import_curl(); return; }
                 KeyCode::Char('i') => { self.input_mode = false; self.show_import_menu = true; return; }
                 KeyCode::Char('d') => { 
-                    let _ = Command::new("open").arg("https://arthema.co").spawn();
-                    self.ai_response = "SYSTEM: Opening donation page... Thank you for your support! 🦾".to_string();
+                    self.ai_response = match self.opener.open("https://arthema.co") {
+                        Ok(()) => "SYSTEM: Opening donation page... Thank you for your support! 🦾".to_string(),
+                        Err(e) => format!("SYSTEM ERROR: Cannot open https://arthema.co ({})", e),
+                    };
                     return; 
                 }
                 KeyCode::Char('z') => { self.undo_active(); return; }
+                KeyCode::Char('x') => { self.stop_request(); return; }
                 KeyCode::Char('t') => { self.new_tab(); return; }
                 KeyCode::Char('w') => { self.handle_delete(); return; } // Ctrl+W también borra pestaña
                 _ => {}
@@ -344,42 +1010,77 @@ impl<'a> App<'a> {
         if self.input_mode {
             if key.code == KeyCode::Esc { self.input_mode = false; return; }
             if key.code == KeyCode::Enter && self.current_tab().editor_focus == EditorFocus::Url { self.input_mode = false; self.send_request(); return; }
-            let tab = self.current_tab_mut();
-            match tab.editor_focus {
-                EditorFocus::Url => { tab.url_area.input(key); }
-                EditorFocus::Headers => { tab.headers_area.input(key); }
-                EditorFocus::Body => { tab.body_area.input(key); }
-                _ => {}
+            let t = self.current_tab_mut();
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('o') { t.toggle_current_row(); return; }
+            if key.code == KeyCode::Tab && t.editor_focus == EditorFocus::Headers && t.complete_header() { return; }
+            if key.code == KeyCode::Tab && t.editor_focus == EditorFocus::Body && t.body_type == BodyType::GraphQl && t.complete_graphql() { return; }
+            // Sólo se sincroniza si la tecla editó algo (mover el cursor no reescribe el otro editor)
+            if t.focused_area_mut().is_some_and(|area| area.input(key)) {
+                match t.editor_focus { EditorFocus::Url => t.sync_params_from_url(), EditorFocus::Params => t.sync_url_from_params(), _ => {} }
             }
             return; // BLOQUEO DEFINITIVO: Si estamos en modo input, no se procesa nada más
         }
+        if self.active_panel == ActivePanel::Response {
+            if self.handle_tree_key(key) { return; }
+            let view = match key.code { KeyCode::Char('1') => Some(ResponseView::Body), KeyCode::Char('2') => Some(ResponseView::Headers), KeyCode::Char('3') => Some(ResponseView::Timing), _ => None };
+            if let Some(v) = view { let t = self.current_tab_mut(); t.response_view = v; t.response_scroll = 0; return; }
+        }
+        if key.code != KeyCode::Char('d') { self.pending_folder_delete = None; }
         match key.code {
             KeyCode::Char('i') => self.input_mode = true,
             KeyCode::Char('h') => self.toggle_left_panel(),
             KeyCode::Char('d') => self.handle_delete(),
-            KeyCode::Char('b') => self.cycle_body_type(),
-            KeyCode::Char('t') => { let t = self.current_tab_mut(); t.is_tree_mode = !t.is_tree_mode; }
+            KeyCode::Char('b') => self.cycle_body_type(true),
+            KeyCode::Char('B') => self.cycle_body_type(false),
+            KeyCode::Char('F') => {
+                if let Err(e) = self.current_tab_mut().format_body() { self.ai_response = format!("SYSTEM ERROR: {}", e); }
+            }
+            KeyCode::Char('G') => self.fetch_graphql_schema(),
+            KeyCode::Char('O') => {
+                let t = self.current_tab_mut();
+                t.cycle_graphql_operation();
+                self.ai_response = format!("SYSTEM: GraphQL operation: {}", self.current_tab().graphql_operation.as_deref().unwrap_or("(none)"));
+            }
+            KeyCode::Char('t') => self.toggle_tree_mode(),
             KeyCode::Char('m') => self.cycle_method(true),
             KeyCode::Char('M') => self.cycle_method(false),
             KeyCode::Char('f') => self.cycle_editor_focus(),
             KeyCode::Char('s') => self.save_current_request(),
+            KeyCode::Char('S') => self.open_save_as(),
+            KeyCode::Char('W') => {
+                self.input_mode = false;
+                self.workspace_selected = self.workspace_entries().iter().position(|(_, p)| self.is_current_workspace(p)).unwrap_or(0);
+                self.workspace_adding = false;
+                self.show_workspace_picker = true;
+            }
+            KeyCode::Char('N') => self.new_folder(),
+            KeyCode::Char('X') => self.move_selected(),
+            KeyCode::Char('D') => self.duplicate_selected(),
             KeyCode::Char('r') => {
                 if matches!(self.left_panel_tab, LeftPanelTab::Collections) {
                     let visible = self.get_visible_items();
-                    if let Some(CollectionItem::Request(real_idx)) = visible.get(self.selected_idx) {
-                        if let Some(req) = self.collections.requests.get(*real_idx) {
-                            self.input_mode = false;
-                            self.rename_input = TextArea::default();
-                            self.rename_input.insert_str(&req.name);
-                            self.show_rename_input = true;
-                            self.active_panel = ActivePanel::Collections;
-                        }
+                    let current = match visible.get(self.selected_idx) {
+                        Some(CollectionItem::Request(real_idx)) => self.collections.requests.get(*real_idx).map(|r| r.name.clone()),
+                        Some(CollectionItem::Folder(path)) if path != "UNGROUPED" => Some(crate::collections::folder_leaf(path).to_string()),
+                        _ => None,
+                    };
+                    if let Some(current) = current {
+                        self.input_mode = false;
+                        self.rename_input = TextArea::default();
+                        self.rename_input.insert_str(current);
+                        self.show_rename_input = true;
+                        self.active_panel = ActivePanel::Collections;
                     }
                 }
             },
             KeyCode::Char('n') => self.next_tab(),
+            KeyCode::Char('R') => self.run_selected_folder(),
+            KeyCode::Char('A') => self.edit_folder_auth(),
+            KeyCode::Char('v') => self.cycle_environment(),
             KeyCode::Char('o') => self.open_in_system(),
+            KeyCode::Char('w') => self.open_save_response(),
             KeyCode::Char('c') => self.copy_to_system(),
+            KeyCode::Char('y') => self.open_code_export(),
             KeyCode::Char('k') => { 
                 self.input_mode = false;
                 self.show_key_input = true; 
@@ -387,6 +1088,15 @@ impl<'a> App<'a> {
                 if let Some(key) = &self.config.gemini_api_key { self.key_input.insert_str(key); }
             },
             KeyCode::Char('?') => { self.input_mode = false; self.show_help = true; },
+            KeyCode::Char('/') => {
+                if matches!(self.left_panel_tab, LeftPanelTab::History) {
+                    self.input_mode = false;
+                    self.history_filter_input = TextArea::default();
+                    self.history_filter_input.insert_str(&self.history_filter.query);
+                    self.show_history_filter = true;
+                    self.active_panel = ActivePanel::Collections;
+                }
+            },
             KeyCode::Char('g') => {
                 self.input_mode = false;
                 self.show_swagger_input = true;
@@ -412,17 +1122,198 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Carpeta del elemento seleccionado en Collections (la propia o la de su petición)
+    fn selected_folder(&self) -> Option<String> {
+        if !matches!(self.left_panel_tab, LeftPanelTab::Collections) { return None; }
+        match self.get_visible_items().get(self.selected_idx)? {
+            CollectionItem::Folder(name) => Some(name.clone()),
+            CollectionItem::Request(idx) => Some(self.collections.requests.get(*idx)?.group.clone().unwrap_or_else(|| "UNGROUPED".to_string())),
+        }
+    }
+
+    /// `R` en Collections ejecuta la carpeta seleccionada; en otro panel reabre el último runner
+    fn run_selected_folder(&mut self) {
+        let folder = if self.active_panel == ActivePanel::Collections { self.selected_folder() } else { None };
+        match folder {
+            Some(folder) => self.start_runner(folder),
+            None if self.runner.is_some() => self.show_runner = true,
+            None => self.ai_response = "SYSTEM: Select a folder in Collections and press R to run it.".to_string(),
+        }
+    }
+
+    fn edit_folder_auth(&mut self) {
+        let Some(folder) = (if self.active_panel == ActivePanel::Collections { self.selected_folder() } else { None }) else {
+            self.ai_response = "SYSTEM: Select a folder in Collections and press A to edit its auth.".to_string();
+            return;
+        };
+        self.folder_auth_input = TextArea::default();
+        self.folder_auth_input.insert_str(self.collections.folder_auth.get(&folder).map(Auth::to_text).unwrap_or_default());
+        self.folder_auth_target = Some(folder);
+        self.input_mode = false;
+    }
+
+    /// Auth de la pestaña ya resuelta contra su carpeta
+    fn tab_auth(&self) -> Result<Auth, String> {
+        let t = self.current_tab();
+        Ok(crate::auth::resolve(&t.auth()?, crate::collections::folder_auth_for(&self.collections.folder_auth, t.group.as_deref()).map(|(_, a)| a)))
+    }
+
+    fn start_runner(&mut self, folder: String) {
+        let requests = crate::runner::filter_group(self.collections.requests.clone(), Some(&folder));
+        let requests = crate::runner::resolve_folder_auth(requests, &self.collections.folder_auth);
+        if requests.is_empty() { self.ai_response = format!("SYSTEM: Folder {} has no requests.", folder); return; }
+        let id = self.runner.as_ref().map(|r| r.id + 1).unwrap_or(1);
+        let concurrency = self.runner_concurrency;
+        let mut run = CollectionRun::new(id, folder, requests.clone(), concurrency);
+        let tx = self.tx.clone();
+        let vars = self.environments.variables();
+        let (tokens, opener) = (self.tokens.clone(), self.opener.clone());
+        run.task = Some(tokio::spawn(async move {
+            crate::runner::run_all(requests, vars, concurrency, &tokens, &opener, |index, run| { let _ = tx.send(AppEvent::RunnerResult(id, index, Box::new(run))); }).await;
+        }).abort_handle());
+        // Soltar la ejecución anterior la cancela: sus resultados no se mezclan con los nuevos
+        self.runner = Some(run);
+        self.show_runner = true;
+        self.input_mode = false;
+    }
+
+    fn handle_runner_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('R') => self.show_runner = false,
+            KeyCode::Up => { if let Some(cr) = self.runner.as_mut() { cr.move_selection(-1); } }
+            KeyCode::Down => { if let Some(cr) = self.runner.as_mut() { cr.move_selection(1); } }
+            KeyCode::Char('+') => self.runner_concurrency = (self.runner_concurrency + 1).min(16),
+            KeyCode::Char('-') => self.runner_concurrency = self.runner_concurrency.saturating_sub(1).max(1),
+            KeyCode::Char('r') => { if let Some(folder) = self.runner.as_ref().map(|cr| cr.folder.clone()) { self.start_runner(folder); } }
+            KeyCode::Enter => self.open_runner_result(),
+            _ => {}
+        }
+    }
+
+    /// Abre la petición seleccionada del runner en una pestaña nueva con su respuesta
+    fn open_runner_result(&mut self) {
+        let Some(row) = self.runner.as_ref().and_then(|cr| cr.selected_row()) else { return };
+        let Some(result) = row.result.as_ref() else { self.ai_response = "SYSTEM: Request still running.".to_string(); return };
+        let mut tab = RequestTab::new(row.request.name.clone());
+        tab.load_request(&row.request);
+        if let Some(resp) = result.response.clone() { tab.show_response(resp); }
+        else if let Some(e) = &result.error { tab.response = format!("ERROR: {}", e); }
+        tab.test_results = result.assertions.clone();
+        self.tabs.push(tab);
+        self.active_tab = self.tabs.len() - 1;
+        self.show_runner = false;
+        self.active_panel = ActivePanel::Response;
+    }
+
+    /// Exporta la petición seleccionada en Collections/History o, si no, la pestaña activa
+    fn open_code_export(&mut self) {
+        let saved = if self.active_panel != ActivePanel::Collections { None } else {
+            match self.left_panel_tab {
+                LeftPanelTab::Collections => match self.get_visible_items().get(self.selected_idx) {
+                    Some(CollectionItem::Request(idx)) => self.collections.requests.get(*idx).cloned(),
+                    _ => None,
+                },
+                LeftPanelTab::History => self.selected_history().map(|e| e.request.clone()),
+            }
+        };
+        let req = match saved {
+            Some(r) => { let mut p = PreparedRequest::from_api_request(&r); p.auth = self.collections.effective_auth(&r); p }
+            None => { let mut p = self.current_tab().to_prepared(); p.auth = self.tab_auth().unwrap_or_default(); p }
+        };
+        self.input_mode = false;
+        self.code_resolved = false;
+        self.code_export = Some(req);
+    }
+
+    /// Snippet del exportador. Por defecto deja los `{{var}}` tal cual para no copiar tokens ni
+    /// contraseñas del entorno activo; con `code_resolved` sustituye las variables
+    pub fn code_snippet(&self) -> Option<String> {
+        let mut req = self.code_export.clone()?;
+        if self.code_resolved { req.substitute(&self.environments.variables()); }
+        // Digest y OAuth2 necesitan red: el snippet sale sin ellas
+        crate::auth::apply_static(&req.auth.clone(), &mut req);
+        Some(crate::codegen::generate(self.code_language, &req))
+    }
+
+    fn handle_code_export_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('y') => self.code_export = None,
+            KeyCode::Right | KeyCode::Tab => self.code_language = self.code_language.cycle(true),
+            KeyCode::Left | KeyCode::BackTab => self.code_language = self.code_language.cycle(false),
+            KeyCode::Char(c @ '1'..='6') => self.code_language = Language::ALL[c as usize - '1' as usize],
+            KeyCode::Char('r') => self.code_resolved = !self.code_resolved,
+            KeyCode::Enter | KeyCode::Char('c') => {
+                let Some(code) = self.code_snippet() else { return };
+                self.code_export = None;
+                if self.copy_text(&code) {
+                    self.ai_response = format!("SYSTEM: {} snippet copied ({}).", self.code_language.label(), self.clipboard.name());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_tree_mode(&mut self) {
+        let t = self.current_tab_mut();
+        if !t.is_tree_mode && t.json_tree.is_none() {
+            self.ai_response = "SYSTEM: Tree view needs a JSON response.".to_string();
+            return;
+        }
+        t.is_tree_mode = !t.is_tree_mode;
+        if t.is_tree_mode { self.active_panel = ActivePanel::Response; }
+    }
+
+    /// Navegación del árbol JSON. Devuelve `true` si la tecla fue consumida.
+    fn handle_tree_key(&mut self, key: KeyEvent) -> bool {
+        let t = self.current_tab_mut();
+        if !t.is_tree_mode { return false; }
+        if t.response_view != ResponseView::Body { return false; }
+        let Some(tree) = t.json_tree.as_mut() else { return false };
+        match key.code {
+            KeyCode::Up => tree.move_selection(-1),
+            KeyCode::Down => tree.move_selection(1),
+            KeyCode::PageUp => tree.move_selection(-20),
+            KeyCode::PageDown => tree.move_selection(20),
+            KeyCode::Right => tree.expand(),
+            KeyCode::Left => tree.collapse(),
+            KeyCode::Enter | KeyCode::Char(' ') => tree.toggle(),
+            KeyCode::Backspace => tree.jump_to_parent(),
+            KeyCode::Char(']') => tree.jump_sibling(true),
+            KeyCode::Char('[') => tree.jump_sibling(false),
+            _ => return false,
+        }
+        true
+    }
+
     fn handle_delete(&mut self) {
         match self.active_panel {
             ActivePanel::Collections => {
                 match self.left_panel_tab {
                     LeftPanelTab::Collections => {
                         let visible = self.get_visible_items();
-                        if let Some(CollectionItem::Request(real_idx)) = visible.get(self.selected_idx) {
-                            let _ = self.collections.delete_request(*real_idx);
+                        match visible.get(self.selected_idx) {
+                            Some(CollectionItem::Request(real_idx)) => { let _ = self.collections.delete_request(*real_idx); }
+                            // Una carpeta se lleva todo su contenido: pide un segundo `d`
+                            Some(CollectionItem::Folder(path)) if path != "UNGROUPED" => {
+                                if self.pending_folder_delete.take().as_ref() != Some(path) {
+                                    let count = self.collections.requests.iter().filter(|r| r.group.as_deref().is_some_and(|g| crate::collections::in_folder(g, path))).count();
+                                    self.ai_response = format!("SYSTEM: Press d again to delete folder {} and its {} request(s).", path, count);
+                                    self.pending_folder_delete = Some(path.clone());
+                                    return;
+                                }
+                                if let Err(e) = self.collections.delete_folder(path) { self.ai_response = format!("SYSTEM ERROR: {}", e); return; }
+                            }
+                            _ => {}
+                        }
+                        // Las pestañas de lo borrado quedan sin origen: el próximo `s` pedirá nombre
+                        self.sync_tabs_with_collections();
+                    }
+                    LeftPanelTab::History => {
+                        if let Some(idx) = self.visible_history().get(self.selected_idx).copied() && let Err(e) = self.collections.history.remove(idx) {
+                            self.ai_response = format!("SYSTEM ERROR: {}", e);
+                            return;
                         }
                     }
-                    LeftPanelTab::History => { self.collections.delete_history_item(self.selected_idx); }
                 }
                 if self.selected_idx > 0 { self.selected_idx -= 1; }
                 self.ai_response = "SYSTEM: Item deleted.".to_string();
@@ -454,14 +1345,48 @@ impl<'a> App<'a> {
         self.file_picker_state.select(Some(0));
     }
 
-    fn cycle_body_type(&mut self) {
+    fn cycle_body_type(&mut self, forward: bool) {
         let t = self.current_tab_mut();
-        t.body_type = match t.body_type { BodyType::Json => BodyType::Text, BodyType::Text => BodyType::Form, BodyType::Form => BodyType::Json };
-        match t.body_type {
-            BodyType::Json => { t.headers_area = TextArea::default(); t.headers_area.insert_str("Content-Type: application/json"); }
-            BodyType::Text => { t.headers_area = TextArea::default(); t.headers_area.insert_str("Content-Type: text/plain"); }
-            BodyType::Form => { t.headers_area = TextArea::default(); t.headers_area.insert_str("Content-Type: multipart/form-data"); }
+        t.body_type = t.body_type.cycle(forward);
+        // RAW conserva el Content-Type que haya escrito el usuario
+        if let Some(content_type) = t.body_type.content_type() { t.set_content_type(content_type); }
+        if t.editor_focus == EditorFocus::Variables && t.body_type != BodyType::GraphQl { t.editor_focus = EditorFocus::Body; }
+    }
+
+    /// `G`: lanza la introspección contra la URL de la pestaña con sus headers y auth
+    fn fetch_graphql_schema(&mut self) {
+        if self.current_tab().body_type != BodyType::GraphQl { self.ai_response = "SYSTEM: Switch the body to GQL (b) to load a GraphQL schema.".to_string(); return; }
+        let auth = match self.tab_auth() {
+            Ok(a) => a,
+            Err(e) => { self.ai_response = format!("SYSTEM ERROR: {}", e); return; }
+        };
+        let mut prepared = self.current_tab().to_prepared();
+        prepared.method = "POST".to_string();
+        prepared.body = GraphQlBody { query: crate::graphql::INTROSPECTION_QUERY.to_string(), ..Default::default() }.to_body();
+        prepared.auth = auth;
+        prepared.substitute(&self.environments.variables());
+        let url = self.current_tab().url();
+        let (tx, tokens, opener) = (self.tx.clone(), self.tokens.clone(), self.opener.clone());
+        self.ai_response = "SYSTEM: Loading GraphQL schema...".to_string();
+        tokio::spawn(async move {
+            let schema = match crate::http::send(prepared, &tokens, &opener).await {
+                Ok(resp) => serde_json::from_slice(&resp.body).map_err(|e| format!("introspection response is not JSON: {}", e)).and_then(|json| Schema::from_introspection(&json)),
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = tx.send(AppEvent::GraphQlSchema(url, schema));
+        });
+    }
+
+    fn cycle_environment(&mut self) {
+        self.environments.load_all();
+        if self.environments.environments.is_empty() {
+            self.ai_response = format!("SYSTEM: No environments found. Add JSON files to {}/environments.", self.environments.base_path);
+            if let Some(report) = self.environments.load_report() { self.ai_response = format!("{}\n{}", self.ai_response, report); }
+            return;
         }
+        self.environments.cycle();
+        self.ai_response = format!("SYSTEM: Active environment: {}", self.environments.active_name().unwrap_or("NONE"));
+        if let Some(report) = self.environments.load_report() { self.ai_response = format!("{}\n{}", self.ai_response, report); }
     }
 
     fn toggle_left_panel(&mut self) { self.left_panel_tab = match self.left_panel_tab { LeftPanelTab::Collections => LeftPanelTab::History, LeftPanelTab::History => LeftPanelTab::Collections }; self.selected_idx = 0; self.active_panel = ActivePanel::Collections; }
@@ -469,56 +1394,76 @@ impl<'a> App<'a> {
     fn next_tab(&mut self) { self.active_tab = (self.active_tab + 1) % self.tabs.len(); }
 
     fn undo_active(&mut self) {
-        let tab = self.current_tab_mut();
-        match tab.editor_focus { EditorFocus::Url => { tab.url_area.undo(); }, EditorFocus::Headers => { tab.headers_area.undo(); }, EditorFocus::Body => { tab.body_area.undo(); }, _ => {} }
+        let t = self.current_tab_mut();
+        if let Some(area) = t.focused_area_mut() { area.undo(); }
+        match t.editor_focus { EditorFocus::Url => t.sync_params_from_url(), EditorFocus::Params => t.sync_url_from_params(), _ => {} }
     }
 
     fn copy_to_system(&mut self) {
         let text = match self.active_panel {
-            ActivePanel::Editor => { let tab = self.current_tab(); match tab.editor_focus { EditorFocus::Url => tab.url_area.lines().join("\n"), EditorFocus::Headers => tab.headers_area.lines().join("\n"), EditorFocus::Body => tab.body_area.lines().join("\n"), EditorFocus::Attachment => tab.file_path.clone() } },
-            ActivePanel::Response => { let r = self.current_tab().response.clone(); if let Some(p) = r.find("\n\n") { r[p+2..].to_string() } else { r } },
+            ActivePanel::Editor => { let tab = self.current_tab(); match tab.editor_focus { EditorFocus::Url => tab.url_area.lines().join("\n"), EditorFocus::Params => tab.params_area.lines().join("\n"), EditorFocus::Headers => tab.headers_area.lines().join("\n"), EditorFocus::Body => tab.body_area.lines().join("\n"), EditorFocus::Variables => tab.graphql_vars_area.lines().join("\n"), EditorFocus::Auth => tab.auth_area.lines().join("\n"), EditorFocus::PreScript => tab.pre_script_area.lines().join("\n"), EditorFocus::PostScript => tab.post_script_area.lines().join("\n"), EditorFocus::Tests => tab.tests_area.lines().join("\n"), EditorFocus::Description => tab.description_area.lines().join("\n"), EditorFocus::Attachment => tab.file_path.clone() } },
+            ActivePanel::Response => { let t = self.current_tab(); t.api_response.as_ref().filter(|r| !r.is_binary()).map(|r| r.body_text()).unwrap_or_else(|| t.response.clone()) },
             ActivePanel::AI => self.ai_response.clone(),
             _ => "".to_string(),
         };
         if text.is_empty() { return; }
-        let _ = Command::new("pbcopy").stdin(Stdio::piped()).spawn().and_then(|mut c| { if let Some(mut s) = c.stdin.take() { let _ = s.write_all(text.as_bytes()); } let _ = c.wait(); Ok(()) });
-        self.ai_response = "SYSTEM: Copied.".to_string();
+        if self.copy_text(&text) { self.ai_response = format!("SYSTEM: Copied ({}).", self.clipboard.name()); }
+    }
+
+    /// Copia al portapapeles; si falla lo cuenta en el panel AI y devuelve `false`
+    fn copy_text(&mut self, text: &str) -> bool {
+        match self.clipboard.copy(text) {
+            Ok(()) => true,
+            Err(e) => { self.ai_response = format!("SYSTEM ERROR: Copy failed ({}): {}", self.clipboard.name(), e); false }
+        }
+    }
+
+    fn paste_text(&mut self) -> Option<String> {
+        match self.clipboard.paste() {
+            Ok(t) => Some(t),
+            Err(e) => { self.ai_response = format!("SYSTEM ERROR: Paste failed ({}): {}", self.clipboard.name(), e); None }
+        }
     }
 
-    fn paste_from_pbpaste(&mut self) {
-        if let Ok(o) = Command::new("pbpaste").output() {
-            let t = String::from_utf8_lossy(&o.stdout).to_string();
+    fn paste_from_clipboard(&mut self) {
+        if let Some(t) = self.paste_text() {
             let tab = self.current_tab_mut();
-            match tab.editor_focus { EditorFocus::Url => { tab.url_area.insert_str(t); }, EditorFocus::Headers => { tab.headers_area.insert_str(t); }, EditorFocus::Body => { tab.body_area.insert_str(t); }, EditorFocus::Attachment => tab.file_path = t }
+            match tab.focused_area_mut() { Some(area) => { area.insert_str(t); }, None => tab.file_path = t }
+            match tab.editor_focus { EditorFocus::Url => tab.sync_params_from_url(), EditorFocus::Params => tab.sync_url_from_params(), _ => {} }
             self.input_mode = true;
         }
     }
 
     fn import_curl(&mut self) {
-        if let Ok(o) = Command::new("pbpaste").output() {
-            let t = String::from_utf8_lossy(&o.stdout).to_string();
+        if let Some(t) = self.paste_text() {
             if let Some(parsed) = crate::curl::parse_curl(&t) {
                 let tab = self.current_tab_mut();
                 tab.method = parsed.method;
                 tab.url_area = TextArea::default(); tab.url_area.insert_str(&parsed.url);
+                tab.params_area = TextArea::default(); tab.sync_params_from_url();
                 
-                let mut h_str = String::new();
-                for (k, v) in parsed.headers { h_str.push_str(&format!("{}: {}\n", k, v)); }
-                tab.headers_area = TextArea::default(); tab.headers_area.insert_str(h_str.trim());
+                tab.headers_area = TextArea::default(); tab.headers_area.insert_str(crate::headers::to_text(&parsed.headers));
                 
                 tab.body_area = TextArea::default();
-                if let Some(b) = parsed.body { 
-                    tab.body_area.insert_str(&b); 
-                    if b.starts_with('{') { tab.body_type = BodyType::Json; }
-                }
-                self.ai_response = "SYSTEM: cURL command imported successfully.".to_string();
+                if let Some(b) = parsed.body { tab.body_area.insert_str(&b); }
+                if let Some(form) = parsed.form { tab.body_area.insert_str(form.to_text()); }
+                tab.body_type = parsed.body_type;
+                tab.file_path = parsed.body_file.unwrap_or_default();
+                tab.insecure = parsed.insecure;
+                tab.auth_area = TextArea::default(); tab.auth_area.insert_str(parsed.auth.to_text());
+                // Lo que no se pudo mapear se informa en vez de perderse en silencio
+                self.ai_response = if parsed.warnings.is_empty() {
+                    "SYSTEM: cURL command imported successfully.".to_string()
+                } else {
+                    format!("SYSTEM: cURL imported with {} warning(s):\n{}", parsed.warnings.len(), parsed.warnings.iter().map(|w| format!("  ⚠ {}", w)).collect::<Vec<_>>().join("\n"))
+                };
             } else {
                 self.ai_response = "SYSTEM ERROR: Clipboard does not contain a valid cURL command.".to_string();
             }
         }
     }
 
-    fn cycle_editor_focus(&mut self) { let tab = self.current_tab_mut(); tab.editor_focus = match tab.editor_focus { EditorFocus::Url => EditorFocus::Headers, EditorFocus::Headers => EditorFocus::Body, EditorFocus::Body => EditorFocus::Attachment, EditorFocus::Attachment => EditorFocus::Url }; }
+    fn cycle_editor_focus(&mut self) { let tab = self.current_tab_mut(); tab.editor_focus = match tab.editor_focus { EditorFocus::Url => EditorFocus::Params, EditorFocus::Params => EditorFocus::Headers, EditorFocus::Headers => EditorFocus::Body, EditorFocus::Body if tab.body_type == BodyType::GraphQl => EditorFocus::Variables, EditorFocus::Body | EditorFocus::Variables => EditorFocus::Auth, EditorFocus::Auth => EditorFocus::PreScript, EditorFocus::PreScript => EditorFocus::PostScript, EditorFocus::PostScript => EditorFocus::Tests, EditorFocus::Tests => EditorFocus::Description, EditorFocus::Description => EditorFocus::Attachment, EditorFocus::Attachment => EditorFocus::Url }; }
     fn cycle_method(&mut self, fwd: bool) { let ms = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"]; let c = self.current_tab().method.clone(); let p = ms.iter().position(|&m| m == c).unwrap_or(0) as i32; let n = if fwd { (p + 1).rem_euclid(ms.len() as i32) } else { (p - 1).rem_euclid(ms.len() as i32) }; self.current_tab_mut().method = ms[n as usize].to_string(); }
 
         fn move_selection(&mut self, delta: i32) {
@@ -531,7 +1476,7 @@ impl<'a> App<'a> {
 
                         LeftPanelTab::Collections => self.get_visible_items().len(),
 
-                        LeftPanelTab::History => self.collections.history.len()
+                        LeftPanelTab::History => self.visible_history().len()
 
                     };
 
@@ -547,9 +1492,8 @@ impl<'a> App<'a> {
 
      let t = self.current_tab_mut(); if delta > 0 { t.response_scroll = t.response_scroll.saturating_add(1); } else { t.response_scroll = t.response_scroll.saturating_sub(1); } }
             ActivePanel::Editor => {
-                let tab = self.current_tab_mut();
                 let key = if delta > 0 { KeyEvent::new(KeyCode::Down, KeyModifiers::empty()) } else { KeyEvent::new(KeyCode::Up, KeyModifiers::empty()) };
-                match tab.editor_focus { EditorFocus::Url => { tab.url_area.input(key); }, EditorFocus::Headers => { tab.headers_area.input(key); }, EditorFocus::Body => { tab.body_area.input(key); }, _ => {} };
+                if let Some(area) = self.current_tab_mut().focused_area_mut() { area.input(key); }
             }
             _ => {}
         }
@@ -561,60 +1505,83 @@ impl<'a> App<'a> {
             if let Some(item) = visible.get(self.selected_idx) {
                 match item {
                     CollectionItem::Folder(name) => {
-                        if self.expanded_groups.contains(name) {
-                            self.expanded_groups.remove(name);
-                        } else {
-                            self.expanded_groups.insert(name.clone());
-                        }
+                        let expanded = &mut self.collections.tree.expanded;
+                        if !expanded.remove(name) { expanded.insert(name.clone()); }
+                        let _ = self.collections.save_tree();
                     }
                     CollectionItem::Request(real_idx) => {
                         if let Some(req) = self.collections.requests.get(*real_idx).cloned() {
                             let t = self.current_tab_mut();
-                            t.url_area = TextArea::default(); t.url_area.insert_str(&req.url);
-                            t.headers_area = TextArea::default(); let h = req.headers.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<_>>().join("\n"); t.headers_area.insert_str(h);
-                            t.body_area = TextArea::default(); if let Some(b) = &req.body { t.body_area.insert_str(b); }
-                            t.method = req.method.clone(); self.active_panel = ActivePanel::Editor;
+                            t.load_request(&req);
+                            t.mark_saved(req);
+                            self.active_panel = ActivePanel::Editor;
                         }
                     }
                 }
             }
         } else {
-            // Historial (sigue siendo plano)
-            if let Some(req) = self.collections.history.get(self.selected_idx).cloned() {
+            // Historial (sigue siendo plano): la petición y lo que se guardó de su respuesta
+            if let Some(entry) = self.selected_history().cloned() {
                 let t = self.current_tab_mut();
-                t.url_area = TextArea::default(); t.url_area.insert_str(&req.url);
-                t.headers_area = TextArea::default(); let h = req.headers.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<_>>().join("\n"); t.headers_area.insert_str(h);
-                t.body_area = TextArea::default(); if let Some(b) = &req.body { t.body_area.insert_str(b); }
-                t.method = req.method.clone(); self.active_panel = ActivePanel::Editor;
+                t.load_request(&entry.request);
+                t.api_response = None; t.json_tree = None; t.response_scroll = 0;
+                let env = entry.environment.as_deref().map(|e| format!(" · ENV {}", e)).unwrap_or_default();
+                let truncated = if entry.truncated { format!("\n\n… truncated at {}", crate::response::format_size(crate::history::BODY_LIMIT)) } else { String::new() };
+                t.response = format!("HISTORY · {} · {}{}\n\n{}{}", if entry.timestamp.is_empty() { "undated" } else { &entry.timestamp }, entry.outcome(), env, entry.body, truncated);
+                self.active_panel = ActivePanel::Editor;
             }
         }
     }
 
-        fn open_in_system(&mut self) {
-
-            let tab = self.current_tab();
-
-            if let Some(bytes) = &tab.response_bytes {
-
-                let temp_path = std::env::temp_dir().join("arthema_resp.png");
-
-                if std::fs::write(&temp_path, bytes).is_ok() {
-
-                    let _ = Command::new("open").arg(&temp_path).spawn();
-
-                    self.ai_response = "SYSTEM: Opening image in default viewer...".to_string();
-
-                }
-
-            } else if !tab.file_path.is_empty() {
-
-                let _ = Command::new("open").arg(&tab.file_path).spawn();
+    /// `w`: elige carpeta y nombre en el selector de archivos para guardar el body tal cual
+    fn open_save_response(&mut self) {
+        let Some(resp) = self.current_tab().api_response.as_ref().filter(|r| !r.body.is_empty()) else {
+            self.ai_response = "SYSTEM: No response body to save.".to_string();
+            return;
+        };
+        let name = format!("response.{}", crate::opener::extension_for(resp.mime()));
+        self.input_mode = false;
+        self.save_file_name = TextArea::from([name]);
+        self.save_file_name.move_cursor(CursorMove::End);
+        self.current_import_type = ImportType::SaveResponse;
+        self.open_file_picker();
+    }
 
+    fn save_response_body(&mut self, path: PathBuf) {
+        let Some(resp) = self.current_tab().api_response.as_ref() else { return };
+        match fs::write(&path, &resp.body) {
+            Ok(()) => {
+                self.ai_response = format!("SYSTEM: Saved {} to {}.", crate::response::format_size(resp.body.len()), path.display());
+                self.show_file_picker = false;
+                self.current_import_type = ImportType::None;
             }
-
+            Err(e) => self.ai_response = format!("SYSTEM ERROR: Cannot save to {}: {}", path.display(), e),
         }
+    }
 
-    
+    /// `o`: abre la respuesta con la aplicación del sistema (extensión según su Content-Type)
+    /// o, con el foco en Attachment, el archivo adjunto
+    fn open_in_system(&mut self) {
+        let t = self.current_tab_mut();
+        let target = if t.editor_focus == EditorFocus::Attachment && !t.file_path.is_empty() { Ok(t.file_path.clone()) } else {
+            match t.api_response.as_ref().filter(|r| !r.body.is_empty()) {
+                None if t.file_path.is_empty() => { self.ai_response = "SYSTEM: Nothing to open (no response body or attachment).".to_string(); return; }
+                None => Ok(t.file_path.clone()),
+                Some(resp) => match &t.response_file {
+                    Some(file) if file.path().is_file() => Ok(file.path().to_string_lossy().to_string()),
+                    _ => TempFile::write(&resp.body, crate::opener::extension_for(resp.mime())).map(|file| {
+                        let path = file.path().to_string_lossy().to_string();
+                        t.response_file = Some(file);
+                        path
+                    }),
+                },
+            }
+        };
+        self.ai_response = match target.and_then(|path| self.opener.open(&path).map(|()| path)) {
+            Ok(path) => format!("SYSTEM: Opening {} in the default application...", path),
+            Err(e) => format!("SYSTEM ERROR: Cannot open in the system viewer ({})", e),
+        };
+    }
 
         fn import_swagger(&mut self) {
         let url = self.swagger_input.lines()[0].trim().to_string();
@@ -642,106 +1609,413 @@ impl<'a> App<'a> {
         self.active_panel = match self.active_panel { ActivePanel::Collections => ActivePanel::Editor, ActivePanel::Editor => ActivePanel::Response, ActivePanel::Response => ActivePanel::AI, _ => ActivePanel::Collections };
     }
 
+    /// Workspaces del selector: los registrados y el abierto aunque no lo esté
+    pub fn workspace_entries(&self) -> Vec<(String, PathBuf)> {
+        let mut entries: Vec<(String, PathBuf)> = self.config.workspaces.iter().map(|(n, p)| (n.clone(), p.clone())).collect();
+        if !entries.iter().any(|(_, p)| self.is_current_workspace(p)) {
+            let name = self.collections.manifest.as_ref().map(|m| m.name.clone()).unwrap_or_else(|| self.collections.base_path.clone());
+            entries.insert(0, (name, PathBuf::from(&self.collections.base_path)));
+        }
+        entries
+    }
+
+    pub fn is_current_workspace(&self, path: &std::path::Path) -> bool {
+        let canonical = |p: &std::path::Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        canonical(path) == canonical(std::path::Path::new(&self.collections.base_path))
+    }
+
+    /// Abre otro workspace (lo crea si el directorio está vacío) con sus colecciones, entornos,
+    /// historial y tokens. Las pestañas abiertas se quedan, pero sin origen: guardarlas pide nombre.
+    pub fn switch_workspace(&mut self, path: PathBuf) -> bool {
+        let mut collections = CollectionManager::open(&path.to_string_lossy());
+        let _ = collections.history.apply_retention(self.config.history, Local::now());
+        let Some(manifest) = collections.manifest.clone() else {
+            self.ai_response = collections.load_report().unwrap_or_else(|| format!("SYSTEM ERROR: Cannot open workspace {}.", path.display()));
+            return false;
+        };
+        let name = crate::workspace::register(&mut self.config, &path, &manifest.name);
+        let _ = self.config.save();
+        self.environments = EnvironmentManager::new(&collections.base_path);
+        self.tokens = TokenStore::new(&collections.base_path);
+        let report = collections.load_report().into_iter().chain(self.environments.load_report()).reduce(|a, b| format!("{}\n{}", a, b));
+        self.collections = collections;
+        for t in &mut self.tabs { t.source = None; }
+        self.selected_idx = 0;
+        self.history_filter = HistoryFilter::default();
+        self.ai_response = format!("SYSTEM: Workspace {} ({}).", name, path.display());
+        if let Some(report) = report { self.ai_response = format!("{}\n{}", self.ai_response, report); }
+        true
+    }
+
+    /// `s`: sobrescribe la petición de origen; sin origen, abre "guardar como"
     fn save_current_request(&mut self) {
+        match self.current_tab().source.clone() {
+            Some(src) => { self.write_request(src.name, src.group); }
+            None => self.open_save_as(),
+        }
+    }
+
+    fn open_save_as(&mut self) {
+        let t = self.current_tab();
+        let (name, folder) = (t.name.clone(), t.group.clone().unwrap_or_default());
+        self.input_mode = false;
+        self.save_as_name = TextArea::default();
+        self.save_as_name.insert_str(&name);
+        self.save_as_folder = TextArea::default();
+        self.save_as_folder.insert_str(&folder);
+        self.save_as_focus_folder = false;
+        self.show_save_as = true;
+    }
+
+    /// Carpeta existente siguiente/anterior a `current` para los campos de carpeta (vacío = sin carpeta)
+    fn next_folder(&self, current: &str, forward: bool) -> String {
+        let mut options = vec![String::new()];
+        options.extend(self.collections.folders());
+        let next = match options.iter().position(|f| f == current) {
+            Some(i) if forward => (i + 1) % options.len(),
+            Some(i) => (i + options.len() - 1) % options.len(),
+            None => 0,
+        };
+        options.swap_remove(next)
+    }
+
+    /// Abre el modal de carpetas para el elemento seleccionado en Collections
+    fn open_folder_prompt(&mut self, make: impl Fn(&CollectionItem, &App) -> Option<(FolderPrompt, String)>) {
+        if self.active_panel != ActivePanel::Collections || !matches!(self.left_panel_tab, LeftPanelTab::Collections) {
+            self.ai_response = "SYSTEM: Select an item in Collections first.".to_string();
+            return;
+        }
+        let item = self.get_visible_items().into_iter().nth(self.selected_idx);
+        let Some((prompt, text)) = item.as_ref().and_then(|i| make(i, self)) else { return };
+        self.folder_prompt_input = TextArea::default();
+        self.folder_prompt_input.insert_str(text);
+        self.folder_prompt = Some(prompt);
+    }
+
+    /// `N`: carpeta nueva dentro de la seleccionada (o de la de la petición seleccionada)
+    fn new_folder(&mut self) {
+        let folder = self.selected_folder().filter(|f| f != "UNGROUPED");
+        self.open_folder_prompt(|_, _| Some((FolderPrompt::NewFolder(folder.clone()), String::new())));
+    }
+
+    /// `X`: mover la petición o carpeta seleccionada; el campo empieza en su carpeta actual
+    fn move_selected(&mut self) {
+        self.open_folder_prompt(|item, app| match item {
+            CollectionItem::Request(idx) => app.collections.requests.get(*idx).map(|r| (FolderPrompt::MoveRequest(r.id.clone()), r.group.clone().unwrap_or_default())),
+            CollectionItem::Folder(path) if path != "UNGROUPED" => Some((FolderPrompt::MoveFolder(path.clone()), crate::collections::parent_folder(path).unwrap_or_default().to_string())),
+            CollectionItem::Folder(_) => None,
+        });
+    }
+
+    fn apply_folder_prompt(&mut self, prompt: FolderPrompt, input: &str) -> anyhow::Result<String> {
+        use crate::collections::{folder_leaf, normalize_folder};
+        match prompt {
+            FolderPrompt::NewFolder(parent) => {
+                let Some(name) = normalize_folder(input) else { anyhow::bail!("folder name is empty") };
+                let path = match &parent { Some(p) => format!("{}/{}", p, name), None => name };
+                self.collections.create_folder(&path)?;
+                // Desplegar el camino hasta la nueva para que se vea
+                let mut open = parent;
+                while let Some(p) = open { open = crate::collections::parent_folder(&p).map(str::to_string); self.collections.tree.expanded.insert(p); }
+                let _ = self.collections.save_tree();
+                Ok(format!("SYSTEM: Folder {} created.", path))
+            }
+            FolderPrompt::MoveRequest(id) => {
+                let Some(idx) = self.collections.find_by_id(&id) else { anyhow::bail!("the request no longer exists") };
+                let (name, group) = (self.collections.requests[idx].name.clone(), normalize_folder(input));
+                if self.collections.find_in_folder(&name, group.as_deref()).is_some_and(|i| i != idx) { anyhow::bail!("a request named '{}' already exists there", name); }
+                self.collections.move_request(idx, group.clone())?;
+                Ok(format!("SYSTEM: {} moved to {}.", name, group.as_deref().unwrap_or("UNGROUPED")))
+            }
+            FolderPrompt::MoveFolder(path) => {
+                let leaf = folder_leaf(&path).to_string();
+                let to = match normalize_folder(input) { Some(dest) => format!("{}/{}", dest, leaf), None => leaf };
+                self.collections.move_folder(&path, &to)?;
+                Ok(format!("SYSTEM: Folder moved to {}.", to))
+            }
+        }
+    }
+
+    /// `D`: copia la petición seleccionada en su misma carpeta
+    fn duplicate_selected(&mut self) {
+        if self.active_panel != ActivePanel::Collections || !matches!(self.left_panel_tab, LeftPanelTab::Collections) { return; }
+        if let Some(CollectionItem::Request(idx)) = self.get_visible_items().get(self.selected_idx) {
+            self.ai_response = match self.collections.duplicate_request(*idx) {
+                Ok(name) => format!("SYSTEM: Duplicated as {}.", name),
+                Err(e) => format!("SYSTEM ERROR: {}", e),
+            };
+        }
+    }
+
+    /// Tras renombrar, mover o borrar en la colección, cada pestaña sigue a su petición
+    /// (por `id`) o se queda sin origen
+    fn sync_tabs_with_collections(&mut self) {
+        for t in &mut self.tabs {
+            let Some(id) = t.source.as_ref().map(|s| s.id.clone()) else { continue };
+            match self.collections.requests.iter().find(|r| r.id == id) {
+                Some(r) if t.source.as_ref().is_some_and(|s| s.name != r.name || s.group != r.group) => t.follow_source(r),
+                Some(_) => {}
+                None => t.source = None,
+            }
+        }
+    }
+
+    /// Guarda la pestaña como `name` en `group`. Si el nombre es de otra petición
+    /// (no la de origen) no la pisa. Devuelve si se guardó.
+    fn write_request(&mut self, name: String, group: Option<String>) -> bool {
         let t = self.current_tab();
-        let mut hs = HashMap::new();
-        for l in t.headers_area.lines() { let pts: Vec<&str> = l.splitn(2, ':').collect(); if pts.len() == 2 { hs.insert(pts[0].trim().to_string(), pts[1].trim().to_string()); } }
-        let new_req = ApiRequest { 
-            name: format!("Req_{}", self.collections.requests.len() + 1), 
-            url: t.url_area.lines()[0].clone(), 
-            method: t.method.clone(), 
-            headers: hs, 
-            body: Some(t.body_area.lines().join("\n")),
-            group: None 
+        let assertions = match crate::assertions::parse_assertions(&t.tests_area.lines().join("\n")) {
+            Ok(a) => a,
+            Err(e) => { self.ai_response = format!("SYSTEM ERROR: {}", e); return false; }
+        };
+        let auth = match t.auth() {
+            Ok(a) => a,
+            Err(e) => { self.ai_response = format!("SYSTEM ERROR: {}", e); return false; }
         };
-        if self.collections.save_request(&new_req).is_ok() { let _ = self.collections.load_all(); self.ai_response = "SYSTEM: saved.".to_string(); }
+        // Solo se sobrescribe la propia petición de origen; lo demás se guarda con `id` nuevo
+        let existing = self.collections.find_in_folder(&name, group.as_deref()).map(|i| self.collections.requests[i].id.clone());
+        if existing.is_some() && existing != t.source.as_ref().map(|s| s.id.clone()) {
+            self.ai_response = format!("SYSTEM ERROR: A request named '{}' already exists in {}.", name, group.as_deref().unwrap_or("UNGROUPED"));
+            return false;
+        }
+        let mut new_req = ApiRequest { id: existing.unwrap_or_default(), name, group, assertions, auth, ..t.to_api_request() };
+        if let Err(e) = self.collections.save_request(&mut new_req) { self.ai_response = format!("SYSTEM ERROR: {}", e); return false; }
+        let _ = self.collections.load_all();
+        self.ai_response = format!("SYSTEM: saved {} in {}.", new_req.name, new_req.group.as_deref().unwrap_or("UNGROUPED"));
+        self.current_tab_mut().mark_saved(new_req);
+        true
     }
 
     pub fn send_request(&mut self) {
+        let auth = match self.tab_auth() {
+            Ok(a) => a,
+            Err(e) => { self.current_tab_mut().response = format!("AUTH ERROR: {}", e); return; }
+        };
         let tx = self.tx.clone();
-        let (url, m_str, body, h_lines, f_path) = {
-            let t = self.current_tab_mut(); t.response = "SYNCING...".to_string(); t.response_bytes = None; t.response_scroll = 0;
-            (t.url_area.lines()[0].clone(), t.method.clone(), t.body_area.lines().join("\n"), t.headers_area.lines().iter().map(|s| s.to_string()).collect::<Vec<_>>(), t.file_path.clone())
+        // Reenviar corta el envío anterior, que queda en el historial con lo que llegó
+        if let Some(entry) = self.stop_in_flight(self.active_tab) { self.record_history(entry); }
+        let draft = {
+            let t = self.current_tab_mut(); t.response = "SYNCING...".to_string(); t.api_response = None; t.test_results.clear(); t.response_scroll = 0; t.json_tree = None;
+            t.to_api_request()
         };
-        let mut h_map = HashMap::new();
-        for l in h_lines { let p: Vec<&str> = l.splitn(2, ':').collect(); if p.len() == 2 { h_map.insert(p[0].trim().to_string(), p[1].trim().to_string()); } }
-        self.collections.add_to_history(ApiRequest { 
-            name: url.clone(), 
-            url: url.clone(), 
-            method: m_str.clone(), 
-            headers: h_map.clone(), 
-            body: Some(body.clone()),
-            group: None 
+        let pre_script = draft.pre_request_script.clone();
+        // El historial guarda la plantilla original ({{var}}) para poder reenviarla en otro entorno;
+        // la entrada se escribe cuando llega la respuesta (o el error)
+        let entry = HistoryEntry::new(ApiRequest {
+            name: draft.url.clone(),
+            group: None,
+            // Ya resuelta (el historial no tiene carpeta de la que heredar) y sin secretos escritos a mano:
+            // history.jsonl queda en texto plano junto al body
+            auth: auth.redacted(),
+            ..draft
+        }, self.environments.active_name().map(str::to_string));
+
+        // Pre-request: corre antes de sustituir variables para que pueda definirlas
+        let mut prepared = self.current_tab().to_prepared();
+        prepared.auth = auth;
+        if let Some(script) = pre_script {
+            match crate::scripting::run_pre_request(&script, &mut prepared, &self.environments.variables()) {
+                Ok(out) => self.apply_script_output("PRE-REQUEST", out),
+                Err(e) => { self.current_tab_mut().response = format!("PRE-REQUEST SCRIPT ERROR: {}", e); return; }
+            }
+        }
+        let vars = self.environments.variables();
+        prepared.substitute(&vars);
+        if let Some(gql) = self.current_tab().graphql() {
+            // Las variables se validan solas para culpar a lo que de verdad falló
+            let variables = crate::environments::substitute_vars(gql.variables.trim(), &vars);
+            let error = match serde_json::from_str::<serde_json::Value>(&variables) {
+                Err(e) if !variables.is_empty() => Some(format!("variables are not valid JSON after substitution ({})", e)),
+                _ => serde_json::from_str::<serde_json::Value>(&prepared.body).err().map(|e| format!("request body is not valid JSON after substitution, check the {{{{var}}}} values in the query ({})", e)),
+            };
+            if let Some(e) = error { self.current_tab_mut().response = format!("GRAPHQL ERROR: {}", e); return; }
+        }
+
+        self.request_seq += 1;
+        let id = self.request_seq;
+        let (tokens, opener) = (self.tokens.clone(), self.opener.clone());
+        // El body llega a la pestaña trozo a trozo; la respuesta final sólo trae status, headers y tiempos
+        let handle = tokio::spawn(async move {
+            let progress = tx.clone();
+            let _ = tx.send(match crate::http::send_streaming(prepared, &tokens, &opener, &move |p| { let _ = progress.send(AppEvent::Stream(id, p)); }).await {
+                Ok(resp) => AppEvent::ApiResponse(id, Box::new(resp)),
+                Err(e) => AppEvent::RequestFailed(id, e.to_string()),
+            });
         });
+        // Reemplazar el envío anterior de la pestaña lo cancela
+        self.current_tab_mut().in_flight = Some(InFlight { id, handle: handle.abort_handle(), entry, started: Instant::now() });
+    }
 
-        tokio::spawn(async move {
-            let client = reqwest::Client::builder().timeout(Duration::from_secs(15)).build().unwrap();
-            let method = match m_str.as_str() { "POST" => Method::POST, "PUT" => Method::PUT, "DELETE" => Method::DELETE, "PATCH" => Method::PATCH, "HEAD" => Method::HEAD, "OPTIONS" => Method::OPTIONS, _ => Method::GET };
-            let mut rb = client.request(method.clone(), &url);
-            for (k, v) in h_map { rb = rb.header(k, v); }
-            if !f_path.is_empty() { if let Ok(b) = std::fs::read(&f_path) { let form = reqwest::multipart::Form::new().part("file", reqwest::multipart::Part::bytes(b).file_name("upload")); rb = rb.multipart(form); } }
-            else if !body.is_empty() && method != Method::GET { rb = rb.body(body); }
-            
-            match rb.header("User-Agent", "Arthema").send().await {
-                Ok(resp) => {
-                    let s = resp.status();
-                    let content_type = resp.headers().get("content-type").and_then(|v| v.to_str().ok()).unwrap_or("").to_string();
-                    let bytes = resp.bytes().await.unwrap_or_default();
-                    
-                    if content_type.starts_with("image/") {
-                        let _ = tx.send(AppEvent::ApiResponse(format!("STATUS: {}\nTYPE: {}\nSIZE: {} bytes", s, content_type, bytes.len()), Some(bytes.to_vec())));
-                    } else {
-                        let text = String::from_utf8_lossy(&bytes).to_string();
-                        let fmtd = if let Ok(val) = serde_json::from_str::<serde_json::Value>(&text) { serde_json::to_string_pretty(&val).unwrap_or(text) } else { text };
-                        let _ = tx.send(AppEvent::ApiResponse(format!("STATUS: {}\n\n{}", s, fmtd), None));
-                    }
-                }
-                Err(e) => { let _ = tx.send(AppEvent::SystemMessage(format!("ERROR: {}", e))); }
+    /// Corta el envío de la pestaña actual y deja lo recibido hasta ahora (Ctrl+X)
+    pub fn stop_request(&mut self) {
+        let Some(entry) = self.stop_in_flight(self.active_tab) else { self.ai_response = "SYSTEM: No request in flight.".to_string(); return; };
+        let t = self.current_tab_mut();
+        self.ai_response = match t.api_response.take() {
+            Some(resp) => {
+                let msg = format!("SYSTEM: Request stopped after {}.", crate::response::format_size(resp.body.len()));
+                t.show_response(resp);
+                msg
             }
-        });
+            None => { t.response = "STOPPED.".to_string(); "SYSTEM: Request stopped before the response arrived.".to_string() }
+        };
+        self.record_history(entry);
+    }
+
+    /// Cancela el envío en curso de la pestaña y devuelve su entrada de historial con lo
+    /// recibido hasta ahora (un stream SSE sólo acaba así)
+    fn stop_in_flight(&mut self, tab: usize) -> Option<HistoryEntry> {
+        let t = &mut self.tabs[tab];
+        let mut f = t.in_flight.take()?;
+        let entry = std::mem::take(&mut f.entry);
+        let entry = match t.api_response.as_ref() {
+            Some(resp) => entry.with_response(resp),
+            None => entry.with_error("stopped".to_string(), f.started.elapsed()),
+        };
+        drop(f);
+        Some(entry)
+    }
+
+    /// Calcula en segundo plano las vistas previas que pidió `ui::draw`: decodificar y codificar
+    /// (Sixel sobre todo) es demasiado lento para hacerlo mientras se dibuja
+    fn start_image_previews(&mut self) {
+        let graphics = self.graphics;
+        for i in 0..self.tabs.len() {
+            let t = &mut self.tabs[i];
+            let Some(spec) = t.preview_wanted.take() else { continue };
+            if t.preview_job.is_some_and(|(_, s)| s == spec) { continue; }
+            let Some(body) = t.api_response.as_ref().map(|r| r.body.clone()) else { continue };
+            self.request_seq += 1;
+            let (job, tx) = (self.request_seq, self.tx.clone());
+            self.tabs[i].preview_job = Some((job, spec));
+            tokio::task::spawn_blocking(move || {
+                let (protocol, width, height) = spec;
+                let _ = tx.send(AppEvent::ImagePreview(job, Box::new(crate::img_preview::render(&graphics, protocol, &body, width, height))));
+            });
+        }
+    }
+
+    fn record_history(&mut self, entry: HistoryEntry) {
+        if let Err(e) = self.collections.history.push(entry) { self.ai_response = format!("SYSTEM ERROR: history not saved ({}).", e); }
     }
 
+    fn request_tab(&self, id: u64) -> Option<usize> { self.tabs.iter().position(|t| t.in_flight.as_ref().is_some_and(|f| f.id == id)) }
+
     pub fn trigger_ai_suggestion(&mut self) { if !self.is_ai_loading { self.is_ai_loading = true; let tx = self.tx.clone(); let url = self.current_tab().url_area.lines()[0].clone(); let key = self.config.gemini_api_key.clone().unwrap_or_default(); tokio::spawn(async move { let s = crate::ai::get_ai_suggestion(&key, &url).await; let _ = tx.send(AppEvent::AiMessage(format!("AI_SUGGESTION:{}", s))); }); } }
-    pub fn trigger_ai_explain(&mut self) { if !self.is_ai_loading { self.is_ai_loading = true; let tx = self.tx.clone(); let r = self.current_tab().response.clone(); let key = self.config.gemini_api_key.clone().unwrap_or_default(); tokio::spawn(async move { let e = crate::ai::explain_response(&key, &r).await; let _ = tx.send(AppEvent::AiMessage(format!("AI_EXPLANATION:{}", e))); }); } }
-    pub fn trigger_ai_fix(&mut self) { if !self.is_ai_loading { self.is_ai_loading = true; let t = self.current_tab(); let tx = self.tx.clone(); let (m, u, h, b, e) = (t.method.clone(), t.url_area.lines()[0].clone(), t.headers_area.lines().join("\n"), t.body_area.lines().join("\n"), t.response.clone()); let key = self.config.gemini_api_key.clone().unwrap_or_default(); tokio::spawn(async move { let e = crate::ai::fix_error(&key, &m, &u, &h, &b, &e).await; let _ = tx.send(AppEvent::AiMessage(format!("AI_EXPLANATION:{}", e))); }); } }
+    pub fn trigger_ai_explain(&mut self) { if !self.is_ai_loading { self.is_ai_loading = true; let tx = self.tx.clone(); let r = self.response_for_ai(); let key = self.config.gemini_api_key.clone().unwrap_or_default(); tokio::spawn(async move { let e = crate::ai::explain_response(&key, &r).await; let _ = tx.send(AppEvent::AiMessage(format!("AI_EXPLANATION:{}", e))); }); } }
+    pub fn trigger_ai_fix(&mut self) { if !self.is_ai_loading { self.is_ai_loading = true; let t = self.current_tab(); let tx = self.tx.clone(); let (m, u, h, b, e) = (t.method.clone(), t.url_area.lines()[0].clone(), t.headers_area.lines().join("\n"), t.body_area.lines().join("\n"), self.response_for_ai()); let key = self.config.gemini_api_key.clone().unwrap_or_default(); tokio::spawn(async move { let e = crate::ai::fix_error(&key, &m, &u, &h, &b, &e).await; let _ = tx.send(AppEvent::AiMessage(format!("AI_EXPLANATION:{}", e))); }); } }
+
+    fn apply_script_output(&mut self, label: &str, out: ScriptOutput) {
+        if out.vars.is_empty() && out.logs.is_empty() { return; }
+        let mut msg = format!("SCRIPT ({}):", label);
+        for (k, v) in &out.vars { msg.push_str(&format!("\n  {} = {}", k, v)); }
+        for l in &out.logs { msg.push_str(&format!("\n  > {}", l)); }
+        if let Err(e) = self.environments.set_vars(out.vars) { msg.push_str(&format!("\n  ERROR saving environment: {}", e)); }
+        self.ai_response = msg;
+    }
+
+    fn run_post_script(&mut self, tab: usize) {
+        let t = &self.tabs[tab];
+        let (Some(script), Some(resp)) = (RequestTab::script_text(&t.post_script_area), t.api_response.as_ref()) else { return };
+        match crate::scripting::run_post_response(&script, resp, &self.environments.variables()) {
+            Ok(out) => self.apply_script_output("POST-RESPONSE", out),
+            Err(e) => self.ai_response = format!("POST-RESPONSE SCRIPT ERROR: {}", e),
+        }
+    }
+
+    fn run_assertions(&mut self, tab: usize) {
+        let t = &mut self.tabs[tab];
+        let Some(resp) = t.api_response.as_ref() else { return };
+        t.test_results = match crate::assertions::parse_assertions(&t.tests_area.lines().join("\n")) {
+            Ok(assertions) => crate::assertions::evaluate_all(&assertions, resp),
+            Err(e) => vec![AssertionResult { label: "parse".to_string(), passed: false, detail: e }],
+        };
+    }
+
+    /// Texto de la respuesta para los prompts de IA: línea de estado + body
+    fn response_for_ai(&self) -> String {
+        let t = self.current_tab();
+        match &t.api_response {
+            Some(r) if r.is_binary() => format!("STATUS: {}\nTYPE: {} (binary)", r.summary(), r.mime()),
+            Some(r) => format!("STATUS: {}\n\n{}", r.summary(), t.response),
+            None => t.response.clone(),
+        }
+    }
 
     pub fn update(&mut self) {
         while let Ok(event) = self.rx.try_recv() {
-            self.is_ai_loading = false;
             match event {
-                AppEvent::ApiResponse(text, bytes) => {
-                    let t = self.current_tab_mut();
-                    t.response = text;
-                    t.response_bytes = bytes;
+                AppEvent::Stream(id, progress) => {
+                    if let Some(i) = self.request_tab(id) { self.tabs[i].receive(progress); }
+                }
+                AppEvent::ApiResponse(id, resp) => {
+                    let Some(i) = self.request_tab(id) else { continue };
+                    let t = &mut self.tabs[i];
+                    let mut resp = *resp;
+                    if let Some(streamed) = t.api_response.take() { resp.body = streamed.body; }
+                    let entry = t.in_flight.take().map(|mut f| std::mem::take(&mut f.entry).with_response(&resp));
+                    t.show_response(resp);
+                    if let Some(entry) = entry { self.record_history(entry); }
+                    self.run_post_script(i);
+                    self.run_assertions(i);
+                }
+                AppEvent::RequestFailed(id, e) => {
+                    let Some(i) = self.request_tab(id) else { continue };
+                    let t = &mut self.tabs[i];
+                    let entry = t.in_flight.take().map(|mut f| { let elapsed = f.started.elapsed(); std::mem::take(&mut f.entry).with_error(e.clone(), elapsed) });
+                    // Cortada a mitad del body: se queda lo que llegó
+                    match t.api_response.take() {
+                        Some(resp) => {
+                            self.ai_response = format!("SYSTEM ERROR: response interrupted after {} ({}).", crate::response::format_size(resp.body.len()), e);
+                            t.show_response(resp);
+                        }
+                        None => t.response = format!("ERROR: {}", e),
+                    }
+                    if let Some(entry) = entry { self.record_history(entry); }
+                }
+                AppEvent::RunnerResult(id, index, run) => {
+                    if let Some(cr) = self.runner.as_mut().filter(|cr| cr.id == id) { cr.record(index, *run); }
                 }
                 AppEvent::AiMessage(res) => {
+                    self.is_ai_loading = false;
                     if let Some(s) = res.strip_prefix("AI_SUGGESTION:") {
                         let t = self.current_tab_mut();
                         t.url_area = TextArea::default();
                         t.url_area.insert_str(s.trim());
+                        t.sync_params_from_url();
                     } else if let Some(e) = res.strip_prefix("AI_EXPLANATION:") {
                         self.ai_response = e.to_string();
                     }
                 }
                 AppEvent::SwaggerImported(reqs) => {
                     let count = reqs.len();
-                    for r in reqs {
-                        let _ = self.collections.save_request(&r);
+                    for mut r in reqs {
+                        let _ = self.collections.save_request(&mut r);
                     }
                     let _ = self.collections.load_all();
                     self.ai_response = format!("SYSTEM: Imported {} requests from Swagger.", count);
                 }
+                AppEvent::GraphQlSchema(url, schema) => match schema {
+                    Ok(schema) => {
+                        self.ai_response = format!("SYSTEM: GraphQL schema loaded ({} types). Press Tab in the query to complete fields.", schema.types.len());
+                        for t in self.tabs.iter_mut().filter(|t| t.url() == url) { t.graphql_schema = Some(schema.clone()); }
+                    }
+                    Err(e) => self.ai_response = format!("SYSTEM ERROR: {}", e),
+                },
+                AppEvent::ImagePreview(job, preview) => {
+                    if let Some(t) = self.tabs.iter_mut().find(|t| t.preview_job.is_some_and(|(id, _)| id == job)) { (t.image_preview, t.preview_job) = (Some(*preview), None); }
+                }
                 AppEvent::SystemMessage(msg) => {
                     self.current_tab_mut().response = msg;
                 }
             }
         }
+        self.start_image_previews();
         if self.last_sys_update.elapsed() > Duration::from_secs(2) {
             self.sys.refresh_cpu(); self.sys.refresh_memory();
             self.cpu_usage = self.sys.global_cpu_info().cpu_usage(); self.mem_total = self.sys.total_memory() / 1024 / 1024; self.mem_used = self.sys.used_memory() / 1024 / 1024;
             let pid = Pid::from_u32(std::process::id()); self.sys.refresh_process(pid);
             if let Some(proc) = self.sys.process(pid) { let num_cpus = self.sys.cpus().len() as f32; self.proc_cpu = proc.cpu_usage() / num_cpus; self.proc_mem = proc.memory() / 1024 / 1024; }
-            if let Ok(output) = Command::new("pmset").arg("-g").arg("batt").output() { let out = String::from_utf8_lossy(&output.stdout); if let Some(line) = out.lines().nth(1) { if let Some(perc) = line.split('\t').nth(1) { self.battery_level = perc.split(';').next().unwrap_or("N/A").to_string(); } } }
+            if let Ok(output) = Command::new("pmset").arg("-g").arg("batt").output() { let out = String::from_utf8_lossy(&output.stdout); if let Some(line) = out.lines().nth(1) && let Some(perc) = line.split('\t').nth(1) { self.battery_level = perc.split(';').next().unwrap_or("N/A").to_string(); } }
             self.last_sys_update = Instant::now();
         }
     }
@@ -750,8 +2024,16 @@ impl<'a> App<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, KeyEventKind, KeyEventState};
 
+    /// App sobre un workspace temporal propio; el `TempDir` tiene que vivir lo que el test
+    fn test_app() -> (TempDir, App<'static>) {
+        let tmp = TempDir::new("app");
+        let app = App::for_tests(tmp.path());
+        (tmp, app)
+    }
+
     fn mock_key(code: KeyCode) -> KeyEvent {
         KeyEvent {
             code,
@@ -763,7 +2045,7 @@ mod tests {
 
     #[test]
     fn test_is_input_active_protection() {
-        let mut app = App::new();
+        let (_tmp, mut app) = test_app();
         assert!(!app.is_input_active());
         
         app.input_mode = true;
@@ -776,7 +2058,7 @@ mod tests {
 
     #[test]
     fn test_shortcut_insert_mode() {
-        let mut app = App::new();
+        let (_tmp, mut app) = test_app();
         assert!(!app.input_mode);
         
         app.handle_key(mock_key(KeyCode::Char('i')));
@@ -788,7 +2070,7 @@ mod tests {
 
     #[test]
     fn test_shortcut_panels_tab() {
-        let mut app = App::new();
+        let (_tmp, mut app) = test_app();
         assert_eq!(app.active_panel, ActivePanel::Editor);
         
         app.handle_key(mock_key(KeyCode::Tab));
@@ -803,7 +2085,7 @@ mod tests {
 
     #[test]
     fn test_shortcut_modals_activation() {
-        let mut app = App::new();
+        let (_tmp, mut app) = test_app();
         
         // Test API Key modal
         app.handle_key(mock_key(KeyCode::Char('k')));
@@ -818,4 +2100,566 @@ mod tests {
         app.handle_key(mock_key(KeyCode::Esc));
         assert!(!app.show_swagger_input);
     }
+
+    #[test]
+    fn test_runner_row_opens_in_new_tab() {
+        let (_tmp, mut app) = test_app();
+        let req = ApiRequest { name: "Login".to_string(), method: "POST".to_string(), url: "https://api.com/login".to_string(), ..Default::default() };
+        let mut cr = CollectionRun::new(1, "Auth".to_string(), vec![req.clone()], 1);
+        cr.record(0, RequestRun {
+            name: req.name.clone(), group: None, method: req.method.clone(), url: req.url.clone(), status: Some(200), elapsed_ms: 12,
+            assertions: Vec::new(), logs: Vec::new(), error: None,
+            response: Some(ApiResponse { status: 200, body: br#"{"ok":true}"#.to_vec(), ..Default::default() }),
+        });
+        app.runner = Some(cr);
+        app.show_runner = true;
+        assert!(app.is_input_active());
+
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(!app.show_runner);
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.current_tab().name, "Login");
+        assert_eq!(app.current_tab().method, "POST");
+        assert!(app.current_tab().json_tree.is_some());
+    }
+
+    #[test]
+    fn test_params_stay_in_sync_with_url() {
+        let (_tmp, mut app) = test_app();
+        let req = ApiRequest {
+            url: "https://api.com/users/:id?page=1".to_string(), method: "GET".to_string(),
+            path_params: [("id".to_string(), "42".to_string())].into_iter().collect(),
+            disabled_params: vec![("debug".to_string(), "true".to_string())],
+            ..Default::default()
+        };
+        app.current_tab_mut().load_request(&req);
+        assert_eq!(app.current_tab().params_area.lines().join("\n"), ":id=42\npage=1\n# debug=true");
+
+        // Escribir en Params reescribe la query de la URL
+        app.current_tab_mut().editor_focus = EditorFocus::Params;
+        app.input_mode = true;
+        app.current_tab_mut().params_area.move_cursor(CursorMove::Bottom);
+        app.current_tab_mut().params_area.move_cursor(CursorMove::End);
+        for c in "\nq=a b".chars() { app.handle_key(mock_key(if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) })); }
+        assert_eq!(app.current_tab().url(), "https://api.com/users/:id?page=1&q=a%20b");
+        assert_eq!(app.current_tab().to_prepared().url, "https://api.com/users/42?page=1&q=a%20b");
+
+        // Y escribir en la URL rehace las filas
+        app.current_tab_mut().editor_focus = EditorFocus::Url;
+        app.current_tab_mut().url_area.move_cursor(CursorMove::End);
+        for c in "&x=1&y=c+d".chars() { app.handle_key(mock_key(KeyCode::Char(c))); }
+        assert_eq!(app.current_tab().params(), Params::parse(":id=42\npage=1\nq=a b\nx=1\ny=c d\n# debug=true"));
+
+        // Editar una fila que no cambia la query deja la URL como la escribió el usuario
+        app.current_tab_mut().editor_focus = EditorFocus::Params;
+        app.current_tab_mut().params_area.move_cursor(CursorMove::Top);
+        app.current_tab_mut().params_area.move_cursor(CursorMove::End);
+        app.handle_key(mock_key(KeyCode::Char('7')));
+        assert_eq!(app.current_tab().url(), "https://api.com/users/:id?page=1&q=a%20b&x=1&y=c+d");
+        assert_eq!(app.current_tab().to_prepared().url, "https://api.com/users/427?page=1&q=a%20b&x=1&y=c+d");
+    }
+
+    #[test]
+    fn test_header_rows_toggle_and_autocomplete() {
+        let (_tmp, mut app) = test_app();
+        let req = ApiRequest { url: "https://api.com".to_string(), method: "GET".to_string(), headers: vec![Header::new("Accept", "text/plain"), Header::new("Accept", "application/json")], ..Default::default() };
+        app.current_tab_mut().load_request(&req);
+        app.current_tab_mut().editor_focus = EditorFocus::Headers;
+        app.input_mode = true;
+        app.current_tab_mut().headers_area.move_cursor(CursorMove::Top);
+
+        // Ctrl+O desactiva la fila del cursor: no se envía pero se guarda
+        app.handle_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        assert_eq!(app.current_tab().to_prepared().headers, vec![("Accept".to_string(), "application/json".to_string())]);
+        assert!(!app.current_tab().headers()[0].enabled);
+
+        // Tab completa el nombre y después un valor común
+        app.current_tab_mut().headers_area.move_cursor(CursorMove::Bottom);
+        app.current_tab_mut().headers_area.move_cursor(CursorMove::End);
+        for c in "\ncontent-t".chars() { app.handle_key(mock_key(if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) })); }
+        app.handle_key(mock_key(KeyCode::Tab));
+        for c in "application/j".chars() { app.handle_key(mock_key(KeyCode::Char(c))); }
+        app.handle_key(mock_key(KeyCode::Tab));
+        assert_eq!(app.current_tab().headers_area.lines()[2], "Content-Type: application/json");
+        assert!(app.current_tab().header_suggestions().is_empty());
+        // La línea se reescribe como una edición más: el undo la deshace (borrado + inserción)
+        let area = &mut app.current_tab_mut().headers_area;
+        area.undo(); area.undo();
+        assert_eq!(area.lines()[2], "Content-Type: application/j");
+    }
+
+    #[test]
+    fn test_form_body_roundtrips_through_api_request() {
+        let (_tmp, mut app) = test_app();
+        let tab = app.current_tab_mut();
+        tab.body_type = BodyType::UrlEncoded;
+        tab.body_area = TextArea::from(["user=ana", "# debug=1"]);
+        let prepared = tab.to_prepared();
+        assert!(prepared.body.is_empty());
+        assert_eq!(prepared.form.as_ref().unwrap().encoded(), "user=ana");
+
+        let req = ApiRequest { url: "https://api.com".to_string(), method: "POST".to_string(), body_type: BodyType::UrlEncoded, form: prepared.form, ..Default::default() };
+        let saved: ApiRequest = serde_json::from_str(&serde_json::to_string(&req).unwrap()).unwrap();
+        let (_tmp, mut app) = test_app();
+        app.current_tab_mut().load_request(&saved);
+        assert!(app.current_tab().body_type == BodyType::UrlEncoded);
+        assert_eq!(app.current_tab().body_area.lines().join("\n"), "user=ana\n# debug=1");
+
+        // Sin formulario se vuelve a un body normal
+        app.current_tab_mut().load_request(&ApiRequest { body: Some("{}".to_string()), body_type: BodyType::Json, form: None, ..req });
+        assert!(app.current_tab().body_type == BodyType::Json);
+    }
+
+    #[test]
+    fn test_loading_a_collection_item_restores_name_and_description() {
+        let (_tmp, mut app) = test_app();
+        app.collections.requests = vec![ApiRequest { name: "Get zen".to_string(), url: "https://api.com/zen".to_string(), method: "GET".to_string(), description: Some("# Zen\nRandom quote".to_string()), ..Default::default() }];
+        app.collections.tree.expanded.insert("UNGROUPED".to_string());
+        app.left_panel_tab = LeftPanelTab::Collections;
+        app.selected_idx = 1;
+        app.active_panel = ActivePanel::Collections;
+        app.handle_key(mock_key(KeyCode::Enter));
+        let tab = app.current_tab();
+        assert_eq!(tab.name, "Get zen");
+        assert_eq!(tab.to_api_request().description.as_deref(), Some("# Zen\nRandom quote"));
+    }
+
+    #[test]
+    fn test_graphql_errors_blame_variables_or_query() {
+        let (_tmp, mut app) = test_app();
+        let tab = app.current_tab_mut();
+        tab.body_type = BodyType::GraphQl;
+        tab.body_area = TextArea::from(["query { user(name: \"{{name}}\") { id } }"]);
+        tab.graphql_vars_area = TextArea::from(["{\"id\": }"]);
+        app.send_request();
+        assert!(app.current_tab().response.starts_with("GRAPHQL ERROR: variables are not valid JSON"), "{}", app.current_tab().response);
+
+        // Variables bien, pero un valor del entorno rompe el JSON de la query
+        app.current_tab_mut().graphql_vars_area = TextArea::from(["{\"id\": 1}"]);
+        app.environments.runtime.insert("name".to_string(), "a\"b".to_string());
+        app.send_request();
+        assert!(app.current_tab().response.starts_with("GRAPHQL ERROR: request body is not valid JSON"), "{}", app.current_tab().response);
+    }
+
+    #[test]
+    fn test_body_type_and_graphql_panes_persist() {
+        let (_tmp, mut app) = test_app();
+        for _ in 0..3 { app.handle_key(mock_key(KeyCode::Char('b'))); }
+        let tab = app.current_tab_mut();
+        assert!(tab.body_type == BodyType::GraphQl);
+        assert_eq!(tab.headers()[0].value, "application/json");
+        tab.body_area = TextArea::from(["query A { a }", "query B($id: ID) { b(id: $id) }"]);
+        tab.graphql_vars_area = TextArea::from(["{\"id\": {{id}}}"]);
+        tab.cycle_graphql_operation();
+        tab.cycle_graphql_operation();
+        assert_eq!(tab.graphql_operation.as_deref(), Some("B"));
+        tab.editor_focus = EditorFocus::Variables;
+        tab.format_body().unwrap();
+        assert_eq!(tab.graphql_vars_area.lines().join("\n"), "{\n  \"id\": {{id}}\n}");
+
+        let saved: ApiRequest = serde_json::from_str(&serde_json::to_string(&tab.to_api_request()).unwrap()).unwrap();
+        assert!(saved.body.is_none());
+        let (_tmp, mut app) = test_app();
+        app.current_tab_mut().load_request(&saved);
+        let tab = app.current_tab();
+        assert!(tab.body_type == BodyType::GraphQl);
+        assert_eq!(tab.graphql(), saved.graphql);
+        assert_eq!(tab.to_prepared().body, "{\"query\":\"query A { a }\\nquery B($id: ID) { b(id: $id) }\",\"variables\":{\n  \"id\": {{id}}\n},\"operationName\":\"B\"}");
+
+        // XML y NONE también sobreviven a guardar y recargar
+        let (_tmp, mut app) = test_app();
+        app.handle_key(mock_key(KeyCode::Char('B')));
+        assert!(app.current_tab().body_type == BodyType::None);
+        assert!(app.current_tab().headers().is_empty());
+        app.current_tab_mut().body_type = BodyType::Xml;
+        app.current_tab_mut().body_area = TextArea::from(["<a><b>1</b></a>"]);
+        app.handle_key(mock_key(KeyCode::Char('F')));
+        let saved = app.current_tab().to_api_request();
+        app.current_tab_mut().load_request(&ApiRequest { body_type: BodyType::None, ..Default::default() });
+        app.current_tab_mut().load_request(&saved);
+        assert!(app.current_tab().body_type == BodyType::Xml);
+        assert_eq!(app.current_tab().body_area.lines().join("\n"), "<a>\n  <b>1</b>\n</a>");
+    }
+
+    #[test]
+    fn test_save_in_place_save_as_and_dirty_marker() {
+        let (_tmp, mut app) = test_app();
+        assert!(!app.current_tab().is_dirty());
+
+        // Pestaña nueva: `s` pide nombre y carpeta; la carpeta puede ser nueva
+        app.current_tab_mut().url_area = TextArea::from(["https://api.com/users"]);
+        assert!(app.current_tab().is_dirty());
+        app.handle_key(mock_key(KeyCode::Char('s')));
+        assert!(app.show_save_as && app.is_input_active());
+        app.save_as_name = TextArea::from(["List users"]);
+        app.handle_key(mock_key(KeyCode::Tab));
+        for c in "Users".chars() { app.handle_key(mock_key(KeyCode::Char(c))); }
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(!app.show_save_as);
+        assert_eq!(app.collections.requests.len(), 1);
+        assert_eq!(app.collections.requests[0].group.as_deref(), Some("Users"));
+        assert_eq!(app.current_tab().name, "List users");
+        assert!(!app.current_tab().is_dirty());
+
+        // Con origen, `s` sobrescribe el mismo archivo
+        app.current_tab_mut().url_area = TextArea::from(["https://api.com/users?page=2"]);
+        assert!(app.current_tab().is_dirty());
+        app.handle_key(mock_key(KeyCode::Char('s')));
+        assert!(!app.show_save_as && !app.current_tab().is_dirty());
+        assert_eq!(app.collections.requests.len(), 1);
+        assert_eq!(app.collections.requests[0].url, "https://api.com/users?page=2");
+
+        // Otra pestaña no puede pisar una petición existente con "guardar como"
+        app.tabs.push(RequestTab::new("Req 2".to_string()));
+        app.active_tab = 1;
+        app.handle_key(mock_key(KeyCode::Char('S')));
+        app.save_as_name = TextArea::from(["List users"]);
+        app.handle_key(mock_key(KeyCode::Tab));
+        app.handle_key(mock_key(KeyCode::Down));
+        assert_eq!(app.save_as_folder.lines()[0], "Users");
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(app.show_save_as);
+        assert!(app.ai_response.contains("already exists"), "{}", app.ai_response);
+        assert_eq!(app.collections.requests[0].url, "https://api.com/users?page=2");
+    }
+
+    #[test]
+    fn test_nested_folders_render_and_move_with_their_requests() {
+        let (tmp, mut app) = test_app();
+        let dir = tmp.path();
+        for (name, group) in [("List", "Users"), ("Sudo", "Users/Admin"), ("Ping", "")] {
+            app.collections.save_request(&mut ApiRequest { name: name.to_string(), group: crate::collections::normalize_folder(group), ..Default::default() }).unwrap();
+        }
+        app.collections.load_all().unwrap();
+        app.left_panel_tab = LeftPanelTab::Collections;
+        app.active_panel = ActivePanel::Collections;
+        let labels = |app: &App| app.get_visible_items().iter().map(|i| match i {
+            CollectionItem::Folder(f) => f.clone(),
+            CollectionItem::Request(idx) => app.collections.requests[*idx].name.clone(),
+        }).collect::<Vec<_>>();
+        assert_eq!(labels(&app), ["Users", "UNGROUPED"]);
+
+        // Desplegar Users y Users/Admin; el estado queda en tree.json
+        app.selected_idx = 0;
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(labels(&app), ["Users", "Users/Admin", "List", "UNGROUPED"]);
+        app.selected_idx = 1;
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(labels(&app), ["Users", "Users/Admin", "Sudo", "List", "UNGROUPED"]);
+        let saved: crate::collections::TreeState = serde_json::from_str(&std::fs::read_to_string(dir.join("tree.json")).unwrap()).unwrap();
+        assert_eq!(saved.expanded.into_iter().collect::<Vec<_>>(), ["Users", "Users/Admin"]);
+
+        // Carpeta nueva dentro de Users/Admin, vacía pero visible
+        app.handle_key(mock_key(KeyCode::Char('N')));
+        for c in "Keys".chars() { app.handle_key(mock_key(KeyCode::Char(c))); }
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(labels(&app)[..3], ["Users", "Users/Admin", "Users/Admin/Keys"]);
+
+        // Renombrar Users/Admin arrastra la subcarpeta, la petición y la pestaña abierta
+        app.selected_idx = 3;
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(app.current_tab().name, "Sudo");
+        app.active_panel = ActivePanel::Collections;
+        app.selected_idx = 1;
+        app.handle_key(mock_key(KeyCode::Char('r')));
+        app.rename_input = TextArea::from(["Root"]);
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(labels(&app), ["Users", "Users/Root", "Users/Root/Keys", "Sudo", "List", "UNGROUPED"]);
+        assert_eq!(app.current_tab().group.as_deref(), Some("Users/Root"));
+        assert!(!app.current_tab().is_dirty());
+
+        // Mover la carpeta al primer nivel y duplicar/mover una petición
+        app.handle_key(mock_key(KeyCode::Char('X')));
+        app.folder_prompt_input = TextArea::default();
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(app.folder_prompt.is_none(), "{}", app.ai_response);
+        assert_eq!(labels(&app), ["Root", "Root/Keys", "Sudo", "Users", "List", "UNGROUPED"]);
+        app.selected_idx = 2;
+        app.handle_key(mock_key(KeyCode::Char('D')));
+        assert_eq!(app.collections.requests.iter().find(|r| r.name == "Sudo copy").unwrap().group.as_deref(), Some("Root"));
+        app.selected_idx = labels(&app).iter().position(|l| l == "Sudo").unwrap();
+        app.handle_key(mock_key(KeyCode::Char('X')));
+        assert_eq!(app.folder_prompt_input.lines()[0], "Root");
+        app.handle_key(mock_key(KeyCode::Down));
+        assert_eq!(app.folder_prompt_input.lines()[0], "Root/Keys");
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert_eq!(app.collections.requests.iter().find(|r| r.name == "Sudo").unwrap().group.as_deref(), Some("Root/Keys"));
+
+        // Borrar una carpeta pide confirmación y se lleva su contenido
+        app.selected_idx = 0;
+        app.handle_key(mock_key(KeyCode::Char('d')));
+        assert_eq!(app.collections.requests.len(), 4);
+        app.handle_key(mock_key(KeyCode::Char('d')));
+        assert_eq!(labels(&app), ["Users", "List", "UNGROUPED"]);
+        assert!(app.current_tab().source.is_none());
+    }
+
+    #[test]
+    fn test_clipboard_copy_paste_and_curl_import_without_system_tools() {
+        let (_tmp, mut app) = test_app();
+        app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert!(app.ai_response.starts_with("SYSTEM ERROR: Paste failed (internal clipboard)"), "{}", app.ai_response);
+
+        app.ai_response = "curl -X POST https://api.test/items -H 'X-Key: 1'".to_string();
+        app.active_panel = ActivePanel::AI;
+        app.handle_key(mock_key(KeyCode::Char('c')));
+        assert_eq!(app.ai_response, "SYSTEM: Copied (internal clipboard).");
+        app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert_eq!((app.current_tab().method.as_str(), app.current_tab().url()), ("POST", "https://api.test/items".to_string()));
+    }
+
+    #[test]
+    fn test_code_export_keeps_placeholders_unless_resolved() {
+        let (_tmp, mut app) = test_app();
+        app.environments.runtime.insert("host".to_string(), "api.test".to_string());
+        app.environments.runtime.insert("token".to_string(), "s3cr3t".to_string());
+        let tab = app.current_tab_mut();
+        tab.url_area = TextArea::from(["https://{{host}}/me"]);
+        tab.auth_area = TextArea::from(["type: bearer", "token: {{token}}"]);
+        app.active_panel = ActivePanel::Editor;
+        app.handle_key(mock_key(KeyCode::Char('y')));
+        let code = app.code_snippet().unwrap();
+        assert!(code.contains("https://{{host}}/me") && code.contains("Bearer {{token}}") && !code.contains("s3cr3t"), "{}", code);
+
+        app.handle_key(mock_key(KeyCode::Char('r')));
+        let code = app.code_snippet().unwrap();
+        assert!(code.contains("https://api.test/me") && code.contains("Bearer s3cr3t"), "{}", code);
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(app.code_export.is_none());
+        assert_eq!(app.clipboard.paste().unwrap(), code);
+    }
+
+    #[test]
+    fn test_open_in_system_caches_response_file_per_tab() {
+        let (_tmp, mut app) = test_app();
+        let jpeg = ApiResponse { status: 200, headers: vec![("Content-Type".to_string(), "image/jpeg".to_string())], body: vec![0xFF, 0xD8, 0xFF], ..Default::default() };
+        app.current_tab_mut().show_response(jpeg);
+        app.handle_key(mock_key(KeyCode::Char('o')));
+        app.handle_key(mock_key(KeyCode::Char('o')));
+        let opened = app.opener.opened();
+        assert_eq!(opened.len(), 2);
+        assert!(opened[0].ends_with(".jpg") && opened[0] == opened[1], "{:?}", opened);
+        let first = PathBuf::from(&opened[0]);
+        assert!(first.is_file());
+
+        // Una respuesta nueva descarta el archivo anterior
+        app.current_tab_mut().show_response(ApiResponse { status: 200, headers: vec![("Content-Type".to_string(), "application/pdf".to_string())], body: b"%PDF".to_vec(), ..Default::default() });
+        assert!(!first.exists());
+        app.handle_key(mock_key(KeyCode::Char('o')));
+        let opened = app.opener.opened();
+        assert!(opened[2].ends_with(".pdf"));
+    }
+
+    #[test]
+    fn test_binary_response_shows_hex_and_saves_through_file_picker() {
+        let (tmp, mut app) = test_app();
+        let body = b"%PDF-1.4\n\x00\xff".to_vec();
+        app.current_tab_mut().show_response(ApiResponse { status: 200, headers: vec![("Content-Type".to_string(), "application/octet-stream".to_string())], body: body.clone(), ..Default::default() });
+        assert!(app.current_tab().response.starts_with("00000000  25 50 44 46") && app.current_tab().json_tree.is_none());
+
+        app.handle_key(mock_key(KeyCode::Char('w')));
+        assert!(app.show_file_picker && app.save_file_name.lines()[0] == "response.pdf");
+        app.current_dir = tmp.path().to_path_buf();
+        app.handle_key(mock_key(KeyCode::Backspace));
+        app.handle_key(mock_key(KeyCode::Char('x')));
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert!(!app.show_file_picker, "{}", app.ai_response);
+        assert_eq!(std::fs::read(tmp.join("response.pdx")).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_fills_the_tab_live_and_ctrl_x_stops_it() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/notifications", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 4096]).await.unwrap();
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n: ping\n\nevent: notice\nid: 7\ndata: {\"n\":1}\n\ndata: part").await.unwrap();
+            // Nunca termina: sólo acaba cuando el cliente corta
+            let _ = s.read(&mut [0u8; 16]).await;
+        });
+        let (_tmp, mut app) = test_app();
+        app.current_tab_mut().url_area = TextArea::from([url]);
+        app.send_request();
+        let id = app.request_seq;
+        for _ in 0..200 {
+            app.update();
+            if !app.current_tab().sse_events.is_empty() { break; }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let t = app.current_tab();
+        assert!(t.in_flight.is_some());
+        assert_eq!(t.sse_events.len(), 1);
+        assert_eq!((t.sse_events[0].name(), t.sse_events[0].id.as_deref(), t.sse_events[0].data.as_str()), ("notice", Some("7"), "{\"n\":1}"));
+
+        // Una respuesta de otro envío no pisa la pestaña
+        let _ = app.tx.send(AppEvent::ApiResponse(id + 1, Box::new(ApiResponse { status: 500, ..Default::default() })));
+        app.update();
+        assert_eq!(app.current_tab().api_response.as_ref().unwrap().status, 200);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        let t = app.current_tab();
+        assert!(t.in_flight.is_none() && t.sse_events.len() == 1);
+        assert!(app.ai_response.starts_with("SYSTEM: Request stopped after"), "{}", app.ai_response);
+        // El servidor ve cerrarse la conexión
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stopped_and_replaced_requests_are_kept_in_history() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // Primera conexión: un stream que no acaba; la segunda nunca responde
+            let (mut s, _) = listener.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 4096]).await.unwrap();
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\ndata: hi\n\n").await.unwrap();
+            let _ = s.read(&mut [0u8; 16]).await;
+            let (mut s, _) = listener.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 16]).await;
+        });
+        let (tmp, mut app) = test_app();
+        app.current_tab_mut().url_area = TextArea::from([url]);
+        app.send_request();
+        for _ in 0..200 {
+            app.update();
+            if !app.current_tab().sse_events.is_empty() { break; }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        let entries = crate::history::History::open(tmp.path()).entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].status, entries[0].body.as_str()), (Some(200), "data: hi\n\n"));
+
+        // Reenviar antes de que llegue nada guarda el envío anterior como cortado
+        app.send_request();
+        app.send_request();
+        let entries = crate::history::History::open(tmp.path()).entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].status, entries[0].error.as_deref()), (None, Some("stopped")));
+        assert!(app.current_tab().in_flight.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rerunning_a_folder_cancels_the_previous_run() {
+        use tokio::io::AsyncReadExt;
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/slow", listener.local_addr().unwrap());
+        // Nunca responde: la primera ejecución sólo acaba si se cancela
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            while s.read(&mut buf).await.unwrap_or(0) > 0 {}
+        });
+        let (_tmp, mut app) = test_app();
+        app.collections.save_request(&mut ApiRequest { name: "slow".to_string(), method: "GET".to_string(), url, group: Some("Slow".to_string()), ..Default::default() }).unwrap();
+        app.collections.load_all().unwrap();
+        app.start_runner("Slow".to_string());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        app.start_runner("Slow".to_string());
+        assert_eq!(app.runner.as_ref().unwrap().id, 2);
+        tokio::time::timeout(Duration::from_secs(5), server).await.expect("first run still connected").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_image_previews_are_rendered_in_the_background() {
+        let (_tmp, mut app) = test_app();
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]))).write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+        app.current_tab_mut().show_response(ApiResponse { status: 200, headers: vec![("Content-Type".to_string(), "image/png".to_string())], body: png, ..Default::default() });
+        // Lo que pide el dibujado se calcula en otro hilo y llega como evento
+        app.current_tab_mut().preview_wanted = Some((Protocol::HalfBlocks, 10, 4));
+        for _ in 0..200 {
+            app.update();
+            if app.current_tab().image_preview.is_some() { break; }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let preview = app.current_tab().image_preview.as_ref().expect("preview not rendered");
+        assert!(preview.fits(Protocol::HalfBlocks, 10, 4) && matches!(preview.content, crate::img_preview::Content::Blocks(_)));
+        assert!(app.current_tab().preview_job.is_none());
+    }
+
+    #[test]
+    fn test_sse_events_keep_only_the_latest() {
+        let mut t = RequestTab::new("sse".to_string());
+        t.receive(Progress::Headers(ApiResponse { status: 200, headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())], ..Default::default() }));
+        for n in 0..SSE_EVENTS_LIMIT + 5 { t.receive(Progress::Chunk(format!("data: {}\n\n", n).into_bytes())); }
+        assert_eq!((t.sse_events.len(), t.sse_dropped), (SSE_EVENTS_LIMIT, 5));
+        assert_eq!(t.sse_events[0].data, "5");
+    }
+
+    #[test]
+    fn test_ai_spinner_stays_on_until_the_ai_reply() {
+        let (_tmp, mut app) = test_app();
+        app.is_ai_loading = true;
+        // Ni los trozos de un stream ni otros avisos son la respuesta de la IA
+        let _ = app.tx.send(AppEvent::Stream(1, Progress::Chunk(b"data: x\n\n".to_vec())));
+        let _ = app.tx.send(AppEvent::SystemMessage("ok".to_string()));
+        app.update();
+        assert!(app.is_ai_loading);
+        let _ = app.tx.send(AppEvent::AiMessage("AI_EXPLANATION:ok".to_string()));
+        app.update();
+        assert!(!app.is_ai_loading);
+    }
+
+    #[test]
+    fn test_history_records_outcome_filters_and_loads_entries() {
+        let (tmp, mut app) = test_app();
+        let dir = tmp.path();
+        let request = |method: &str, url: &str| ApiRequest { method: method.to_string(), url: url.to_string(), ..Default::default() };
+        let ok = ApiResponse { status: 200, body: br#"{"id":1}"#.to_vec(), elapsed: Duration::from_millis(12), ..Default::default() };
+        app.record_history(HistoryEntry::new(request("GET", "https://api.test/users"), Some("Dev".to_string())).with_response(&ok));
+        app.record_history(HistoryEntry::new(request("POST", "https://api.test/orders"), None).with_error("timed out".to_string(), Duration::from_secs(30)));
+        assert_eq!(crate::history::History::open(dir).entries.len(), 2);
+
+        app.handle_key(mock_key(KeyCode::Char('h')));
+        app.handle_key(mock_key(KeyCode::Char('/')));
+        assert!(app.show_history_filter && app.is_input_active());
+        app.history_filter_input = TextArea::from(["2xx users"]);
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(!app.show_history_filter, "{}", app.ai_response);
+        assert_eq!(app.visible_history(), vec![1]);
+
+        app.load_selected_item();
+        assert_eq!(app.current_tab().url(), "https://api.test/users");
+        assert!(app.current_tab().response.contains("200 · 12 ms") && app.current_tab().response.contains("ENV Dev") && app.current_tab().response.contains(r#"{"id":1}"#));
+
+        app.history_filter_input = TextArea::from(["status:bogus"]);
+        app.show_history_filter = true;
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(app.show_history_filter && app.ai_response.contains("bogus"));
+    }
+
+    #[test]
+    fn test_workspace_picker_switches_collections_environments_and_history() {
+        let (tmp, mut app) = test_app();
+        let dir = tmp.join("billing");
+        let original = PathBuf::from(&app.collections.base_path);
+        if let Some(r) = app.collections.requests.first().cloned() { app.current_tab_mut().load_request(&r); app.current_tab_mut().mark_saved(r); }
+
+        app.handle_key(mock_key(KeyCode::Char('W')));
+        assert!(app.show_workspace_picker && app.is_input_active());
+        app.handle_key(mock_key(KeyCode::Char('a')));
+        app.workspace_input = TextArea::from([dir.to_string_lossy().to_string()]);
+        app.handle_key(mock_key(KeyCode::Enter));
+        assert!(!app.show_workspace_picker, "{}", app.ai_response);
+        assert!(app.is_current_workspace(&dir));
+        assert_eq!(app.collections.manifest.as_ref().unwrap().name, "billing");
+        assert!(app.collections.requests.is_empty() && app.collections.history.entries.is_empty());
+        assert_eq!(app.environments.base_path, dir.to_string_lossy());
+        assert!(app.current_tab().source.is_none());
+        assert!(app.config.workspaces.contains_key("billing"));
+
+        // De vuelta al original desde la lista
+        app.handle_key(mock_key(KeyCode::Char('W')));
+        let entries = app.workspace_entries();
+        app.workspace_selected = entries.iter().position(|(_, p)| !app.is_current_workspace(p)).unwrap();
+        app.handle_key(mock_key(KeyCode::Char('d')));
+        assert_eq!(app.workspace_entries().len(), 1);
+        app.handle_key(mock_key(KeyCode::Esc));
+        assert!(app.switch_workspace(original.clone()));
+        assert!(app.is_current_workspace(&original));
+    }
 }
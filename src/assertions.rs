@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use crate::response::ApiResponse;
+
+/// Comprobación declarada sobre una petición guardada. Se serializa con la colección
+/// y en el editor se escribe con una línea por aserción:
+///
+/// ```text
+/// status == 200
+/// $.data.token exists
+/// $.data.user.id == 42
+/// header Content-Type
+/// time < 500
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    Status { equals: u16 },
+    JsonPath { path: String, equals: Value },
+    JsonPathExists { path: String },
+    HeaderPresent { name: String },
+    ResponseTime { max_ms: u64 },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AssertionResult {
+    pub label: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Status { equals } => write!(f, "status == {}", equals),
+            Assertion::JsonPath { path, equals } => write!(f, "{} == {}", path, equals),
+            Assertion::JsonPathExists { path } => write!(f, "{} exists", path),
+            Assertion::HeaderPresent { name } => write!(f, "header {}", name),
+            Assertion::ResponseTime { max_ms } => write!(f, "time < {}", max_ms),
+        }
+    }
+}
+
+impl Assertion {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let err = || format!("invalid assertion: '{}'", line);
+        if let Some(rest) = line.strip_prefix("status") {
+            let n = rest.trim().strip_prefix("==").ok_or_else(err)?.trim();
+            return n.parse().map(|equals| Assertion::Status { equals }).map_err(|_| err());
+        }
+        if let Some(rest) = line.strip_prefix("time") {
+            let n = rest.trim().strip_prefix('<').ok_or_else(err)?.trim().trim_end_matches("ms").trim();
+            return n.parse().map(|max_ms| Assertion::ResponseTime { max_ms }).map_err(|_| err());
+        }
+        if let Some(name) = line.strip_prefix("header ") {
+            return Ok(Assertion::HeaderPresent { name: name.trim().to_string() });
+        }
+        if line.starts_with('$') {
+            if let Some(path) = line.strip_suffix("exists") {
+                return Ok(Assertion::JsonPathExists { path: path.trim().to_string() });
+            }
+            let (path, expected) = line.split_once("==").ok_or_else(err)?;
+            let expected = expected.trim();
+            // Si no es JSON válido lo tratamos como string sin comillas
+            let equals = serde_json::from_str(expected).unwrap_or_else(|_| Value::String(expected.to_string()));
+            return Ok(Assertion::JsonPath { path: path.trim().to_string(), equals });
+        }
+        Err(err())
+    }
+
+    pub fn evaluate(&self, resp: &ApiResponse, json: Option<&Value>) -> AssertionResult {
+        let (passed, detail) = match self {
+            Assertion::Status { equals } => (resp.status == *equals, format!("got {}", resp.status)),
+            Assertion::ResponseTime { max_ms } => {
+                let ms = resp.elapsed.as_millis() as u64;
+                (ms < *max_ms, format!("took {} ms", ms))
+            }
+            Assertion::HeaderPresent { name } => match resp.header(name) {
+                Some(v) => (true, v.to_string()),
+                None => (false, "missing".to_string()),
+            },
+            Assertion::JsonPathExists { path } => match json.and_then(|j| json_path(j, path)) {
+                Some(v) => (true, truncate(&v.to_string())),
+                None => (false, "not found".to_string()),
+            },
+            Assertion::JsonPath { path, equals } => match json.and_then(|j| json_path(j, path)) {
+                Some(v) => (v == equals, format!("got {}", truncate(&v.to_string()))),
+                None => (false, "not found".to_string()),
+            },
+        };
+        AssertionResult { label: self.to_string(), passed, detail }
+    }
+}
+
+/// Una aserción por línea; vacías y comentarios (`#`) se ignoran.
+pub fn parse_assertions(text: &str) -> Result<Vec<Assertion>, String> {
+    text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(Assertion::parse).collect()
+}
+
+pub fn to_text(assertions: &[Assertion]) -> String {
+    assertions.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+pub fn evaluate_all(assertions: &[Assertion], resp: &ApiResponse) -> Vec<AssertionResult> {
+    let json = serde_json::from_slice::<Value>(&resp.body).ok();
+    assertions.iter().map(|a| a.evaluate(resp, json.as_ref())).collect()
+}
+
+/// Subconjunto de JSONPath: `$`, `.campo`, `[índice]` y `['campo']`.
+pub fn json_path<'v>(root: &'v Value, path: &str) -> Option<&'v Value> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut current = root;
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(['.', '[']).unwrap_or(r.len());
+            current = current.get(&r[..end])?;
+            rest = &r[end..];
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']')?;
+            let inner = r[..end].trim();
+            current = if let Some(key) = inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')).or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"'))) {
+                current.get(key)?
+            } else {
+                current.get(inner.parse::<usize>().ok()?)?
+            };
+            rest = &r[end + 1..];
+        } else {
+            return None;
+        }
+    }
+    Some(current)
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() > 40 { format!("{}…", s.chars().take(40).collect::<String>()) } else { s.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn response() -> ApiResponse {
+        ApiResponse {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: br#"{"data":{"id":42,"tags":["a","b"],"user name":"ana"}}"#.to_vec(),
+            elapsed: Duration::from_millis(120),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_json_path_subset() {
+        let v = json!({"data": {"tags": ["a", "b"], "user name": "ana"}});
+        assert_eq!(json_path(&v, "$.data.tags[1]"), Some(&json!("b")));
+        assert_eq!(json_path(&v, "$.data['user name']"), Some(&json!("ana")));
+        assert_eq!(json_path(&v, "$.data.missing"), None);
+        assert_eq!(json_path(&v, "data"), None);
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let text = "status == 200\n# comentario\n$.data.id == 42\n$.data.tags exists\nheader Content-Type\ntime < 500";
+        let parsed = parse_assertions(text).unwrap();
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(to_text(&parsed), text.replace("# comentario\n", ""));
+        assert!(parse_assertions("status = 200").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_pass_and_fail() {
+        let parsed = parse_assertions("status == 200\n$.data.id == 41\nheader X-Missing\ntime < 100").unwrap();
+        let results = evaluate_all(&parsed, &response());
+        assert!(results[0].passed);
+        assert!(!results[1].passed);
+        assert_eq!(results[1].detail, "got 42");
+        assert!(!results[2].passed);
+        assert!(!results[3].passed);
+    }
+}
@@ -0,0 +1,558 @@
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::environments::substitute_vars;
+use crate::http::{percent_decode, percent_encode, PreparedRequest};
+use crate::opener::Opener;
+
+/// Autenticación de una petición o carpeta. En el editor se escribe como `clave: valor`:
+///
+/// ```text
+/// type: bearer
+/// token: {{token}}
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Auth {
+    /// Usa la de la carpeta (o ninguna si la carpeta no define)
+    #[default]
+    Inherit,
+    None,
+    Basic { username: String, password: String },
+    Bearer { token: String },
+    ApiKey { key: String, value: String, location: ApiKeyLocation },
+    Digest { username: String, password: String },
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2Config),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation { #[default] Header, Query }
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2Grant { #[default] ClientCredentials, Password, AuthorizationCode }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct OAuth2Config {
+    pub grant: OAuth2Grant,
+    pub token_url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub auth_url: String,
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub client_secret: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub scope: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub password: String,
+    /// Puerto del listener local para el redirect del auth-code
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: u16,
+}
+
+fn default_redirect_port() -> u16 { 8765 }
+
+impl Auth {
+    pub fn is_inherit(&self) -> bool { matches!(self, Auth::Inherit) }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut fields: HashMap<String, String> = HashMap::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (k, v) = line.split_once(':').ok_or_else(|| format!("invalid auth line: '{}'", line))?;
+            fields.insert(k.trim().to_lowercase(), v.trim().to_string());
+        }
+        let get = |k: &str| fields.get(k).cloned().unwrap_or_default();
+        let require = |k: &str| fields.get(k).filter(|v| !v.is_empty()).cloned().ok_or_else(|| format!("auth: missing '{}'", k));
+        let kind = fields.get("type").map(|t| t.to_lowercase()).unwrap_or_else(|| "inherit".to_string());
+        Ok(match kind.as_str() {
+            "inherit" => Auth::Inherit,
+            "none" => Auth::None,
+            "basic" => Auth::Basic { username: require("username")?, password: get("password") },
+            "bearer" => Auth::Bearer { token: require("token")? },
+            "apikey" | "api_key" => {
+                let location = match get("in").to_lowercase().as_str() {
+                    "" | "header" => ApiKeyLocation::Header,
+                    "query" => ApiKeyLocation::Query,
+                    other => return Err(format!("auth: 'in' must be header or query, got '{}'", other)),
+                };
+                Auth::ApiKey { key: require("key")?, value: get("value"), location }
+            }
+            "digest" => Auth::Digest { username: require("username")?, password: get("password") },
+            "oauth2" => {
+                let grant = match get("grant").to_lowercase().as_str() {
+                    "" | "client_credentials" => OAuth2Grant::ClientCredentials,
+                    "password" => OAuth2Grant::Password,
+                    "authorization_code" | "auth_code" => OAuth2Grant::AuthorizationCode,
+                    other => return Err(format!("auth: unknown oauth2 grant '{}'", other)),
+                };
+                let redirect_port = match fields.get("redirect_port") {
+                    Some(p) => p.parse().map_err(|_| format!("auth: invalid redirect_port '{}'", p))?,
+                    None => default_redirect_port(),
+                };
+                let cfg = OAuth2Config {
+                    grant, token_url: require("token_url")?, auth_url: get("auth_url"), client_id: require("client_id")?,
+                    client_secret: get("client_secret"), scope: get("scope"), username: get("username"), password: get("password"), redirect_port,
+                };
+                if grant == OAuth2Grant::AuthorizationCode && cfg.auth_url.is_empty() { return Err("auth: authorization_code needs 'auth_url'".to_string()); }
+                if grant == OAuth2Grant::Password && cfg.username.is_empty() { return Err("auth: password grant needs 'username'".to_string()); }
+                Auth::OAuth2(cfg)
+            }
+            other => return Err(format!("auth: unknown type '{}'", other)),
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<(&str, String)> = Vec::new();
+        match self {
+            Auth::Inherit => return String::new(),
+            Auth::None => lines.push(("type", "none".to_string())),
+            Auth::Basic { username, password } | Auth::Digest { username, password } => {
+                lines.push(("type", if matches!(self, Auth::Basic { .. }) { "basic" } else { "digest" }.to_string()));
+                lines.push(("username", username.clone()));
+                lines.push(("password", password.clone()));
+            }
+            Auth::Bearer { token } => { lines.push(("type", "bearer".to_string())); lines.push(("token", token.clone())); }
+            Auth::ApiKey { key, value, location } => {
+                lines.push(("type", "apikey".to_string()));
+                lines.push(("key", key.clone()));
+                lines.push(("value", value.clone()));
+                lines.push(("in", if *location == ApiKeyLocation::Query { "query" } else { "header" }.to_string()));
+            }
+            Auth::OAuth2(c) => {
+                lines.push(("type", "oauth2".to_string()));
+                lines.push(("grant", match c.grant { OAuth2Grant::ClientCredentials => "client_credentials", OAuth2Grant::Password => "password", OAuth2Grant::AuthorizationCode => "authorization_code" }.to_string()));
+                for (k, v) in [("token_url", &c.token_url), ("auth_url", &c.auth_url), ("client_id", &c.client_id), ("client_secret", &c.client_secret), ("scope", &c.scope), ("username", &c.username), ("password", &c.password)] {
+                    if !v.is_empty() { lines.push((k, v.clone())); }
+                }
+                if c.redirect_port != default_redirect_port() { lines.push(("redirect_port", c.redirect_port.to_string())); }
+            }
+        }
+        lines.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Expande `{{var}}` en todos los campos de texto
+    pub fn substitute(&self, vars: &BTreeMap<String, String>) -> Auth {
+        let s = |v: &String| substitute_vars(v, vars);
+        match self {
+            Auth::Inherit | Auth::None => self.clone(),
+            Auth::Basic { username, password } => Auth::Basic { username: s(username), password: s(password) },
+            Auth::Digest { username, password } => Auth::Digest { username: s(username), password: s(password) },
+            Auth::Bearer { token } => Auth::Bearer { token: s(token) },
+            Auth::ApiKey { key, value, location } => Auth::ApiKey { key: s(key), value: s(value), location: *location },
+            Auth::OAuth2(c) => Auth::OAuth2(OAuth2Config {
+                token_url: s(&c.token_url), auth_url: s(&c.auth_url), client_id: s(&c.client_id), client_secret: s(&c.client_secret),
+                scope: s(&c.scope), username: s(&c.username), password: s(&c.password), ..c.clone()
+            }),
+        }
+    }
+
+    /// Copia para guardar fuera de la colección (historial): los secretos escritos a mano se
+    /// tapan y solo se conservan las plantillas `{{var}}`, cuyo valor vive en el entorno
+    pub fn redacted(&self) -> Auth {
+        let r = |v: &String| {
+            let t = v.trim();
+            if t.is_empty() || (t.starts_with("{{") && t.ends_with("}}") && t.matches("{{").count() == 1) { v.clone() } else { REDACTED.to_string() }
+        };
+        match self {
+            Auth::Inherit | Auth::None => self.clone(),
+            Auth::Basic { username, password } => Auth::Basic { username: username.clone(), password: r(password) },
+            Auth::Digest { username, password } => Auth::Digest { username: username.clone(), password: r(password) },
+            Auth::Bearer { token } => Auth::Bearer { token: r(token) },
+            Auth::ApiKey { key, value, location } => Auth::ApiKey { key: key.clone(), value: r(value), location: *location },
+            Auth::OAuth2(c) => Auth::OAuth2(OAuth2Config { client_secret: r(&c.client_secret), password: r(&c.password), ..c.clone() }),
+        }
+    }
+}
+
+/// Lo que queda en lugar de un secreto tapado por `Auth::redacted`
+pub const REDACTED: &str = "********";
+
+/// `Inherit` toma la auth de la carpeta
+pub fn resolve(own: &Auth, folder: Option<&Auth>) -> Auth {
+    match own {
+        Auth::Inherit => folder.filter(|a| !a.is_inherit()).cloned().unwrap_or(Auth::None),
+        other => other.clone(),
+    }
+}
+
+/// Aplica las auth que no necesitan red (Basic, Bearer, API key). Digest y OAuth2 se resuelven en `http::send`.
+pub fn apply_static(auth: &Auth, req: &mut PreparedRequest) {
+    match auth {
+        Auth::Basic { username, password } => {
+            let creds = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            set_header(req, "Authorization", format!("Basic {}", creds));
+        }
+        Auth::Bearer { token } => set_header(req, "Authorization", format!("Bearer {}", token)),
+        Auth::ApiKey { key, value, location: ApiKeyLocation::Header } => set_header(req, key, value.clone()),
+        Auth::ApiKey { key, value, location: ApiKeyLocation::Query } => {
+            req.url.push(if req.url.contains('?') { '&' } else { '?' });
+            req.url.push_str(&format!("{}={}", percent_encode(key), percent_encode(value)));
+        }
+        _ => {}
+    }
+}
+
+/// Reemplaza el header si ya existe (sin distinguir mayúsculas)
+pub fn set_header(req: &mut PreparedRequest, name: &str, value: String) {
+    req.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    req.headers.push((name.to_string(), value));
+}
+
+/// Cabecera `Authorization: Digest ...` (RFC 7616, qop=auth) para el reto del servidor
+pub fn digest_authorization(challenge: &str, username: &str, password: &str, method: &str, uri: &str, cnonce: &str) -> Option<String> {
+    let params = challenge.trim().strip_prefix("Digest")?.trim();
+    let mut p: HashMap<String, String> = HashMap::new();
+    for part in split_params(params) {
+        if let Some((k, v)) = part.split_once('=') { p.insert(k.trim().to_lowercase(), v.trim().trim_matches('"').to_string()); }
+    }
+    let realm = p.get("realm")?;
+    let nonce = p.get("nonce")?;
+    let algorithm = p.get("algorithm").cloned().unwrap_or_else(|| "MD5".to_string());
+    let hash = |s: String| -> String {
+        if algorithm.to_uppercase().starts_with("SHA-256") { hex(&sha2::Sha256::digest(s.as_bytes())) } else { hex(&md5::Md5::digest(s.as_bytes())) }
+    };
+    let mut ha1 = hash(format!("{}:{}:{}", username, realm, password));
+    if algorithm.to_uppercase().ends_with("-SESS") { ha1 = hash(format!("{}:{}:{}", ha1, nonce, cnonce)); }
+    let ha2 = hash(format!("{}:{}", method, uri));
+    let qop = p.get("qop").and_then(|q| q.split(',').map(str::trim).find(|q| *q == "auth"));
+    let nc = "00000001";
+    let response = match qop {
+        Some(q) => hash(format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, q, ha2)),
+        None => hash(format!("{}:{}:{}", ha1, nonce, ha2)),
+    };
+    let mut header = format!("Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"", username, realm, nonce, uri, algorithm, response);
+    if let Some(q) = qop { header.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", q, nc, cnonce)); }
+    if let Some(opaque) = p.get("opaque") { header.push_str(&format!(", opaque=\"{}\"", opaque)); }
+    Some(header)
+}
+
+/// Separa por comas respetando las comillas (`qop="auth,auth-int"`)
+fn split_params(s: &str) -> Vec<String> {
+    let (mut parts, mut current, mut quoted) = (Vec::new(), String::new(), false);
+    for c in s.chars() {
+        match c {
+            '"' => { quoted = !quoted; current.push(c); }
+            ',' if !quoted => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+fn hex(bytes: &[u8]) -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() }
+
+pub fn random_token(len: usize) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| CHARS[rng.gen_range(0..CHARS.len())] as char).collect()
+}
+
+/// `code_challenge` S256 de PKCE
+pub fn pkce_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(verifier.as_bytes()))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CachedToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Unix timestamp (segundos) de expiración, si el servidor lo indica
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+impl CachedToken {
+    /// Se da por caducado 30 s antes para no enviar un token a punto de expirar
+    pub fn is_fresh(&self, now: i64) -> bool { self.expires_at.is_none_or(|exp| exp - 30 > now) }
+}
+
+/// Caché de tokens OAuth2 compartida por la TUI y el runner, persistida en `oauth_tokens.json`
+#[derive(Clone)]
+pub struct TokenStore {
+    path: Option<PathBuf>,
+    tokens: Arc<Mutex<HashMap<String, CachedToken>>>,
+}
+
+impl TokenStore {
+    pub fn new(base_path: &str) -> Self {
+        let path = PathBuf::from(base_path).join("oauth_tokens.json");
+        let tokens = fs::read_to_string(&path).ok().and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default();
+        Self { path: Some(path), tokens: Arc::new(Mutex::new(tokens)) }
+    }
+
+    /// Caché sólo en memoria (tests)
+    #[cfg(test)]
+    pub fn in_memory() -> Self { Self { path: None, tokens: Arc::default() } }
+
+    fn get(&self, key: &str) -> Option<CachedToken> { self.tokens.lock().ok()?.get(key).cloned() }
+
+    fn put(&self, key: String, token: CachedToken) {
+        let Ok(mut tokens) = self.tokens.lock() else { return };
+        tokens.insert(key, token);
+        if let Some(path) = &self.path && let Ok(json) = serde_json::to_string_pretty(&*tokens) { let _ = fs::write(path, json); }
+    }
+}
+
+fn cache_key(c: &OAuth2Config) -> String { format!("{:?}|{}|{}|{}|{}", c.grant, c.token_url, c.client_id, c.scope, c.username) }
+
+/// Access token válido: de la caché, renovado con el refresh token o pedido de nuevo.
+/// `timeout` limita cada llamada al token endpoint; la espera del login en el navegador
+/// tiene su propio límite (`REDIRECT_TIMEOUT`).
+pub async fn oauth2_token(cfg: &OAuth2Config, store: &TokenStore, client: &reqwest::Client, opener: &Opener, timeout: Duration) -> Result<String> {
+    let key = cache_key(cfg);
+    let now = chrono::Utc::now().timestamp();
+    let cached = store.get(&key);
+    if let Some(t) = cached.as_ref().filter(|t| t.is_fresh(now)) { return Ok(t.access_token.clone()); }
+
+    if let Some(refresh) = cached.and_then(|t| t.refresh_token)
+        && let Ok(token) = request_token(cfg, client, timeout, vec![("grant_type", "refresh_token".to_string()), ("refresh_token", refresh.clone())]).await
+    {
+        // Algunos servidores no devuelven un refresh token nuevo: conservamos el anterior
+        let token = CachedToken { refresh_token: token.refresh_token.or(Some(refresh)), ..token };
+        store.put(key, token.clone());
+        return Ok(token.access_token);
+    }
+
+    let token = match cfg.grant {
+        OAuth2Grant::ClientCredentials => request_token(cfg, client, timeout, vec![("grant_type", "client_credentials".to_string())]).await?,
+        OAuth2Grant::Password => request_token(cfg, client, timeout, vec![("grant_type", "password".to_string()), ("username", cfg.username.clone()), ("password", cfg.password.clone())]).await?,
+        OAuth2Grant::AuthorizationCode => authorization_code(cfg, client, opener, timeout).await?,
+    };
+    store.put(key, token.clone());
+    Ok(token.access_token)
+}
+
+async fn request_token(cfg: &OAuth2Config, client: &reqwest::Client, timeout: Duration, mut form: Vec<(&str, String)>) -> Result<CachedToken> {
+    form.push(("client_id", cfg.client_id.clone()));
+    if !cfg.client_secret.is_empty() { form.push(("client_secret", cfg.client_secret.clone())); }
+    if !cfg.scope.is_empty() { form.push(("scope", cfg.scope.clone())); }
+    let exchange = async {
+        let resp = client.post(&cfg.token_url).header("Accept", "application/json").form(&form).send().await?;
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.map_err(|e| anyhow!("oauth2: invalid token response ({}): {}", status, e))?;
+        Ok::<_, anyhow::Error>((status, body))
+    };
+    let (status, body) = tokio::time::timeout(timeout, exchange).await.map_err(|_| anyhow!("oauth2: token request timed out after {}s", timeout.as_secs()))??;
+    let Some(access_token) = body.get("access_token").and_then(|v| v.as_str()) else {
+        bail!("oauth2: token request failed ({}): {}", status, body.get("error_description").or(body.get("error")).unwrap_or(&body));
+    };
+    Ok(CachedToken {
+        access_token: access_token.to_string(),
+        refresh_token: body.get("refresh_token").and_then(|v| v.as_str()).map(String::from),
+        expires_at: body.get("expires_in").and_then(|v| v.as_i64()).map(|secs| chrono::Utc::now().timestamp() + secs),
+    })
+}
+
+/// Tiempo para iniciar sesión en el navegador en el auth-code
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Auth-code con PKCE: abre el navegador y espera el redirect en un listener local
+async fn authorization_code(cfg: &OAuth2Config, client: &reqwest::Client, opener: &Opener, timeout: Duration) -> Result<CachedToken> {
+    let verifier = random_token(64);
+    let state = random_token(16);
+    let redirect_uri = format!("http://127.0.0.1:{}/callback", cfg.redirect_port);
+    let mut url = format!(
+        "{}{}response_type=code&client_id={}&redirect_uri={}&code_challenge={}&code_challenge_method=S256&state={}",
+        cfg.auth_url, if cfg.auth_url.contains('?') { '&' } else { '?' },
+        percent_encode(&cfg.client_id), percent_encode(&redirect_uri), pkce_challenge(&verifier), state,
+    );
+    if !cfg.scope.is_empty() { url.push_str(&format!("&scope={}", percent_encode(&cfg.scope))); }
+    // Sin navegador nadie completaría el login: mejor fallar que esperar REDIRECT_TIMEOUT
+    if matches!(opener, Opener::Headless) { bail!("oauth2: interactive OAuth2 login not available in headless mode; cache a token first"); }
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", cfg.redirect_port)).await
+        .map_err(|e| anyhow!("oauth2: cannot listen on port {}: {}", cfg.redirect_port, e))?;
+    // Si no se puede abrir, la URL sigue en el error del timeout para abrirla a mano
+    let _ = opener.open(&url);
+    let params = tokio::time::timeout(REDIRECT_TIMEOUT, wait_for_redirect(&listener)).await
+        .map_err(|_| anyhow!("oauth2: timed out waiting for the browser redirect. Open manually: {}", url))??;
+    if params.get("state") != Some(&state) { bail!("oauth2: state mismatch in redirect"); }
+    if let Some(err) = params.get("error") { bail!("oauth2: authorization denied: {}", err); }
+    let code = params.get("code").ok_or_else(|| anyhow!("oauth2: redirect without code"))?;
+    request_token(cfg, client, timeout, vec![
+        ("grant_type", "authorization_code".to_string()), ("code", code.clone()),
+        ("redirect_uri", redirect_uri), ("code_verifier", verifier),
+    ]).await
+}
+
+async fn wait_for_redirect(listener: &tokio::net::TcpListener) -> Result<HashMap<String, String>> {
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let mut buf = vec![0u8; 8192];
+        let n = socket.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        // "GET /callback?code=...&state=... HTTP/1.1"
+        let target = request.lines().next().and_then(|l| l.split_whitespace().nth(1)).unwrap_or("");
+        let Some(("/callback", query)) = target.split_once('?') else {
+            let _ = socket.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await;
+            continue;
+        };
+        let body = "<html><body style=\"font-family:monospace\">ARTHEMA: authorization received. You can close this tab.</body></html>";
+        let _ = socket.write_all(format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).as_bytes()).await;
+        return Ok(query.split('&').filter_map(|kv| kv.split_once('=')).map(|(k, v)| (k.to_string(), percent_decode(v))).collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_text_roundtrip() {
+        let text = "type: apikey\nkey: X-Api-Key\nvalue: {{key}}\nin: query";
+        let auth = Auth::parse(text).unwrap();
+        assert_eq!(auth, Auth::ApiKey { key: "X-Api-Key".to_string(), value: "{{key}}".to_string(), location: ApiKeyLocation::Query });
+        assert_eq!(auth.to_text(), text);
+        assert_eq!(Auth::parse("").unwrap(), Auth::Inherit);
+        assert!(Auth::parse("type: oauth2\ngrant: authorization_code\ntoken_url: https://t\nclient_id: c").is_err());
+        let oauth = Auth::parse("type: oauth2\ngrant: password\ntoken_url: https://t\nclient_id: c\nusername: ana").unwrap();
+        assert_eq!(Auth::parse(&oauth.to_text()).unwrap(), oauth);
+    }
+
+    #[test]
+    fn test_resolve_and_apply_static() {
+        let folder = Auth::Bearer { token: "{{token}}".to_string() };
+        let mut vars = BTreeMap::new();
+        vars.insert("token".to_string(), "abc".to_string());
+        let auth = resolve(&Auth::Inherit, Some(&folder)).substitute(&vars);
+        let mut req = PreparedRequest { url: "https://api.com".to_string(), headers: vec![("authorization".to_string(), "old".to_string())], ..Default::default() };
+        apply_static(&auth, &mut req);
+        assert_eq!(req.headers, vec![("Authorization".to_string(), "Bearer abc".to_string())]);
+        assert_eq!(resolve(&Auth::None, Some(&folder)), Auth::None);
+        assert_eq!(resolve(&Auth::Inherit, None), Auth::None);
+
+        let mut req = PreparedRequest { url: "https://api.com?a=1".to_string(), ..Default::default() };
+        apply_static(&Auth::ApiKey { key: "k".to_string(), value: "a b".to_string(), location: ApiKeyLocation::Query }, &mut req);
+        assert_eq!(req.url, "https://api.com?a=1&k=a%20b");
+    }
+
+    #[test]
+    fn test_redacted_keeps_templates_only() {
+        assert_eq!(Auth::Bearer { token: "{{token}}".to_string() }.redacted(), Auth::Bearer { token: "{{token}}".to_string() });
+        assert_eq!(Auth::Bearer { token: "abc123".to_string() }.redacted(), Auth::Bearer { token: REDACTED.to_string() });
+        assert_eq!(Auth::Basic { username: "ana".to_string(), password: "pw{{x}}".to_string() }.redacted(), Auth::Basic { username: "ana".to_string(), password: REDACTED.to_string() });
+        let oauth = Auth::parse("type: oauth2\ngrant: password\ntoken_url: https://t\nclient_id: c\nclient_secret: s3cr3t\nusername: ana\npassword: {{pw}}").unwrap();
+        let Auth::OAuth2(c) = oauth.redacted() else { panic!() };
+        assert_eq!((c.client_secret.as_str(), c.password.as_str(), c.client_id.as_str()), (REDACTED, "{{pw}}", "c"));
+    }
+
+    #[test]
+    fn test_digest_rfc2617_example() {
+        // Ejemplo de la RFC 2617 §3.5
+        let challenge = r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#;
+        let header = digest_authorization(challenge, "Mufasa", "Circle Of Life", "GET", "/dir/index.html", "0a4f113b").unwrap();
+        assert!(header.contains("response=\"6629fae49393a05397450978507c4ef1\""), "{}", header);
+        assert!(header.contains("qop=auth, nc=00000001"));
+        assert!(header.contains("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+    }
+
+    #[test]
+    fn test_pkce_and_token_freshness() {
+        // Ejemplo de la RFC 7636 apéndice B
+        assert_eq!(pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+        assert_eq!(random_token(64).len(), 64);
+        let t = CachedToken { access_token: "a".to_string(), refresh_token: None, expires_at: Some(1_000) };
+        assert!(t.is_fresh(900));
+        assert!(!t.is_fresh(980));
+        let store = TokenStore::in_memory();
+        store.put("k".to_string(), t.clone());
+        assert_eq!(store.get("k"), Some(t));
+    }
+
+    /// Servidor de token que responde una vez con `access_token`
+    async fn token_server() -> String {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 4096]).await.unwrap();
+            let body = r#"{"access_token":"tok","expires_in":3600}"#;
+            s.write_all(format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    /// Hace de navegador: espera a que se abra la URL de login y, tras `delay`, llama al redirect
+    fn browser_login(opener: Opener, port: u16, delay: Duration) {
+        tokio::spawn(async move {
+            let url = loop {
+                if let Some(url) = opener.opened().pop() { break url; }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            let state = url.split("state=").nth(1).and_then(|s| s.split('&').next()).unwrap().to_string();
+            tokio::time::sleep(delay).await;
+            let mut s = tokio::net::TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            s.write_all(format!("GET /callback?code=c0de&state={} HTTP/1.1\r\nHost: localhost\r\n\r\n", state).as_bytes()).await.unwrap();
+        });
+    }
+
+    fn auth_code_config(token_url: String) -> OAuth2Config {
+        let port = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port();
+        OAuth2Config { grant: OAuth2Grant::AuthorizationCode, token_url, auth_url: "https://idp.test/authorize".to_string(), client_id: "c".to_string(), redirect_port: port, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_opens_the_browser_through_the_opener() {
+        let cfg = auth_code_config(token_server().await);
+        let opener = Opener::fake();
+        browser_login(opener.clone(), cfg.redirect_port, Duration::ZERO);
+        let token = oauth2_token(&cfg, &TokenStore::in_memory(), &reqwest::Client::new(), &opener, Duration::from_secs(15)).await.unwrap();
+        assert_eq!(token, "tok");
+        let opened = opener.opened();
+        assert_eq!(opened.len(), 1);
+        assert!(opened[0].starts_with("https://idp.test/authorize?response_type=code&client_id=c&redirect_uri="), "{}", opened[0]);
+    }
+
+    #[tokio::test]
+    async fn test_headless_authorization_code_fails_fast() {
+        let cfg = auth_code_config("http://127.0.0.1:1/token".to_string());
+        let (store, client) = (TokenStore::in_memory(), reqwest::Client::new());
+        let login = oauth2_token(&cfg, &store, &client, &Opener::Headless, Duration::from_secs(15));
+        let err = tokio::time::timeout(Duration::from_secs(1), login).await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("headless mode"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_token_timeout_does_not_cover_the_browser_login() {
+        // El login tarda más que el límite del token endpoint y aun así termina bien
+        let cfg = auth_code_config(token_server().await);
+        let opener = Opener::fake();
+        browser_login(opener.clone(), cfg.redirect_port, Duration::from_millis(400));
+        let token = oauth2_token(&cfg, &TokenStore::in_memory(), &reqwest::Client::new(), &opener, Duration::from_millis(100)).await;
+        assert_eq!(token.unwrap(), "tok");
+
+        // El límite sí aplica a la llamada al token endpoint
+        let silent = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let cfg = OAuth2Config { token_url: format!("http://{}/token", silent.local_addr().unwrap()), client_id: "c".to_string(), ..Default::default() };
+        let err = oauth2_token(&cfg, &TokenStore::in_memory(), &reqwest::Client::new(), &opener, Duration::from_millis(100)).await.unwrap_err();
+        assert!(err.to_string().contains("token request timed out"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_listener_parses_code() {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut s = tokio::net::TcpStream::connect(addr).await.unwrap();
+            s.write_all(b"GET /callback?code=a%2Fb&state=xyz HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        });
+        let params = wait_for_redirect(&listener).await.unwrap();
+        assert_eq!(params.get("code").map(String::as_str), Some("a/b"));
+        assert_eq!(params.get("state").map(String::as_str), Some("xyz"));
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+use crate::form::FormKind;
+
+/// Tipo de body de una petición. Decide cómo se interpreta el editor de body al enviar.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyType {
+    #[default]
+    Json,
+    Text,
+    Xml,
+    /// Query y variables en paneles separados; se envía como JSON
+    #[serde(rename = "graphql")]
+    GraphQl,
+    /// multipart/form-data
+    Form,
+    #[serde(rename = "urlencoded")]
+    UrlEncoded,
+    /// El fichero de Attachment se envía tal cual como body
+    Binary,
+    /// Texto tal cual con el Content-Type que se ponga en Headers
+    Raw,
+    None,
+}
+
+impl BodyType {
+    pub const ALL: [BodyType; 9] = [BodyType::Json, BodyType::Text, BodyType::Xml, BodyType::GraphQl, BodyType::Form, BodyType::UrlEncoded, BodyType::Binary, BodyType::Raw, BodyType::None];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BodyType::Json => "JSON",
+            BodyType::Text => "TEXT",
+            BodyType::Xml => "XML",
+            BodyType::GraphQl => "GQL",
+            BodyType::Form => "FORM",
+            BodyType::UrlEncoded => "URLENC",
+            BodyType::Binary => "BIN",
+            BodyType::Raw => "RAW",
+            BodyType::None => "NONE",
+        }
+    }
+
+    pub fn cycle(&self, forward: bool) -> BodyType {
+        let i = Self::ALL.iter().position(|t| t == self).unwrap_or(0) as i32;
+        Self::ALL[(i + if forward { 1 } else { -1 }).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+
+    pub fn is_default(&self) -> bool { *self == BodyType::Json }
+
+    /// Los tipos de formulario guardan el body como campos, no como texto
+    pub fn form_kind(&self) -> Option<FormKind> {
+        match self { BodyType::Form => Some(FormKind::Multipart), BodyType::UrlEncoded => Some(FormKind::UrlEncoded), _ => None }
+    }
+
+    pub fn from_form_kind(kind: FormKind) -> Self {
+        match kind { FormKind::Multipart => BodyType::Form, FormKind::UrlEncoded => BodyType::UrlEncoded }
+    }
+
+    /// Content-Type al cambiar de tipo: `Some(None)` lo quita, `None` deja el que haya
+    pub fn content_type(&self) -> Option<Option<&'static str>> {
+        match self {
+            BodyType::Json | BodyType::GraphQl => Some(Some("application/json")),
+            BodyType::Text => Some(Some("text/plain")),
+            BodyType::Xml => Some(Some("application/xml")),
+            BodyType::UrlEncoded => Some(Some("application/x-www-form-urlencoded")),
+            BodyType::Binary => Some(Some("application/octet-stream")),
+            // En multipart el boundary lo pone el cliente
+            BodyType::Form | BodyType::None => Some(None),
+            BodyType::Raw => None,
+        }
+    }
+
+    /// Tipo que corresponde a un Content-Type (importadores)
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let ct = content_type.to_ascii_lowercase();
+        if ct.contains("json") { Some(BodyType::Json) }
+        else if ct.contains("xml") { Some(BodyType::Xml) }
+        else if ct.starts_with("text/plain") { Some(BodyType::Text) }
+        else if ct.starts_with("multipart/form-data") { Some(BodyType::Form) }
+        else if ct.starts_with("application/x-www-form-urlencoded") { Some(BodyType::UrlEncoded) }
+        else if ct.is_empty() { None }
+        else { Some(BodyType::Raw) }
+    }
+}
+
+/// Reindenta JSON sin reordenar claves ni exigir que sea válido: respeta strings y
+/// deja intactos los `{{var}}`, así que sirve también con variables sin sustituir
+pub fn pretty_json(text: &str) -> String {
+    let mut out = String::new();
+    let mut depth = 0usize;
+    let mut chars = text.chars().peekable();
+    let newline = |out: &mut String, depth: usize| { out.push('\n'); out.push_str(&"  ".repeat(depth)); };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    if c == '\\' { if let Some(n) = chars.next() { out.push(n); } } else if c == '"' { break; }
+                }
+            }
+            '{' if chars.peek() == Some(&'{') => {
+                out.push_str("{{");
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() { out.push(c); if prev == '}' && c == '}' { break; } prev = c; }
+            }
+            '{' | '[' => {
+                let close = if c == '{' { '}' } else { ']' };
+                while chars.peek().is_some_and(|c| c.is_whitespace()) { chars.next(); }
+                if chars.peek() == Some(&close) { chars.next(); out.push(c); out.push(close); continue; }
+                out.push(c);
+                depth += 1;
+                newline(&mut out, depth);
+            }
+            '}' | ']' => { depth = depth.saturating_sub(1); newline(&mut out, depth); out.push(c); }
+            ',' => { out.push(c); newline(&mut out, depth); }
+            ':' => out.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretty_json_keeps_order_and_variables() {
+        assert_eq!(pretty_json(r#"{"b":1,"a":[1, 2],"id":{{id}},"s":"x, {y}","e":{ }}"#), "{\n  \"b\": 1,\n  \"a\": [\n    1,\n    2\n  ],\n  \"id\": {{id}},\n  \"s\": \"x, {y}\",\n  \"e\": {}\n}");
+    }
+
+    #[test]
+    fn test_cycle_wraps_both_ways() {
+        assert_eq!(BodyType::Json.cycle(false), BodyType::None);
+        assert_eq!(BodyType::None.cycle(true), BodyType::Json);
+        assert_eq!(BodyType::from_content_type("application/soap+xml"), Some(BodyType::Xml));
+    }
+}
@@ -1,28 +1,31 @@
 use crate::collections::ApiRequest;
-use std::collections::HashMap;
+use crate::body::BodyType;
+use crate::form::{Form, FormField, FormKind};
+use crate::graphql::GraphQlBody;
 use std::fs;
 use std::path::Path;
 
 pub fn parse_bruno_folder(dir_path: &Path) -> Vec<ApiRequest> {
+    let root = dir_path.file_name().map(|s| crate::collections::folder_segment(&s.to_string_lossy()));
+    parse_dir(dir_path, root)
+}
+
+/// Peticiones de `dir` en la carpeta `group`; cada subdirectorio es una subcarpeta
+fn parse_dir(dir: &Path, group: Option<String>) -> Vec<ApiRequest> {
     let mut requests = Vec::new();
-    if let Ok(entries) = fs::read_dir(dir_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
             if path.is_dir() {
-                requests.extend(parse_bruno_folder(&path));
-            } else if path.extension().and_then(|s| s.to_str()) == Some("bru") {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    if let Some(req) = parse_bru_file(&content) {
-                        let group = path.parent()
-                            .and_then(|p| p.file_name())
-                            .and_then(|s| s.to_str())
-                            .map(|s| s.to_string());
-                        
-                        let mut req_with_group = req;
-                        req_with_group.group = group;
-                        requests.push(req_with_group);
-                    }
-                }
+                let name = crate::collections::folder_segment(&path.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default());
+                let sub = match &group { Some(parent) => format!("{}/{}", parent, name), None => name };
+                requests.extend(parse_dir(&path, Some(sub)));
+            } else if path.extension().and_then(|s| s.to_str()) == Some("bru")
+                && let Ok(content) = fs::read_to_string(&path)
+                && let Some(req) = parse_bru_file(&content)
+            {
+                requests.push(ApiRequest { group: group.clone(), ..req });
             }
         }
     }
@@ -33,41 +36,82 @@ fn parse_bru_file(content: &str) -> Option<ApiRequest> {
     let mut name = String::from("Unnamed Bruno");
     let mut url = String::new();
     let mut method = String::from("GET");
-    let mut body = String::new();
-    let mut in_body = false;
+    let (mut body, mut vars) = (String::new(), String::new());
+    let mut body_type = None;
+    // Bloque de body abierto (`body:json`, `body:graphql:vars`...)
+    let mut block: Option<String> = None;
+    // Bloque de formulario abierto y el ya cerrado
+    let mut form: Option<Form> = None;
+    let mut form_done: Option<Form> = None;
 
     for line in content.lines() {
         let trimmed = line.trim();
-        if trimmed.starts_with("name:") {
+        // El contenido va indentado: sólo un `}` en la primera columna cierra el bloque
+        if let Some(b) = block.as_deref() {
+            if line.trim_end() == "}" { block = None; continue; }
+            let target = if b == "body:graphql:vars" { &mut vars } else { &mut body };
+            target.push_str(line.strip_prefix("  ").unwrap_or(line));
+            target.push('\n');
+            continue;
+        }
+        // Dentro de un formulario `name:`/`url:` son campos, no metadatos
+        if trimmed != "}" && let Some(f) = form.as_mut() {
+            if let Some(field) = parse_form_line(trimmed) { f.fields.push(field); }
+        } else if trimmed.starts_with("name:") {
             name = trimmed.replace("name:", "").trim().to_string();
-        } else if trimmed.ends_with("{") {
-            let m = trimmed.split_whitespace().next().unwrap_or("GET").to_uppercase();
+        } else if let Some(head) = trimmed.strip_suffix('{').map(str::trim) {
+            let m = head.to_uppercase();
             if ["GET", "POST", "PUT", "DELETE", "PATCH"].contains(&m.as_str()) {
                 method = m;
             }
-            if trimmed.starts_with("body") { in_body = true; }
+            if head == "body:form-urlencoded" { form = Some(Form { kind: FormKind::UrlEncoded, fields: Vec::new() }); }
+            else if head == "body:multipart-form" { form = Some(Form { kind: FormKind::Multipart, fields: Vec::new() }); }
+            else if head.starts_with("body") {
+                if head != "body:graphql:vars" {
+                    body_type = Some(match head { "body:xml" => BodyType::Xml, "body:text" => BodyType::Text, "body:graphql" => BodyType::GraphQl, "body" | "body:json" => BodyType::Json, _ => BodyType::Raw });
+                }
+                block = Some(head.to_string());
+            }
         } else if trimmed.starts_with("url:") {
             url = trimmed.replace("url:", "").trim().to_string();
-        } else if trimmed == "}" {
-            in_body = false;
-        } else if in_body {
-            body.push_str(line);
-            body.push('\n');
+        } else if trimmed == "body: none" {
+            body_type = body_type.or(Some(BodyType::None));
+        } else if trimmed == "}" && let Some(f) = form.take() {
+            form_done = Some(f);
         }
     }
 
     if url.is_empty() { return None; }
 
+    let body_type = match &form_done { Some(f) => BodyType::from_form_kind(f.kind), None => body_type.unwrap_or_default() };
+    let graphql = (body_type == BodyType::GraphQl).then(|| GraphQlBody { query: body.trim().to_string(), variables: vars.trim().to_string(), operation_name: None });
     Some(ApiRequest {
         name,
         url,
         method,
-        headers: HashMap::new(),
-        body: if body.trim().is_empty() { None } else { Some(body.trim().to_string()) },
+        headers: Vec::new(),
+        body: if body.trim().is_empty() || graphql.is_some() { None } else { Some(body.trim().to_string()) },
+        body_type,
+        form: form_done,
+        graphql,
         group: None,
+        ..Default::default()
     })
 }
 
+/// `clave: valor`, `~clave: valor` (desactivado) y `clave: @file(ruta)` en multipart
+fn parse_form_line(line: &str) -> Option<FormField> {
+    let (line, enabled) = match line.strip_prefix('~') { Some(rest) => (rest, false), None => (line, true) };
+    let (name, value) = line.split_once(':')?;
+    let value = value.trim();
+    let mut field = match value.strip_prefix("@file(").and_then(|v| v.strip_suffix(')')) {
+        Some(path) => FormField::file(name.trim(), path.split('|').next().unwrap_or(path).trim()),
+        None => FormField::text(name.trim(), value),
+    };
+    field.enabled = enabled;
+    Some(field)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +131,38 @@ get {
         assert_eq!(req.name, "Get Users");
         assert_eq!(req.url, "https://api.com/users");
     }
+
+    #[test]
+    fn test_parse_bru_form_bodies() {
+        let content = "meta {\n  name: Upload\n}\npost {\n  url: https://api.com/up\n}\nbody:multipart-form {\n  name: cv\n  doc: @file(/tmp/cv.pdf)\n  ~old: 1\n}\n";
+        let req = parse_bru_file(content).unwrap();
+        // `name: cv` del formulario no pisa el nombre de la petición
+        assert_eq!(req.name, "Upload");
+        assert!(req.body.is_none());
+        assert_eq!(req.form.unwrap().to_text(), "name=cv\ndoc=@/tmp/cv.pdf\n# old=1");
+    }
+
+    #[test]
+    fn test_parse_bru_json_and_graphql_bodies() {
+        let content = "post {\n  url: https://api.com/users\n  body: json\n}\nbody:json {\n  {\n    \"name\": \"ana\"\n  }\n}\n";
+        let req = parse_bru_file(content).unwrap();
+        assert_eq!((req.body_type, req.body.as_deref()), (BodyType::Json, Some("{\n  \"name\": \"ana\"\n}")));
+        let content = "post {\n  url: https://api.com/gql\n}\nbody:graphql {\n  query Me {\n    me { id }\n  }\n}\nbody:graphql:vars {\n  {\"a\": 1}\n}\n";
+        let req = parse_bru_file(content).unwrap();
+        let gql = req.graphql.unwrap();
+        assert_eq!((req.body_type, gql.query.as_str(), gql.variables.as_str()), (BodyType::GraphQl, "query Me {\n  me { id }\n}", "{\"a\": 1}"));
+        assert_eq!(parse_bru_file("get {\n  url: https://api.com\n  body: none\n}\n").unwrap().body_type, BodyType::None);
+    }
+
+    #[test]
+    fn test_nested_directories_become_nested_folders() {
+        let tmp = crate::test_util::TempDir::new("bruno_tree");
+        let root = tmp.join("Shop");
+        fs::create_dir_all(root.join("Orders/Refunds")).unwrap();
+        fs::write(root.join("ping.bru"), "meta {\n  name: Ping\n}\nget {\n  url: https://api.com/ping\n}\n").unwrap();
+        fs::write(root.join("Orders/Refunds/create.bru"), "meta {\n  name: Refund\n}\npost {\n  url: https://api.com/refunds\n}\n").unwrap();
+        let reqs = parse_bruno_folder(&root);
+        let groups: Vec<_> = reqs.iter().map(|r| (r.name.as_str(), r.group.as_deref())).collect();
+        assert_eq!(groups, [("Refund", Some("Shop/Orders/Refunds")), ("Ping", Some("Shop"))]);
+    }
 }
@@ -0,0 +1,120 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+
+/// De dónde sale y adónde va lo que se copia
+pub enum Backend {
+    /// Portapapeles nativo (macOS, Windows, X11)
+    Arboard(arboard::Clipboard),
+    /// Herramienta externa: (programa y args para copiar, programa y args para pegar)
+    Command { copy: &'static [&'static str], paste: &'static [&'static str] },
+    /// Secuencia OSC 52: la terminal (también por SSH) copia al portapapeles local. No puede pegar.
+    Osc52,
+    /// Solo dentro de Arthema
+    Memory,
+}
+
+const WAYLAND: Backend = Backend::Command { copy: &["wl-copy"], paste: &["wl-paste", "--no-newline"] };
+const XCLIP: Backend = Backend::Command { copy: &["xclip", "-selection", "clipboard"], paste: &["xclip", "-selection", "clipboard", "-o"] };
+
+/// Portapapeles de la app. Lo copiado se guarda también en memoria, así pegar funciona
+/// aunque el backend no sepa leer (OSC 52) o no haya ninguno.
+pub struct Clipboard {
+    backend: Backend,
+    last: String,
+}
+
+impl Clipboard {
+    pub fn new(backend: Backend) -> Self { Self { backend, last: String::new() } }
+
+    /// `ARTHEMA_CLIPBOARD` (arboard, wayland, xclip, osc52, memory) o, sin ella: OSC 52 en una
+    /// sesión SSH sin display, arboard, wl-copy, xclip y por último memoria
+    pub fn detect() -> Self {
+        let env = |k: &str| std::env::var_os(k).is_some_and(|v| !v.is_empty());
+        let backend = match std::env::var("ARTHEMA_CLIPBOARD").unwrap_or_default().as_str() {
+            "osc52" => Backend::Osc52,
+            "memory" => Backend::Memory,
+            "wayland" => WAYLAND,
+            "xclip" => XCLIP,
+            "arboard" => arboard::Clipboard::new().map(Backend::Arboard).unwrap_or(Backend::Memory),
+            _ if (env("SSH_TTY") || env("SSH_CONNECTION")) && !env("DISPLAY") && !env("WAYLAND_DISPLAY") => Backend::Osc52,
+            _ => match arboard::Clipboard::new() {
+                Ok(c) => Backend::Arboard(c),
+                Err(_) if env("WAYLAND_DISPLAY") && in_path("wl-copy") => WAYLAND,
+                Err(_) if env("DISPLAY") && in_path("xclip") => XCLIP,
+                Err(_) => Backend::Memory,
+            },
+        };
+        Self::new(backend)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match &self.backend {
+            Backend::Arboard(_) => "system clipboard",
+            Backend::Command { copy, .. } => copy[0],
+            Backend::Osc52 => "OSC 52",
+            Backend::Memory => "internal clipboard",
+        }
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        self.last = text.to_string();
+        match &mut self.backend {
+            Backend::Arboard(c) => c.set_text(text).map_err(|e| anyhow!("{}", e)),
+            Backend::Command { copy, .. } => {
+                let mut child = Command::new(copy[0]).args(&copy[1..]).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().map_err(|e| anyhow!("{}: {}", copy[0], e))?;
+                if let Some(mut stdin) = child.stdin.take() { stdin.write_all(text.as_bytes())?; }
+                let status = child.wait()?;
+                if status.success() { Ok(()) } else { Err(anyhow!("{} exited with {}", copy[0], status)) }
+            }
+            Backend::Osc52 => {
+                let mut out = std::io::stdout();
+                write!(out, "{}", osc52(text))?;
+                out.flush()?;
+                Ok(())
+            }
+            Backend::Memory => Ok(()),
+        }
+    }
+
+    pub fn paste(&mut self) -> Result<String> {
+        match &mut self.backend {
+            Backend::Arboard(c) => c.get_text().map_err(|e| anyhow!("{}", e)),
+            Backend::Command { paste, .. } => {
+                let out = Command::new(paste[0]).args(&paste[1..]).stderr(Stdio::null()).output().map_err(|e| anyhow!("{}: {}", paste[0], e))?;
+                if !out.status.success() { return Err(anyhow!("{} exited with {}", paste[0], out.status)); }
+                Ok(String::from_utf8_lossy(&out.stdout).to_string())
+            }
+            Backend::Osc52 | Backend::Memory if self.last.is_empty() => {
+                Err(anyhow!("nothing copied in this session (the {} cannot be read; use the terminal's paste)", if matches!(self.backend, Backend::Osc52) { "OSC 52 clipboard" } else { "system clipboard" }))
+            }
+            Backend::Osc52 | Backend::Memory => Ok(self.last.clone()),
+        }
+    }
+}
+
+/// `ESC ] 52 ; c ; <base64> BEL`
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))
+}
+
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend_and_osc52_sequence() {
+        let mut clipboard = Clipboard::new(Backend::Memory);
+        assert_eq!(clipboard.name(), "internal clipboard");
+        assert!(clipboard.paste().unwrap_err().to_string().contains("nothing copied"));
+        clipboard.copy("curl https://api.test").unwrap();
+        assert_eq!(clipboard.paste().unwrap(), "curl https://api.test");
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+}
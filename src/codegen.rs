@@ -0,0 +1,351 @@
+use serde_json::Value;
+
+use crate::form::{Form, FormField, FormKind};
+use crate::http::PreparedRequest;
+
+/// Lenguajes/herramientas a los que se puede exportar una petición
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language { Curl, Reqwest, Fetch, Axios, PythonRequests, HTTPie }
+
+impl Language {
+    pub const ALL: [Language; 6] = [Language::Curl, Language::Reqwest, Language::Fetch, Language::Axios, Language::PythonRequests, Language::HTTPie];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::Curl => "cURL",
+            Language::Reqwest => "Rust reqwest",
+            Language::Fetch => "JS fetch",
+            Language::Axios => "axios",
+            Language::PythonRequests => "Python requests",
+            Language::HTTPie => "HTTPie",
+        }
+    }
+
+    pub fn cycle(&self, forward: bool) -> Language {
+        let i = Self::ALL.iter().position(|l| l == self).unwrap_or(0) as i32;
+        Self::ALL[(i + if forward { 1 } else { -1 }).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+}
+
+/// Vista normalizada de la petición: mismas reglas que `http::send`
+/// (el formulario o el adjunto mandan sobre el body y GET nunca lleva body).
+struct Parts<'r> {
+    method: &'r str,
+    url: &'r str,
+    headers: Vec<(&'r str, &'r str)>,
+    body: Option<&'r str>,
+    form: Option<Form>,
+    /// Fichero enviado tal cual como body (tipo BINARY)
+    body_file: Option<&'r str>,
+    insecure: bool,
+}
+
+impl<'r> Parts<'r> {
+    fn new(req: &'r PreparedRequest) -> Self {
+        let form = req.effective_form();
+        let body_file = Some(req.body_file.as_str()).filter(|f| form.is_none() && !f.is_empty());
+        let body = Some(req.body.as_str()).filter(|b| form.is_none() && body_file.is_none() && !b.trim().is_empty() && req.method != "GET");
+        // En multipart el Content-Type (con boundary) lo pone el cliente
+        let multipart = matches!(&form, Some(f) if f.kind == FormKind::Multipart);
+        let headers = req.headers.iter()
+            .filter(|(k, _)| !k.trim().is_empty())
+            .filter(|(k, _)| !multipart || !k.eq_ignore_ascii_case("content-type"))
+            .map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        Self { method: req.method.as_str(), url: req.url.as_str(), headers, body, form, body_file, insecure: req.insecure }
+    }
+
+    fn json_body(&self) -> Option<Value> { self.body.and_then(|b| serde_json::from_str(b).ok()).filter(|v: &Value| v.is_object() || v.is_array()) }
+
+    fn multipart(&self) -> Option<&Form> { self.form.as_ref().filter(|f| f.kind == FormKind::Multipart) }
+
+    fn urlencoded(&self) -> Option<&Form> { self.form.as_ref().filter(|f| f.kind == FormKind::UrlEncoded) }
+
+    fn has_files(&self) -> bool { self.body_file.is_some() || self.multipart().is_some_and(|f| f.active().any(|f| f.file)) }
+}
+
+pub fn generate(lang: Language, req: &PreparedRequest) -> String {
+    let p = Parts::new(req);
+    match lang {
+        Language::Curl => curl(&p),
+        Language::Reqwest => reqwest(&p),
+        Language::Fetch => fetch(&p),
+        Language::Axios => axios(&p),
+        Language::PythonRequests => python(&p),
+        Language::HTTPie => httpie(&p),
+    }
+}
+
+fn curl(p: &Parts) -> String {
+    let mut lines = vec![if p.method == "GET" { format!("curl {}", sh(p.url)) } else { format!("curl -X {} {}", p.method, sh(p.url)) }];
+    if p.insecure { lines.push("-k".to_string()); }
+    for (k, v) in &p.headers { lines.push(format!("-H {}", sh(&format!("{}: {}", k, v)))); }
+    for f in p.multipart().into_iter().flat_map(Form::active) {
+        // `-F` interpreta `@`/`<` al inicio del texto: esos van con --form-string
+        let flag = if !f.file && (f.value.starts_with('@') || f.value.starts_with('<')) { "--form-string" } else { "-F" };
+        lines.push(format!("{} {}", flag, sh(&format!("{}={}", f.name, f.curl_value()))));
+    }
+    for f in p.urlencoded().into_iter().flat_map(Form::active) { lines.push(format!("--data-urlencode {}", sh(&format!("{}={}", f.name, f.value)))); }
+    if let Some(b) = p.body { lines.push(format!("--data-raw {}", sh(b))); }
+    if let Some(path) = p.body_file { lines.push(format!("--data-binary {}", sh(&format!("@{}", path)))); }
+    lines.join(" \\\n  ")
+}
+
+fn httpie(p: &Parts) -> String {
+    let mut parts = vec!["http".to_string()];
+    if p.multipart().is_some() { parts.push("--multipart".to_string()); }
+    if p.urlencoded().is_some() { parts.push("--form".to_string()); }
+    if p.insecure { parts.push("--verify=no".to_string()); }
+    if let Some(b) = p.body { parts.push(format!("--raw {}", sh(b))); }
+    parts.push(p.method.to_string());
+    parts.push(sh(p.url));
+    for (k, v) in &p.headers { parts.push(sh(&format!("{}:{}", k, v))); }
+    for f in p.form.iter().flat_map(Form::active) {
+        parts.push(sh(&if f.file { format!("{}@{};type={}", f.name, f.value, f.mime()) } else { format!("{}={}", f.name, f.value) }));
+    }
+    if let Some(path) = p.body_file { parts.push(format!("< {}", sh(path))); }
+    parts.join(" ")
+}
+
+fn reqwest(p: &Parts) -> String {
+    let mut out = String::from(if p.insecure { "let client = reqwest::Client::builder().danger_accept_invalid_certs(true).build()?;\n" } else { "let client = reqwest::Client::new();\n" });
+    if let Some(form) = p.multipart() {
+        out.push_str("let form = reqwest::multipart::Form::new()");
+        for f in form.active() {
+            if f.file {
+                out.push_str(&format!("\n    .part({}, reqwest::multipart::Part::bytes(std::fs::read({})?).file_name({}).mime_str({})?)", rust_str(&f.name), rust_str(&f.value), rust_str(&f.file_name()), rust_str(&f.mime())));
+            } else {
+                out.push_str(&format!("\n    .text({}, {})", rust_str(&f.name), rust_str(&f.value)));
+            }
+        }
+        out.push_str(";\n");
+    }
+    let builder = match p.method {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD" => format!("client.{}({})", p.method.to_lowercase(), rust_str(p.url)),
+        m => format!("client.request(reqwest::Method::from_bytes(b{})?, {})", rust_str(m), rust_str(p.url)),
+    };
+    out.push_str(&format!("let response = {}\n", builder));
+    for (k, v) in &p.headers { out.push_str(&format!("    .header({}, {})\n", rust_str(k), rust_str(v))); }
+    if p.multipart().is_some() { out.push_str("    .multipart(form)\n"); }
+    if let Some(form) = p.urlencoded() {
+        let pairs: Vec<String> = form.active().map(|f| format!("({}, {})", rust_str(&f.name), rust_str(&f.value))).collect();
+        out.push_str(&format!("    .form(&[{}])\n", pairs.join(", ")));
+    }
+    if let Some(b) = p.body { out.push_str(&format!("    .body({})\n", rust_str(b))); }
+    if let Some(path) = p.body_file { out.push_str(&format!("    .body(tokio::fs::File::open({}).await?)\n", rust_str(path))); }
+    out.push_str("    .send()\n    .await?;\nprintln!(\"{}\", response.text().await?);");
+    out
+}
+
+/// `form.append(...)` para FormData/URLSearchParams; `file` da la expresión del fichero
+fn js_form(form: &Form, ctor: &str, file: impl Fn(&FormField) -> String) -> String {
+    let mut out = format!("const form = new {}();\n", ctor);
+    for f in form.active() {
+        let value = if f.file { file(f) } else { js_str(&f.value) };
+        out.push_str(&format!("form.append({}, {});\n", js_str(&f.name), value));
+    }
+    out
+}
+
+fn fetch(p: &Parts) -> String {
+    let mut out = String::new();
+    if p.has_files() { out.push_str("import { openAsBlob } from \"node:fs\";\n\n"); }
+    if let Some(form) = p.multipart() {
+        out.push_str(&js_form(form, "FormData", |f| format!("await openAsBlob({}, {{ type: {} }}), {}", js_str(&f.value), js_str(&f.mime()), js_str(&f.file_name()))));
+        out.push('\n');
+    }
+    if let Some(form) = p.urlencoded() { out.push_str(&js_form(form, "URLSearchParams", |_| String::new())); out.push('\n'); }
+    let mut opts = vec![format!("  method: {}", js_str(p.method))];
+    if !p.headers.is_empty() { opts.push(format!("  headers: {}", js_headers(&p.headers, "  "))); }
+    if p.form.is_some() { opts.push("  body: form".to_string()); }
+    else if let Some(path) = p.body_file { opts.push(format!("  body: await openAsBlob({})", js_str(path))); }
+    else if let Some(v) = p.json_body() { opts.push(format!("  body: JSON.stringify({})", indent(&pretty(&v), "  "))); }
+    else if let Some(b) = p.body { opts.push(format!("  body: {}", js_str(b))); }
+    out.push_str(&format!("const response = await fetch({}, {{\n{},\n}});\nconsole.log(await response.text());", js_str(p.url), opts.join(",\n")));
+    out
+}
+
+fn axios(p: &Parts) -> String {
+    let mut out = String::from("import axios from \"axios\";\n");
+    if p.insecure { out.push_str("import https from \"node:https\";\n"); }
+    if let Some(form) = p.multipart() {
+        out.push_str("import FormData from \"form-data\";\n");
+        if p.has_files() { out.push_str("import fs from \"node:fs\";\n"); }
+        out.push('\n');
+        out.push_str(&js_form(form, "FormData", |f| format!("fs.createReadStream({}), {{ filename: {}, contentType: {} }}", js_str(&f.value), js_str(&f.file_name()), js_str(&f.mime()))));
+    }
+    if p.body_file.is_some() { out.push_str("import fs from \"node:fs\";\n"); }
+    if let Some(form) = p.urlencoded() { out.push('\n'); out.push_str(&js_form(form, "URLSearchParams", |_| String::new())); }
+    let mut opts = vec![format!("  method: {}", js_str(&p.method.to_lowercase())), format!("  url: {}", js_str(p.url))];
+    match (p.multipart().is_some(), p.headers.is_empty()) {
+        (true, _) => {
+            let extra: String = p.headers.iter().map(|(k, v)| format!("\n    {}: {},", js_str(k), js_str(v))).collect();
+            opts.push(format!("  headers: {{\n    ...form.getHeaders(),{}\n  }}", extra));
+        }
+        (false, false) => opts.push(format!("  headers: {}", js_headers(&p.headers, "  "))),
+        (false, true) => {}
+    }
+    if p.form.is_some() { opts.push("  data: form".to_string()); }
+    else if let Some(path) = p.body_file { opts.push(format!("  data: fs.createReadStream({})", js_str(path))); }
+    else if let Some(v) = p.json_body() { opts.push(format!("  data: {}", indent(&pretty(&v), "  "))); }
+    else if let Some(b) = p.body { opts.push(format!("  data: {}", js_str(b))); }
+    if p.insecure { opts.push("  httpsAgent: new https.Agent({ rejectUnauthorized: false })".to_string()); }
+    out.push_str(&format!("\nconst response = await axios({{\n{},\n}});\nconsole.log(response.data);", opts.join(",\n")));
+    out
+}
+
+fn python(p: &Parts) -> String {
+    let mut out = String::from("import requests\n\n");
+    out.push_str(&format!("url = {}\n", js_str(p.url)));
+    let mut args = vec![js_str(p.method), "url".to_string()];
+    if !p.headers.is_empty() {
+        let items: String = p.headers.iter().map(|(k, v)| format!("    {}: {},\n", js_str(k), js_str(v))).collect();
+        out.push_str(&format!("headers = {{\n{}}}\n", items));
+        args.push("headers=headers".to_string());
+    }
+    // Listas de tuplas: conservan el orden y los nombres repetidos
+    if let Some(form) = p.multipart() {
+        let items: String = form.active().map(|f| if f.file {
+            format!("    ({}, ({}, open({}, \"rb\"), {})),\n", js_str(&f.name), js_str(&f.file_name()), js_str(&f.value), js_str(&f.mime()))
+        } else {
+            format!("    ({}, (None, {})),\n", js_str(&f.name), js_str(&f.value))
+        }).collect();
+        out.push_str(&format!("files = [\n{}]\n", items));
+        args.push("files=files".to_string());
+    } else if let Some(form) = p.urlencoded() {
+        let items: String = form.active().map(|f| format!("    ({}, {}),\n", js_str(&f.name), js_str(&f.value))).collect();
+        out.push_str(&format!("data = [\n{}]\n", items));
+        args.push("data=data".to_string());
+    } else if let Some(v) = p.json_body() {
+        out.push_str(&format!("payload = {}\n", py_value(&v, "")));
+        args.push("json=payload".to_string());
+    } else if let Some(b) = p.body {
+        out.push_str(&format!("payload = {}\n", js_str(b)));
+        args.push("data=payload".to_string());
+    } else if let Some(path) = p.body_file {
+        out.push_str(&format!("payload = open({}, \"rb\")\n", js_str(path)));
+        args.push("data=payload".to_string());
+    }
+    if p.insecure { args.push("verify=False".to_string()); }
+    out.push_str(&format!("\nresponse = requests.request({})\nprint(response.status_code)\nprint(response.text)", args.join(", ")));
+    out
+}
+
+/// Comillas simples de shell: `'` se cierra, se escapa y se reabre
+fn sh(s: &str) -> String { format!("'{}'", s.replace('\'', "'\\''")) }
+
+/// Literal de Rust: raw string si se puede (más legible para JSON)
+fn rust_str(s: &str) -> String { if s.contains("\"#") { format!("{:?}", s) } else if s.contains('"') || s.contains('\\') { format!("r#\"{}\"#", s) } else { format!("\"{}\"", s) } }
+
+/// Literal JSON: válido tal cual en JavaScript y en Python
+fn js_str(s: &str) -> String { serde_json::to_string(s).unwrap_or_default() }
+
+fn js_headers(headers: &[(&str, &str)], pad: &str) -> String {
+    let items: Vec<String> = headers.iter().map(|(k, v)| format!("{}  {}: {}", pad, js_str(k), js_str(v))).collect();
+    format!("{{\n{},\n{}}}", items.join(",\n"), pad)
+}
+
+fn pretty(v: &Value) -> String { serde_json::to_string_pretty(v).unwrap_or_default() }
+
+fn indent(s: &str, pad: &str) -> String { s.lines().collect::<Vec<_>>().join(&format!("\n{}", pad)) }
+
+/// JSON → literal de Python (`None`/`True`/`False`)
+fn py_value(v: &Value, pad: &str) -> String {
+    let inner = format!("{}    ", pad);
+    match v {
+        Value::Null => "None".to_string(),
+        Value::Bool(b) => if *b { "True" } else { "False" }.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => js_str(s),
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Array(items) => format!("[\n{}\n{}]", items.iter().map(|i| format!("{}{},", inner, py_value(i, &inner))).collect::<Vec<_>>().join("\n"), pad),
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Object(map) => format!("{{\n{}\n{}}}", map.iter().map(|(k, v)| format!("{}{}: {},", inner, js_str(k), py_value(v, &inner))).collect::<Vec<_>>().join("\n"), pad),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post() -> PreparedRequest {
+        PreparedRequest {
+            method: "POST".to_string(),
+            url: "https://api.com/users".to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string()), ("X-Note".to_string(), "it's".to_string())],
+            body: "{\"name\":\"ana\",\"admin\":false,\"team\":null}".to_string(),
+            file_path: String::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_curl_quotes_and_body() {
+        let out = generate(Language::Curl, &post());
+        assert_eq!(out, "curl -X POST 'https://api.com/users' \\\n  -H 'Content-Type: application/json' \\\n  -H 'X-Note: it'\\''s' \\\n  --data-raw '{\"name\":\"ana\",\"admin\":false,\"team\":null}'");
+        let get = PreparedRequest { method: "GET".to_string(), url: "https://api.com".to_string(), body: "ignored".to_string(), ..Default::default() };
+        assert_eq!(generate(Language::Curl, &get), "curl 'https://api.com'");
+    }
+
+    #[test]
+    fn test_python_uses_json_literals() {
+        let out = generate(Language::PythonRequests, &post());
+        assert!(out.contains("\"admin\": False,"));
+        assert!(out.contains("\"team\": None,"));
+        assert!(out.contains("requests.request(\"POST\", url, headers=headers, json=payload)"));
+    }
+
+    #[test]
+    fn test_multipart_drops_content_type_and_body() {
+        let mut req = post();
+        req.file_path = "/tmp/avatar.png".to_string();
+        for lang in Language::ALL {
+            let out = generate(lang, &req);
+            assert!(!out.contains("application/json"), "{:?} kept content-type", lang);
+            assert!(!out.contains("ana"), "{:?} kept body", lang);
+            assert!(out.contains("/tmp/avatar.png"), "{:?} lost the file", lang);
+        }
+        assert!(generate(Language::HTTPie, &req).starts_with("http --multipart POST"));
+    }
+
+    #[test]
+    fn test_reqwest_and_fetch_shapes() {
+        let out = generate(Language::Reqwest, &post());
+        assert!(out.contains("let response = client.post(\"https://api.com/users\")"));
+        assert!(out.contains(".body(r#\"{\"name\":\"ana\",\"admin\":false,\"team\":null}\"#)"));
+        let out = generate(Language::Fetch, &post());
+        assert!(out.contains("body: JSON.stringify({\n    \"admin\": false,"));
+        assert_eq!(Language::Curl.cycle(false), Language::HTTPie);
+    }
+
+    #[test]
+    fn test_form_fields_and_files_roundtrip_through_curl() {
+        let mut req = post();
+        req.form = Some(Form::parse("name=Ana\navatar=@/tmp/me.png;type=image/webp;filename=ana.png\n# off=1", FormKind::Multipart));
+        let curl = generate(Language::Curl, &req);
+        assert!(curl.contains("-F 'name=Ana'") && curl.contains("-F 'avatar=@/tmp/me.png;type=image/webp;filename=ana.png'"), "{}", curl);
+        // Las filas desactivadas no se exportan
+        assert_eq!(crate::curl::parse_curl(&curl).unwrap().form.unwrap().fields, req.form.as_ref().unwrap().fields[..2]);
+        let python = generate(Language::PythonRequests, &req);
+        assert!(python.contains("(\"avatar\", (\"ana.png\", open(\"/tmp/me.png\", \"rb\"), \"image/webp\")),"), "{}", python);
+        assert!(!python.contains("off"));
+
+        req.form = Some(Form::parse("user=ana\npass=a&b", FormKind::UrlEncoded));
+        req.headers.clear();
+        let curl = generate(Language::Curl, &req);
+        assert!(curl.ends_with("--data-urlencode 'user=ana' \\\n  --data-urlencode 'pass=a&b'"), "{}", curl);
+        assert_eq!(crate::curl::parse_curl(&curl).unwrap().form, req.form);
+        assert!(generate(Language::Reqwest, &req).contains(".form(&[(\"user\", \"ana\"), (\"pass\", \"a&b\")])"));
+    }
+
+    #[test]
+    fn test_binary_body_file_replaces_body() {
+        let mut req = post();
+        req.body_file = "/tmp/dump.bin".to_string();
+        for lang in Language::ALL {
+            let out = generate(lang, &req);
+            assert!(!out.contains("ana") && out.contains("/tmp/dump.bin"), "{:?}: {}", lang, out);
+        }
+        assert_eq!(crate::curl::parse_curl(&generate(Language::Curl, &req)).unwrap().body_file.as_deref(), Some("/tmp/dump.bin"));
+        assert!(generate(Language::HTTPie, &req).ends_with("< '/tmp/dump.bin'"));
+    }
+}
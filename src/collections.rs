@@ -1,92 +1,605 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use anyhow::Result;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use crate::assertions::Assertion;
+use crate::auth::Auth;
+use crate::headers::Header;
+use crate::body::BodyType;
+use crate::form::Form;
+use crate::graphql::GraphQlBody;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Versión del esquema de los `.json` de colecciones. Los archivos sin `version` son v1
+/// (headers como mapa, sin `body_type`); los v2 no tienen `id`. Se migran en memoria al
+/// cargarlos y en disco al guardarlos (o con `arthema migrate`).
+pub const SCHEMA_VERSION: u64 = 3;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ApiRequest {
+    /// Identificador estable: no cambia al renombrar ni al mover la petición
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub name: String,
     pub url: String,
     pub method: String,
-    pub headers: HashMap<String, String>,
+    /// Documentación en Markdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Lista ordenada; los archivos antiguos con un mapa `{nombre: valor}` siguen cargando
+    #[serde(default, deserialize_with = "crate::headers::deserialize")]
+    pub headers: Vec<Header>,
     pub body: Option<String>,
+    /// Cómo se envía el body; sin el campo (archivos antiguos) es JSON
+    #[serde(default, skip_serializing_if = "BodyType::is_default")]
+    pub body_type: BodyType,
+    /// Formulario de los tipos FORM/URLENCODED
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form: Option<Form>,
+    /// Query, variables y operación del tipo GRAPHQL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphql: Option<GraphQlBody>,
+    /// Fichero del body binario o adjunto multipart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// Carpeta; las anidadas van separadas por `/` ("Users/Admin")
     pub group: Option<String>,
+    /// Script Rhai ejecutado antes de enviar la petición
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_request_script: Option<String>,
+    /// Script Rhai ejecutado al recibir la respuesta
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_response_script: Option<String>,
+    /// Aserciones evaluadas tras cada envío
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    /// No verificar el certificado TLS (`curl -k`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure: bool,
+    /// Por defecto hereda la auth de su carpeta
+    #[serde(default, skip_serializing_if = "Auth::is_inherit")]
+    pub auth: Auth,
+    /// Valores de `:id`/`{id}` en la URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub path_params: BTreeMap<String, String>,
+    /// Parámetros de query desactivados en el editor (los activos viven en la URL)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_params: Vec<(String, String)>,
+}
+
+/// Archivo de colección: la petición con el número de versión delante
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u64,
+    #[serde(flatten)]
+    request: &'a ApiRequest,
+}
+
+/// Campos siempre en el mismo orden y salto de línea final, para que los diffs sean limpios
+pub fn to_json(req: &ApiRequest) -> Result<String> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(&Versioned { version: SCHEMA_VERSION, request: req })?))
+}
+
+pub fn new_id() -> String { format!("{:016x}", rand::random::<u64>()) }
+
+/// `id` de un archivo que no lo tiene (o lo repite): derivado de su ruta (FNV-1a) para que
+/// no cambie entre cargas mientras no se reescriba
+fn path_id(path: &Path) -> String {
+    let hash = path.to_string_lossy().bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+/// Nombre de archivo válido en cualquier sistema: minúsculas ASCII, dígitos y `-`
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() { slug.push(c.to_ascii_lowercase()); } else if !slug.is_empty() && !slug.ends_with('-') { slug.push('-'); }
+    }
+    let slug: String = slug.chars().take(60).collect();
+    let slug = slug.trim_end_matches('-');
+    // Nombres reservados de Windows
+    let reserved = matches!(slug, "con" | "prn" | "aux" | "nul") || (slug.len() == 4 && (slug.starts_with("com") || slug.starts_with("lpt")) && slug.ends_with(|c: char| c.is_ascii_digit()));
+    match slug {
+        "" => "untitled".to_string(),
+        s if reserved => format!("{}-request", s),
+        s => s.to_string(),
+    }
+}
+
+/// `path` es `<slug>.json` o `<slug>-N.json`
+fn stem_matches(path: &Path, slug: &str) -> bool {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    stem == slug || stem.strip_prefix(slug).and_then(|r| r.strip_prefix('-')).is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Lee un archivo de colección de cualquier versión. El `bool` indica si venía de una anterior;
+/// los v2 y anteriores quedan sin `id`.
+pub fn from_json(content: &str) -> Result<(ApiRequest, bool)> {
+    let mut value: serde_json::Value = serde_json::from_str(content)?;
+    let version = value.get("version").and_then(serde_json::Value::as_u64).unwrap_or(1);
+    if version > SCHEMA_VERSION { anyhow::bail!("schema v{} is newer than this Arthema (v{})", version, SCHEMA_VERSION); }
+    if version < 2 { migrate_v1(&mut value); }
+    let req: ApiRequest = serde_json::from_value(value)?;
+    let outdated = version < SCHEMA_VERSION || req.id.is_empty();
+    Ok((req, outdated))
+}
+
+/// v1 → v2: headers de mapa a lista y `body_type` deducido del formulario o del Content-Type
+fn migrate_v1(value: &mut serde_json::Value) {
+    let Some(obj) = value.as_object_mut() else { return };
+    if let Some(serde_json::Value::Object(map)) = obj.get("headers") {
+        let list: Vec<Header> = map.iter().map(|(k, v)| Header::new(k.clone(), v.as_str().unwrap_or_default())).collect();
+        obj.insert("headers".to_string(), serde_json::to_value(list).unwrap_or_default());
+    }
+    if obj.contains_key("body_type") { return; }
+    let content_type = obj.get("headers").and_then(|h| h.as_array()).into_iter().flatten()
+        .find(|h| h["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case("content-type")))
+        .and_then(|h| h["value"].as_str()).unwrap_or("");
+    let form_kind = obj.get("form").and_then(|f| serde_json::from_value::<Form>(f.clone()).ok()).map(|f| f.kind);
+    let body_type = match form_kind {
+        Some(kind) => BodyType::from_form_kind(kind),
+        None => BodyType::from_content_type(content_type).filter(|t| t.form_kind().is_none()).unwrap_or_default(),
+    };
+    obj.insert("body_type".to_string(), serde_json::to_value(body_type).unwrap_or_default());
+}
+
+/// Peticiones de un directorio y lo que no se pudo cargar
+#[derive(Default)]
+pub struct LoadedDir {
+    pub requests: Vec<ApiRequest>,
+    /// Archivo de cada petición, en el mismo orden
+    pub files: Vec<PathBuf>,
+    /// `archivo: motivo` de los que no se pudieron leer
+    pub errors: Vec<String>,
+    /// Índices de las peticiones leídas de un esquema anterior
+    pub outdated: BTreeSet<usize>,
+}
+
+/// Carga todas las peticiones `*.json` de un directorio y sus subdirectorios, ordenadas por
+/// ruta para que el orden sea el mismo en la TUI y en `arthema run`. No escribe nada: los
+/// archivos de esquemas anteriores se devuelven migrados y marcados en `outdated`.
+pub fn load_dir(dir: &Path) -> Result<LoadedDir> {
+    let mut paths = Vec::new();
+    collect_json(dir, &mut paths);
+    paths.sort();
+    let mut loaded = LoadedDir::default();
+    for path in paths {
+        let name = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().to_string();
+        match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|c| from_json(&c)) {
+            Ok((mut req, old)) => {
+                if old { loaded.outdated.insert(loaded.requests.len()); }
+                if req.id.is_empty() { req.id = path_id(Path::new(&name)); }
+                loaded.requests.push(req);
+                loaded.files.push(path);
+            }
+            Err(e) => loaded.errors.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(loaded)
+}
+
+fn collect_json(dir: &Path, paths: &mut Vec<PathBuf>) {
+    for path in fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()) {
+        if path.is_dir() { collect_json(&path, paths); } else if path.extension().and_then(|s| s.to_str()) == Some("json") { paths.push(path); }
+    }
+}
+
+/// Auth por carpeta (`group`), guardada en `<base>/folders.json`
+pub fn load_folder_auth(base_path: &str) -> BTreeMap<String, Auth> {
+    fs::read_to_string(format!("{}/folders.json", base_path)).ok().and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default()
+}
+
+pub const FOLDER_SEP: char = '/';
+
+/// Carpeta que contiene a `path` (`None` si es de primer nivel)
+pub fn parent_folder(path: &str) -> Option<&str> { path.rsplit_once(FOLDER_SEP).map(|(p, _)| p) }
+
+/// Último tramo de la ruta: lo que se ve en el árbol
+pub fn folder_leaf(path: &str) -> &str { path.rsplit(FOLDER_SEP).next().unwrap_or(path) }
+
+/// `path` es `folder` o está dentro de ella, a cualquier profundidad
+pub fn in_folder(path: &str, folder: &str) -> bool {
+    path == folder || path.strip_prefix(folder).is_some_and(|rest| rest.starts_with(FOLDER_SEP))
+}
+
+/// Nombre de una carpeta importada: un `/` dentro del nombre crearía un nivel que no existe
+pub fn folder_segment(name: &str) -> String { name.trim().replace(FOLDER_SEP, "-") }
+
+/// Ruta escrita por el usuario sin tramos vacíos ni espacios sobrantes; vacía o UNGROUPED = sin carpeta
+pub fn normalize_folder(path: &str) -> Option<String> {
+    let path = path.split(FOLDER_SEP).map(str::trim).filter(|s| !s.is_empty()).collect::<Vec<_>>().join("/");
+    (!path.is_empty() && path != "UNGROUPED").then_some(path)
+}
+
+/// Auth de la carpeta más cercana que la defina (la propia o un ancestro) y de qué carpeta viene
+pub fn folder_auth_for<'a>(folder_auth: &'a BTreeMap<String, Auth>, group: Option<&str>) -> Option<(&'a str, &'a Auth)> {
+    let mut folder = group.unwrap_or("UNGROUPED");
+    loop {
+        if let Some((name, auth)) = folder_auth.get_key_value(folder) { return Some((name, auth)); }
+        folder = parent_folder(folder)?;
+    }
+}
+
+/// Estado del árbol en `<base>/tree.json`: carpetas creadas a mano (aunque estén vacías)
+/// y las desplegadas, para recuperarlas al volver a abrir
+#[derive(Serialize, Deserialize, Default)]
+pub struct TreeState {
+    #[serde(default)]
+    pub folders: BTreeSet<String>,
+    #[serde(default)]
+    pub expanded: BTreeSet<String>,
 }
 
 pub struct CollectionManager {
     pub requests: Vec<ApiRequest>,
-    pub history: Vec<ApiRequest>,
+    /// Archivo de cada petición por `id`
+    files: BTreeMap<String, PathBuf>,
+    /// Resultado de la última carga: archivos ilegibles
+    pub load_errors: Vec<String>,
+    /// `id` de las peticiones cargadas de un esquema anterior o fuera de su sitio: migradas
+    /// solo en memoria hasta que se guarden o se llame a `migrate`
+    pub stale: BTreeSet<String>,
+    pub history: crate::history::History,
+    pub folder_auth: BTreeMap<String, Auth>,
+    pub tree: TreeState,
+    /// Manifiesto del workspace (`None` si no se pudo abrir)
+    pub manifest: Option<crate::workspace::Manifest>,
     pub base_path: String,
 }
 
 impl CollectionManager {
-    pub fn new() -> Self {
-        let base_path = ".clicaude".to_string();
-        let coll_path = format!("{}/collections", base_path);
-        if !Path::new(&coll_path).exists() { let _ = fs::create_dir_all(&coll_path); }
-        let mut manager = Self { requests: Vec::new(), history: Vec::new(), base_path };
+    /// Abre el workspace `base_path` (lo inicializa si hace falta) y carga su contenido
+    pub fn open(base_path: &str) -> Self {
+        let base_path = base_path.to_string();
+        let manifest = crate::workspace::open(Path::new(&base_path));
+        let folder_auth = load_folder_auth(&base_path);
+        let tree = fs::read_to_string(format!("{}/tree.json", base_path)).ok().and_then(|c| serde_json::from_str(&c).ok()).unwrap_or_default();
+        let mut manager = Self { requests: Vec::new(), files: BTreeMap::new(), load_errors: Vec::new(), stale: BTreeSet::new(), history: crate::history::History::open(Path::new(&base_path)), folder_auth, tree, manifest: None, base_path };
         let _ = manager.load_all();
-        let _ = manager.load_history();
+        match manifest {
+            Ok(m) => manager.manifest = Some(m),
+            Err(e) => manager.load_errors.insert(0, format!("{}: {}", crate::workspace::MANIFEST_FILE, e)),
+        }
         manager
     }
 
+    fn collections_dir(&self) -> PathBuf { Path::new(&self.base_path).join("collections") }
+
+    /// Directorio de una carpeta: un subdirectorio por nivel
+    fn folder_dir(&self, group: Option<&str>) -> PathBuf {
+        let mut dir = self.collections_dir();
+        for segment in group.into_iter().flat_map(|g| g.split(FOLDER_SEP)) { dir.push(slugify(segment)); }
+        dir
+    }
+
+    /// Recarga del disco sin escribir nada. Los archivos de esquemas anteriores o fuera de su
+    /// sitio (movidos o copiados a mano con un `id` repetido...) quedan en `stale`.
     pub fn load_all(&mut self) -> Result<()> {
-        self.requests.clear();
-        let coll_path = format!("{}/collections", self.base_path);
-        if let Ok(entries) = fs::read_dir(coll_path) {
-            for entry in entries.flatten() {
-                if entry.path().extension().and_then(|s| s.to_str()) == Some("json") {
-                    let content = fs::read_to_string(entry.path())?;
-                    if let Ok(req) = serde_json::from_str::<ApiRequest>(&content) { self.requests.push(req); }
-                }
-            }
+        let mut loaded = load_dir(&self.collections_dir())?;
+        let mut files = BTreeMap::new();
+        let mut stale = BTreeSet::new();
+        for (i, (req, file)) in loaded.requests.iter_mut().zip(loaded.files).enumerate() {
+            let duplicate = files.contains_key(&req.id);
+            if duplicate { req.id = path_id(&file); }
+            let in_place = file.parent() == Some(self.folder_dir(req.group.as_deref()).as_path()) && stem_matches(&file, &slugify(&req.name));
+            if duplicate || !in_place || loaded.outdated.contains(&i) { stale.insert(req.id.clone()); }
+            files.insert(req.id.clone(), file);
         }
+        self.files = files;
+        self.requests = loaded.requests;
+        self.load_errors = loaded.errors;
+        self.stale = stale;
         Ok(())
     }
 
-    pub fn save_request(&self, req: &ApiRequest) -> Result<()> {
-        let path = format!("{}/collections/{}.json", self.base_path, req.name.replace(" ", "_"));
-        let content = serde_json::to_string_pretty(req)?;
-        fs::write(path, content)?;
+    /// Reescribe las peticiones de `stale` en el esquema actual y en el directorio de su
+    /// carpeta. Devuelve cuántas.
+    pub fn migrate(&mut self) -> Result<usize> {
+        let stale: Vec<ApiRequest> = self.requests.iter().filter(|r| self.stale.contains(&r.id)).cloned().collect();
+        for mut req in stale.clone() { self.save_request(&mut req)?; }
+        self.load_all()?;
+        Ok(stale.len())
+    }
+
+    /// Aviso de la última carga: archivos migrados y los que no se pudieron leer
+    pub fn load_report(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        if !self.stale.is_empty() { lines.push(format!("SYSTEM: {} request file(s) use an older collection schema or sit outside their folder. They are upgraded to v{} when saved, or all at once with `arthema migrate`.", self.stale.len(), SCHEMA_VERSION)); }
+        if let Some(w) = &self.history.warning { lines.push(format!("SYSTEM ERROR: History: {}.", w)); }
+        if !self.load_errors.is_empty() {
+            lines.push(format!("SYSTEM ERROR: {} collection file(s) could not be loaded:", self.load_errors.len()));
+            lines.extend(self.load_errors.iter().map(|e| format!("  ⚠ {}", e)));
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<usize> { self.requests.iter().position(|r| r.id == id) }
+
+    /// Petición con ese nombre en esa carpeta (no se permiten dos iguales en la misma)
+    pub fn find_in_folder(&self, name: &str, group: Option<&str>) -> Option<usize> {
+        self.requests.iter().position(|r| r.name == name && r.group.as_deref() == group)
+    }
+
+    /// Todas las carpetas (las de las peticiones, las creadas vacías, las que solo tienen auth
+    /// y sus ancestros), en el orden del árbol: cada una seguida de sus subcarpetas
+    pub fn folders(&self) -> Vec<String> {
+        let mut folders = BTreeSet::new();
+        for f in self.requests.iter().filter_map(|r| r.group.as_deref()).chain(self.tree.folders.iter().map(String::as_str)).chain(self.folder_auth.keys().map(String::as_str)) {
+            let mut f = Some(f);
+            while let Some(path) = f.filter(|p| *p != "UNGROUPED") { folders.insert(path.to_string()); f = parent_folder(path); }
+        }
+        let mut folders: Vec<String> = folders.into_iter().collect();
+        folders.sort_by(|a, b| a.split(FOLDER_SEP).cmp(b.split(FOLDER_SEP)));
+        folders
+    }
+
+    pub fn save_tree(&self) -> Result<()> {
+        fs::write(format!("{}/tree.json", self.base_path), format!("{}\n", serde_json::to_string_pretty(&self.tree)?))?;
         Ok(())
     }
 
-    pub fn delete_request(&mut self, idx: usize) -> Result<()> {
-        if let Some(req) = self.requests.get(idx) {
-            let path = format!("{}/collections/{}.json", self.base_path, req.name.replace(" ", "_"));
-            let _ = fs::remove_file(path);
-            self.requests.remove(idx);
+    fn save_folder_auth(&self) -> Result<()> {
+        fs::write(format!("{}/folders.json", self.base_path), format!("{}\n", serde_json::to_string_pretty(&self.folder_auth)?))?;
+        Ok(())
+    }
+
+    /// Los directorios salen del slug de cada nivel: "Users" y "users" (o "A B" y "a-b") caerían
+    /// en el mismo y mezclarían sus archivos. Error si `path` o un ancestro choca con otra
+    /// carpeta hermana; las que cuelgan de `ignore` (la que se está moviendo) no cuentan.
+    fn check_folder_slug(&self, path: &str, ignore: Option<&str>) -> Result<()> {
+        let folders = self.folders();
+        let mut p = Some(path);
+        while let Some(level) = p {
+            let clash = folders.iter().filter(|f| ignore.is_none_or(|i| !in_folder(f, i))).find(|f| {
+                f.as_str() != level && parent_folder(f) == parent_folder(level) && slugify(folder_leaf(f)) == slugify(folder_leaf(level))
+            });
+            if let Some(other) = clash { anyhow::bail!("folder '{}' would share its directory with '{}': choose another name", level, other); }
+            p = parent_folder(level);
         }
         Ok(())
     }
 
-    pub fn add_to_history(&mut self, req: ApiRequest) {
-        self.history.insert(0, req);
-        if self.history.len() > 50 { self.history.pop(); }
-        let _ = self.save_history();
+    pub fn create_folder(&mut self, path: &str) -> Result<()> {
+        if self.folders().iter().any(|f| f == path) { anyhow::bail!("folder '{}' already exists", path); }
+        self.check_folder_slug(path, None)?;
+        self.tree.folders.insert(path.to_string());
+        self.save_tree()
     }
 
-    pub fn delete_history_item(&mut self, idx: usize) {
-        if idx < self.history.len() {
-            self.history.remove(idx);
-            let _ = self.save_history();
+    /// Mueve o renombra una carpeta con todo lo que cuelga de ella: peticiones, subcarpetas,
+    /// auth y estado desplegado
+    pub fn move_folder(&mut self, from: &str, to: &str) -> Result<()> {
+        if from == to { return Ok(()); }
+        if in_folder(to, from) { anyhow::bail!("cannot move '{}' inside itself", from); }
+        if self.folders().iter().any(|f| f == to) { anyhow::bail!("folder '{}' already exists", to); }
+        self.check_folder_slug(to, Some(from))?;
+        let rebase = |path: &str| in_folder(path, from).then(|| format!("{}{}", to, &path[from.len()..]));
+        for mut req in self.requests.clone() {
+            if let Some(group) = req.group.as_deref().and_then(rebase) { req.group = Some(group); self.write_request(&mut req)?; }
         }
+        let rebase_all = |set: &BTreeSet<String>| set.iter().map(|p| rebase(p).unwrap_or_else(|| p.clone())).collect();
+        self.tree.folders = rebase_all(&self.tree.folders);
+        self.tree.expanded = rebase_all(&self.tree.expanded);
+        self.folder_auth = std::mem::take(&mut self.folder_auth).into_iter().map(|(k, v)| (rebase(&k).unwrap_or(k), v)).collect();
+        self.save_tree()?;
+        self.save_folder_auth()?;
+        self.load_all()
+    }
+
+    /// Borra la carpeta, sus subcarpetas y sus peticiones. Devuelve cuántas peticiones se borraron.
+    pub fn delete_folder(&mut self, path: &str) -> Result<usize> {
+        let doomed: Vec<String> = self.requests.iter().filter(|r| r.group.as_deref().is_some_and(|g| in_folder(g, path))).map(|r| r.id.clone()).collect();
+        for id in &doomed { self.remove_file(id); }
+        self.tree.folders.retain(|f| !in_folder(f, path));
+        self.tree.expanded.retain(|f| !in_folder(f, path));
+        self.folder_auth.retain(|f, _| !in_folder(f, path));
+        self.save_tree()?;
+        self.save_folder_auth()?;
+        self.load_all()?;
+        Ok(doomed.len())
+    }
+
+    pub fn move_request(&mut self, idx: usize, group: Option<String>) -> Result<()> {
+        let Some(req) = self.requests.get(idx) else { return Ok(()) };
+        self.save_request(&mut ApiRequest { group, ..req.clone() })?;
+        self.load_all()
+    }
+
+    /// Copia la petición (con `id` nuevo) en su misma carpeta como "<nombre> copy" (o "copy 2"...).
+    /// Devuelve el nombre.
+    pub fn duplicate_request(&mut self, idx: usize) -> Result<String> {
+        let Some(req) = self.requests.get(idx).cloned() else { anyhow::bail!("no request selected") };
+        let name = (1..).map(|n| if n == 1 { format!("{} copy", req.name) } else { format!("{} copy {}", req.name, n) })
+            .find(|n| self.find_in_folder(n, req.group.as_deref()).is_none()).unwrap_or_default();
+        self.save_request(&mut ApiRequest { id: String::new(), name: name.clone(), ..req })?;
+        self.load_all()?;
+        Ok(name)
+    }
+
+    /// Guarda en `collections/<carpeta>/<subcarpeta>/<slug>.json` (`<slug>-2.json`... si otra
+    /// petición ocupa ese nombre). Sin `id` se le asigna uno; si cambió de nombre o de carpeta
+    /// su archivo se mueve.
+    pub fn save_request(&mut self, req: &mut ApiRequest) -> Result<()> {
+        if let Some(group) = req.group.as_deref().filter(|g| *g != "UNGROUPED") { self.check_folder_slug(group, None)?; }
+        self.write_request(req)
     }
 
-    fn save_history(&self) -> Result<()> {
-        let path = format!("{}/history.json", self.base_path);
-        let content = serde_json::to_string_pretty(&self.history)?;
-        fs::write(path, content)?;
+    fn write_request(&mut self, req: &mut ApiRequest) -> Result<()> {
+        if req.id.is_empty() { req.id = new_id(); }
+        let dir = self.folder_dir(req.group.as_deref());
+        let slug = slugify(&req.name);
+        let old = self.files.get(&req.id).cloned();
+        let path = match &old {
+            Some(p) if p.parent() == Some(dir.as_path()) && stem_matches(p, &slug) => p.clone(),
+            _ => (1..).map(|n| dir.join(if n == 1 { format!("{}.json", slug) } else { format!("{}-{}.json", slug, n) })).find(|p| !p.exists()).unwrap_or_default(),
+        };
+        fs::create_dir_all(&dir)?;
+        // Primero el archivo nuevo: si falla, el anterior sigue intacto
+        fs::write(&path, to_json(req)?)?;
+        self.files.insert(req.id.clone(), path.clone());
+        self.stale.remove(&req.id);
+        if let Some(old) = old.filter(|o| *o != path) {
+            // Si quedara, la próxima carga vería dos archivos con el mismo id
+            match fs::remove_file(&old) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => bail!("saved to {} but could not remove {}: {}", path.display(), old.display(), e),
+                _ => self.prune_empty_dirs(&old),
+            }
+        }
         Ok(())
     }
 
-    fn load_history(&mut self) -> Result<()> {
-        let path = format!("{}/history.json", self.base_path);
-        if Path::new(&path).exists() {
-            let content = fs::read_to_string(path)?;
-            self.history = serde_json::from_str(&content)?;
+    pub fn delete_request(&mut self, idx: usize) -> Result<()> {
+        if idx < self.requests.len() {
+            let req = self.requests.remove(idx);
+            self.remove_file(&req.id);
         }
         Ok(())
     }
+
+    fn remove_file(&mut self, id: &str) {
+        if let Some(path) = self.files.remove(id) {
+            let _ = fs::remove_file(&path);
+            self.prune_empty_dirs(&path);
+        }
+    }
+
+    /// Borra los directorios de carpeta que se quedaron vacíos encima de `path`
+    fn prune_empty_dirs(&self, path: &Path) {
+        let root = self.collections_dir();
+        let mut dir = path.parent();
+        while let Some(d) = dir.filter(|d| d.starts_with(&root) && *d != root) {
+            if fs::remove_dir(d).is_err() { break; }
+            dir = d.parent();
+        }
+    }
+
+    /// Auth efectiva de una petición: la suya o la heredada de su carpeta o de un ancestro
+    pub fn effective_auth(&self, req: &ApiRequest) -> Auth {
+        crate::auth::resolve(&req.auth, folder_auth_for(&self.folder_auth, req.group.as_deref()).map(|(_, a)| a))
+    }
+
+    pub fn set_folder_auth(&mut self, folder: &str, auth: Auth) -> Result<()> {
+        if auth.is_inherit() { self.folder_auth.remove(folder); } else { self.folder_auth.insert(folder.to_string(), auth); }
+        self.save_folder_auth()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v1_files_migrate_and_bad_files_are_reported() {
+        let tmp = crate::test_util::TempDir::new("schema");
+        let dir = tmp.path();
+        fs::write(dir.join("a.json"), r#"{"name": "Soap", "url": "https://api.com", "method": "POST", "headers": {"Content-Type": "text/xml"}, "body": "<a/>", "group": null}"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{"name": "Broken", "url": 42}"#).unwrap();
+        fs::write(dir.join("c.json"), r#"{"version": 99, "name": "Future", "url": "", "method": "GET"}"#).unwrap();
+
+        let loaded = load_dir(dir).unwrap();
+        assert_eq!((loaded.requests.len(), loaded.outdated.len()), (1, 1));
+        assert_eq!(loaded.requests[0].body_type, BodyType::Xml);
+        assert_eq!(loaded.requests[0].headers, vec![Header::new("Content-Type", "text/xml")]);
+        assert!(loaded.errors[0].starts_with("b.json: "), "{:?}", loaded.errors);
+        assert!(loaded.errors[1].contains("v99"), "{:?}", loaded.errors);
+
+        // Cargar no toca el archivo y el `id` derivado de la ruta es el mismo en cada carga
+        assert!(fs::read_to_string(dir.join("a.json")).unwrap().starts_with(r#"{"name": "Soap""#));
+        let again = load_dir(dir).unwrap();
+        assert_eq!((again.outdated.len(), again.requests[0].body.as_deref()), (1, Some("<a/>")));
+        assert_eq!(again.requests[0].id, loaded.requests[0].id);
+    }
+
+    #[test]
+    fn test_v1_fixture_migrates_only_on_explicit_migrate() {
+        let tmp = crate::test_util::TempDir::new("migrate");
+        let v1 = include_str!("../.clicaude/collections/Request_1.json");
+        fs::create_dir_all(tmp.join("collections")).unwrap();
+        fs::write(tmp.join("collections/Request_1.json"), v1).unwrap();
+
+        let mut m = CollectionManager::open(&tmp.path().to_string_lossy());
+        assert_eq!((m.requests.len(), m.stale.len()), (1, 1));
+        assert_eq!(fs::read_to_string(tmp.join("collections/Request_1.json")).unwrap(), v1);
+        assert!(m.load_report().unwrap().contains("arthema migrate"));
+        let id = m.requests[0].id.clone();
+
+        assert_eq!(m.migrate().unwrap(), 1);
+        assert!(!tmp.join("collections/Request_1.json").exists());
+        let migrated = fs::read_to_string(tmp.join("collections/request-1.json")).unwrap();
+        assert!(migrated.starts_with(&format!("{{\n  \"version\": 3,\n  \"id\": \"{}\",", id)), "{}", migrated);
+        let reopened = CollectionManager::open(&tmp.path().to_string_lossy());
+        assert!(reopened.stale.is_empty() && reopened.load_report().is_none());
+        assert_eq!((reopened.requests[0].id.as_str(), reopened.requests[0].url.as_str()), (id.as_str(), "https://api.github.com/zen"));
+    }
+
+    #[test]
+    fn test_slugs_never_collide_and_files_follow_renames_and_folders() {
+        assert_eq!(slugify("Get /users/:id  (v2)"), "get-users-id-v2");
+        assert_eq!((slugify("¿¿??"), slugify("CON")), ("untitled".to_string(), "con-request".to_string()));
+
+        let tmp = crate::test_util::TempDir::new("layout");
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("collections")).unwrap();
+        // Un archivo v2 suelto en la raíz se migra a la carpeta de su grupo
+        fs::write(dir.join("collections/Old_One.json"), r#"{"version": 2, "name": "Old One", "url": "", "method": "GET", "body": null, "group": "Users/Admin"}"#).unwrap();
+        let mut m = CollectionManager::open(&dir.to_string_lossy());
+        assert_eq!(m.migrate().unwrap(), 1);
+        assert!(dir.join("collections/users/admin/old-one.json").exists());
+        assert!(!dir.join("collections/Old_One.json").exists());
+
+        let mut a = ApiRequest { name: "list users".to_string(), group: Some("Users".to_string()), ..Default::default() };
+        let mut b = ApiRequest { name: "list_users".to_string(), group: Some("Users".to_string()), ..Default::default() };
+        m.save_request(&mut a).unwrap();
+        m.save_request(&mut b).unwrap();
+        assert_ne!(a.id, b.id);
+        assert!(dir.join("collections/users/list-users.json").exists() && dir.join("collections/users/list-users-2.json").exists());
+
+        // Renombrar y mover mantiene el `id` y mueve el archivo; las carpetas vacías desaparecen
+        m.load_all().unwrap();
+        let idx = m.find_by_id(&a.id).unwrap();
+        m.save_request(&mut ApiRequest { name: "All users".to_string(), ..m.requests[idx].clone() }).unwrap();
+        assert!(!dir.join("collections/users/list-users.json").exists());
+        let idx = m.find_by_id(&b.id).unwrap();
+        m.move_request(idx, None).unwrap();
+        let idx = m.find_by_id(&a.id).unwrap();
+        m.move_request(idx, Some("People".to_string())).unwrap();
+        assert!(dir.join("collections/list-users.json").exists());
+        let content = fs::read_to_string(dir.join("collections/people/all-users.json")).unwrap();
+        assert!(content.contains(&a.id) && content.ends_with("}\n"));
+        let left: Vec<_> = fs::read_dir(dir.join("collections/users")).unwrap().flatten().map(|e| e.file_name()).collect();
+        assert_eq!(left, ["admin"]);
+        assert_eq!(m.requests.len(), 3);
+    }
+
+    #[test]
+    fn test_failed_removal_of_the_old_file_is_reported() {
+        let tmp = crate::test_util::TempDir::new("move_fail");
+        let mut m = CollectionManager::open(&tmp.path().to_string_lossy());
+        let mut req = ApiRequest { name: "list".to_string(), group: Some("Users".to_string()), ..Default::default() };
+        m.save_request(&mut req).unwrap();
+        // Un directorio donde estaba el archivo hace fallar su borrado
+        let old = tmp.join("collections/users/list.json");
+        fs::remove_file(&old).unwrap();
+        fs::create_dir_all(old.join("keep")).unwrap();
+        let err = m.save_request(&mut ApiRequest { group: Some("People".to_string()), ..req.clone() }).unwrap_err();
+        assert!(err.to_string().contains("could not remove"), "{}", err);
+        // El nuevo ya está escrito y es el que se sigue usando
+        assert!(fs::read_to_string(tmp.join("collections/people/list.json")).unwrap().contains(&req.id));
+        assert_eq!(m.files.get(&req.id), Some(&tmp.join("collections/people/list.json")));
+    }
+
+    #[test]
+    fn test_folders_sharing_a_directory_are_rejected() {
+        let tmp = crate::test_util::TempDir::new("folder_slug");
+        let mut m = CollectionManager::open(&tmp.path().to_string_lossy());
+        m.save_request(&mut ApiRequest { name: "list".to_string(), group: Some("Users".to_string()), ..Default::default() }).unwrap();
+        m.load_all().unwrap();
+        assert!(m.save_request(&mut ApiRequest { name: "list".to_string(), group: Some("users/Admin".to_string()), ..Default::default() }).is_err());
+        assert!(m.create_folder("A B").is_ok() && m.create_folder("a-b").is_err());
+        // En otro nivel el mismo nombre no choca, y cambiar sólo mayúsculas al renombrar vale
+        assert!(m.create_folder("People/users").is_ok());
+        m.move_folder("Users", "USERS").unwrap();
+        assert_eq!(m.requests[0].group.as_deref(), Some("USERS"));
+        assert!(tmp.join("collections/users/list.json").exists());
+    }
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use directories::ProjectDirs;
@@ -7,20 +8,47 @@ use anyhow::Result;
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Config {
     pub gemini_api_key: Option<String>,
+    /// Último workspace abierto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<PathBuf>,
+    /// Workspaces registrados, por nombre
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// Retención del historial de cada workspace
+    #[serde(default)]
+    pub history: crate::history::Retention,
+    /// Archivo del que se cargó; sin él `save` no escribe nada
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Por qué no se pudo leer el archivo; mientras lo haya `save` no lo sobreescribe
+    #[serde(skip)]
+    load_error: Option<String>,
 }
 
 impl Config {
-    pub fn load() -> Self {
-        if let Some(path) = Self::get_path() {
-            if let Ok(content) = fs::read_to_string(path) {
-                return serde_json::from_str(&content).unwrap_or_default();
-            }
-        }
-        Self::default()
+    pub fn load() -> Self { Self::load_from(Self::get_path()) }
+
+    /// Config de `path`. Si no existe, la de por defecto; si no se puede leer, también, pero
+    /// recordando el error para no pisar el archivo del usuario al guardar
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let read = path.as_ref().map(|p| fs::read_to_string(p).map_err(anyhow::Error::from).and_then(|c| Ok(serde_json::from_str::<Self>(&c)?)));
+        let mut config = match read {
+            Some(Ok(config)) => config,
+            Some(Err(e)) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) => Self::default(),
+            Some(Err(e)) => Self { load_error: Some(e.to_string()), ..Self::default() },
+            None => Self::default(),
+        };
+        config.path = path;
+        config
     }
 
+    pub fn load_error(&self) -> Option<&str> { self.load_error.as_deref() }
+
     pub fn save(&self) -> Result<()> {
-        if let Some(path) = Self::get_path() {
+        if let (Some(path), Some(e)) = (&self.path, &self.load_error) {
+            anyhow::bail!("{} could not be read ({}); not overwriting it", path.display(), e);
+        }
+        if let Some(path) = &self.path {
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent)?;
             }
@@ -30,8 +58,36 @@ impl Config {
         Ok(())
     }
 
+    /// Directorio de configuración de Arthema (también guarda el workspace `default`)
+    pub fn dir() -> Option<PathBuf> {
+        ProjectDirs::from("com", "arthema", "arthema").map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+    }
+
     fn get_path() -> Option<PathBuf> {
-        ProjectDirs::from("com", "arthema", "arthema")
-            .map(|proj_dirs| proj_dirs.config_dir().join("config.json"))
+        Self::dir().map(|dir| dir.join("config.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreadable_config_is_never_overwritten() {
+        let tmp = crate::test_util::TempDir::new("config");
+        let path = tmp.join("config.json");
+        fs::write(&path, r#"{"gemini_api_key": "k", "workspaces": {"api": "/w"#).unwrap();
+        let mut config = Config::load_from(Some(path.clone()));
+        assert!(config.load_error().is_some() && config.gemini_api_key.is_none());
+        config.workspace = Some(PathBuf::from("/other"));
+        assert!(config.save().is_err());
+        assert!(fs::read_to_string(&path).unwrap().contains("\"gemini_api_key\": \"k\""));
+
+        // Sin archivo no hay error y se puede guardar
+        let mut config = Config::load_from(Some(tmp.join("new.json")));
+        assert!(config.load_error().is_none());
+        config.gemini_api_key = Some("k".to_string());
+        config.save().unwrap();
+        assert_eq!(Config::load_from(Some(tmp.join("new.json"))).gemini_api_key.as_deref(), Some("k"));
     }
 }
@@ -1,12 +1,45 @@
-use std::collections::HashMap;
+use crate::auth::Auth;
+use crate::body::BodyType;
+use crate::form::{Form, FormField, FormKind};
+use crate::headers::{self, Header};
 
 pub struct ParsedCurl {
     pub method: String,
     pub url: String,
-    pub headers: HashMap<String, String>,
+    /// En orden; varios `-H` con el mismo nombre se conservan
+    pub headers: Vec<Header>,
     pub body: Option<String>,
+    /// `-F` (multipart) o un `-d clave=valor&...` sin Content-Type propio (urlencoded)
+    pub form: Option<Form>,
+    /// `--data-binary @fichero` / `-T fichero`: el fichero se manda tal cual
+    pub body_file: Option<String>,
+    pub body_type: BodyType,
+    /// `-k/--insecure`: no verificar el certificado TLS
+    pub insecure: bool,
+    /// `-u usuario:clave` (Basic); sin `-u` hereda la de la carpeta
+    pub auth: Auth,
+    /// Flags o valores que no se pudieron trasladar a la petición
+    pub warnings: Vec<String>,
 }
 
+/// Flags sin valor que no cambian la petición (salida, verbosidad, redirecciones...)
+const IGNORED_FLAGS: &[&str] = &[
+    "-L", "--location", "-i", "--include", "-s", "--silent", "-S", "--show-error", "-v", "--verbose",
+    "-f", "--fail", "-#", "--progress-bar", "--http1.1", "--http2", "--http2-prior-knowledge", "-N", "--no-buffer",
+];
+
+/// Flags cortas con valor que sí se importan
+const SHORT_WITH_VALUE: &[&str] = &["-X", "-H", "-d", "-F", "-u", "-b", "-A", "-e", "-T"];
+
+/// Flags con valor que no podemos mapear: se salta el valor y se avisa
+const FLAGS_WITH_VALUE: &[&str] = &[
+    "-o", "--output", "-m", "--max-time", "--connect-timeout", "-x", "--proxy", "-U", "--proxy-user", "-w", "--write-out",
+    "--retry", "--retry-delay", "--retry-max-time", "-c", "--cookie-jar", "-E", "--cert", "--key", "--cacert", "--capath",
+    "--resolve", "--connect-to", "-r", "--range", "--limit-rate", "-K", "--config", "-D", "--dump-header",
+    "--max-redirs", "--interface", "--noproxy", "--trace", "--trace-ascii", "--stderr", "--dns-servers", "--local-port",
+    "-y", "--speed-time", "-Y", "--speed-limit", "-z", "--time-cond", "--ciphers", "--tls-max", "--unix-socket", "--abstract-unix-socket",
+];
+
 pub fn parse_curl(curl: &str) -> Option<ParsedCurl> {
     let trimmed = curl.trim();
     if !trimmed.to_lowercase().starts_with("curl") {
@@ -15,8 +48,15 @@ pub fn parse_curl(curl: &str) -> Option<ParsedCurl> {
 
     let mut method = String::new();
     let mut url = String::new();
-    let mut headers = HashMap::new();
+    let mut headers = Vec::new();
     let mut body_parts = Vec::new();
+    let mut cookies = Vec::new();
+    let mut form_fields = Vec::new();
+    let mut body_file = None;
+    let mut insecure = false;
+    let mut auth = Auth::Inherit;
+    let mut as_query = false;
+    let mut warnings = Vec::new();
     
     // Limpiar saltos de línea y escapar caracteres de shell
     let cleaned_curl = trimmed.replace("\\\n", " ").replace("\\\r\n", " ");
@@ -26,60 +66,187 @@ pub fn parse_curl(curl: &str) -> Option<ParsedCurl> {
     iter.next(); // saltar "curl"
 
     while let Some(token) = iter.next() {
-        match token.as_str() {
+        // `--flag=valor` equivale a `--flag valor` y `-XPOST` a `-X POST`
+        let (flag, inline) = match token.split_once('=') {
+            Some((f, v)) if token.starts_with("--") => (f, Some(v.to_string())),
+            _ if is_attached_short(token) => (&token[..2], Some(token[2..].to_string())),
+            _ => (token.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| iter.next().cloned());
+        match flag {
             "-X" | "--request" => {
-                if let Some(m) = iter.next() {
+                if let Some(m) = value() {
                     method = m.to_uppercase();
                 }
             }
             "-H" | "--header" => {
-                if let Some(h) = iter.next() {
-                    if let Some((k, v)) = h.split_once(':') {
-                        headers.insert(k.trim().to_string(), v.trim().to_string());
+                if let Some(h) = value() {
+                    match h.split_once(':') {
+                        Some((k, v)) => headers.push(Header::new(k.trim(), v.trim())),
+                        None => warnings.push(format!("{} {}: not a 'Name: value' header, ignored", flag, h)),
                     }
                 }
             }
             "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => {
-                if let Some(d) = iter.next() {
-                    body_parts.push(d.clone());
-                    if method.is_empty() {
-                        method = "POST".to_string();
+                if let Some(d) = value() {
+                    if let Some(path) = d.strip_prefix('@') && flag == "--data-binary" {
+                        body_file = Some(path.to_string());
+                    } else if d.starts_with('@') && flag != "--data-raw" {
+                        warnings.push(format!("{} {}: body read from a file was not imported", flag, d));
+                    } else {
+                        body_parts.push(d);
                     }
                 }
             }
-            u if u.starts_with("http") => {
-                url = u.to_string();
+            "--data-urlencode" => {
+                if let Some(d) = value() {
+                    match url_encode_data(&d) {
+                        Some(encoded) => body_parts.push(encoded),
+                        None => warnings.push(format!("--data-urlencode {}: file contents were not imported", d)),
+                    }
+                }
+            }
+            "-F" | "--form" => {
+                if let Some(part) = value() {
+                    let (name, content) = part.split_once('=').unwrap_or((part.as_str(), ""));
+                    if content.starts_with('<') { warnings.push(format!("-F {}: field read from a file was not imported", part)); }
+                    else { form_fields.push(crate::form::parse_curl_field(name, content)); }
+                }
+            }
+            "--form-string" => {
+                if let Some(part) = value() {
+                    let (name, content) = part.split_once('=').unwrap_or((part.as_str(), ""));
+                    form_fields.push(FormField::text(name, content));
+                }
+            }
+            "-u" | "--user" => {
+                if let Some(creds) = value() {
+                    let (username, password) = creds.split_once(':').unwrap_or((creds.as_str(), ""));
+                    auth = Auth::Basic { username: username.to_string(), password: password.to_string() };
+                }
+            }
+            "--oauth2-bearer" => {
+                if let Some(token) = value() { headers::set(&mut headers, "Authorization", format!("Bearer {}", token)); }
+            }
+            "-b" | "--cookie" => {
+                if let Some(c) = value() {
+                    // Sin `=` curl lo interpreta como fichero de cookies
+                    if c.contains('=') { cookies.push(c); } else { warnings.push(format!("{} {}: cookie files are not supported", flag, c)); }
+                }
+            }
+            "-A" | "--user-agent" => {
+                if let Some(ua) = value() { headers::set(&mut headers, "User-Agent", ua); }
+            }
+            "-e" | "--referer" => {
+                if let Some(r) = value() { headers::set(&mut headers, "Referer", r); }
             }
-            flag if flag.starts_with('-') => {
-                // Otras flags que no nos interesan por ahora
-                if !["-L", "--location", "-i", "--include", "-s", "--silent"].contains(&flag) {
-                    // Si es una flag que espera valor y no la conocemos, saltamos el siguiente
-                    // Pero por ahora, el parser simple de arriba cubre lo básico.
+            "-T" | "--upload-file" => {
+                if let Some(path) = value() {
+                    body_file = Some(path);
+                    if method.is_empty() { method = "PUT".to_string(); }
                 }
             }
+            "--url" => {
+                if let Some(u) = value() { url = u; }
+            }
+            "-G" | "--get" => as_query = true,
+            "-I" | "--head" => method = "HEAD".to_string(),
+            "-k" | "--insecure" => insecure = true,
+            // Pediría gzip/br, pero las respuestas comprimidas no se descomprimen
+            "--compressed" => warnings.push("--compressed: compressed responses are not supported, ignored".to_string()),
+            f if IGNORED_FLAGS.contains(&f) => {}
+            f if is_short_cluster(f) => insecure |= f.contains('k'),
+            f if FLAGS_WITH_VALUE.contains(&f) => {
+                let v = value().unwrap_or_default();
+                warnings.push(format!("{} {}: not supported, ignored", f, v));
+            }
+            u if u.starts_with("http") => {
+                url = token.to_string();
+            }
+            f if f.starts_with('-') && f.len() > 1 => {
+                warnings.push(format!("{}: unknown flag, ignored", token));
+            }
             _ => {
                 if url.is_empty() {
                     url = token.to_string();
+                } else {
+                    warnings.push(format!("{}: unexpected argument, ignored", token));
                 }
             }
         }
     }
 
     if url.is_empty() { return None; }
-    if method.is_empty() { method = "GET".to_string(); }
-
-    Some(ParsedCurl {
-        method,
-        url,
-        headers,
-        body: if body_parts.is_empty() { None } else { Some(body_parts.join("")) },
-    })
+    if !cookies.is_empty() { headers::set(&mut headers, "Cookie", cookies.join("; ")); }
+
+    // curl une varios -d con `&`; con -G van a la query string
+    let mut body = if body_parts.is_empty() { None } else { Some(body_parts.join("&")) };
+    if as_query && let Some(query) = body.take() {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&query);
+    }
+    let mut form = None;
+    if !form_fields.is_empty() {
+        if let Some(b) = body.take() { warnings.push(format!("body '{}' dropped: a request cannot have both a body and form parts", b)); }
+        form = Some(Form { kind: FormKind::Multipart, fields: form_fields });
+    } else if let Some(b) = &body && is_urlencoded(b, &headers) {
+        let fields = b.split('&').filter_map(|p| p.split_once('=')).map(|(k, v)| FormField::text(crate::http::percent_decode(k), crate::http::percent_decode(v))).collect();
+        form = Some(Form { kind: FormKind::UrlEncoded, fields });
+        body = None;
+    }
+    if body_file.is_some() && let Some(b) = body.take() { warnings.push(format!("body '{}' dropped: the request body is read from a file", b)); }
+    if method.is_empty() {
+        method = if body.is_some() || form.is_some() || body_file.is_some() { "POST" } else { "GET" }.to_string();
+    }
+    let content_type = headers.iter().find(|h| h.name.eq_ignore_ascii_case("content-type")).map(|h| h.value.as_str()).unwrap_or("");
+    let body_type = match (&form, &body_file, &body) {
+        (Some(f), _, _) => BodyType::from_form_kind(f.kind),
+        (_, Some(_), _) => BodyType::Binary,
+        (_, _, Some(b)) => BodyType::from_content_type(content_type).unwrap_or(if b.trim_start().starts_with(['{', '[']) { BodyType::Json } else { BodyType::Text }),
+        _ => BodyType::Json,
+    };
+
+    Some(ParsedCurl { method, url, headers, body, form, body_file, body_type, insecure, auth, warnings })
+}
+
+/// Formatos de `--data-urlencode`: `contenido`, `=contenido`, `nombre=contenido`.
+/// Los que leen de fichero (`@f`, `nombre@f`) devuelven `None`.
+fn url_encode_data(d: &str) -> Option<String> {
+    if let Some((name, content)) = d.split_once('=') {
+        return Some(if name.is_empty() { crate::http::percent_encode(content) } else { format!("{}={}", name, crate::http::percent_encode(content)) });
+    }
+    if d.contains('@') { return None; }
+    Some(crate::http::percent_encode(d))
+}
+
+/// `-d` manda `application/x-www-form-urlencoded` salvo que se indique otro Content-Type;
+/// sólo se convierte en formulario si todo son pares `clave=valor`.
+fn is_urlencoded(body: &str, headers: &[Header]) -> bool {
+    let content_type = headers.iter().find(|h| h.name.eq_ignore_ascii_case("content-type")).map(|h| h.value.to_ascii_lowercase());
+    content_type.is_none_or(|ct| ct.starts_with("application/x-www-form-urlencoded"))
+        && !body.contains('\n')
+        && body.split('&').all(|pair| pair.split_once('=').is_some_and(|(k, _)| !k.is_empty() && !k.contains(['{', '[', '"'])))
+}
+
+/// Flag corta con el valor pegado: `-XPOST`, `-HAccept:x`, `-dfoo`
+fn is_attached_short(token: &str) -> bool {
+    token.len() > 2 && token.starts_with('-') && !token.starts_with("--") && token.is_char_boundary(2)
+        && (SHORT_WITH_VALUE.contains(&&token[..2]) || FLAGS_WITH_VALUE.contains(&&token[..2]))
+}
+
+/// Agrupaciones como `-sSLk` hechas sólo de flags sin valor
+fn is_short_cluster(flag: &str) -> bool {
+    flag.len() > 2 && !flag.starts_with("--") && flag[1..].chars().all(|c| "sSLivfN#k".contains(c))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn header<'a>(parsed: &'a ParsedCurl, name: &str) -> &'a str {
+        parsed.headers.iter().find(|h| h.name == name).map(|h| h.value.as_str()).unwrap()
+    }
+
     #[test]
     fn test_parse_simple_get() {
         let curl = "curl https://api.example.com/data";
@@ -93,16 +260,99 @@ mod tests {
         let curl = "curl -X POST https://api.com -H 'Content-Type: application/json' -d '{\"key\":\"val\"}'";
         let parsed = parse_curl(curl).unwrap();
         assert_eq!(parsed.method, "POST");
-        assert_eq!(parsed.headers.get("Content-Type").unwrap(), "application/json");
+        assert_eq!(header(&parsed, "Content-Type"), "application/json");
         assert_eq!(parsed.body.unwrap(), "{\"key\":\"val\"}");
     }
 
+    #[test]
+    fn test_attached_short_flags() {
+        let parsed = parse_curl("curl -XPOST -HAccept:x '-HX-Key: 1' -dfoo -uana:s3cr3t -Aagent -oout.json -sSL https://api.com").unwrap();
+        assert_eq!((parsed.method.as_str(), parsed.body.as_deref()), ("POST", Some("foo")));
+        assert_eq!((header(&parsed, "Accept"), header(&parsed, "X-Key"), header(&parsed, "User-Agent")), ("x", "1", "agent"));
+        assert_eq!(parsed.auth, Auth::Basic { username: "ana".to_string(), password: "s3cr3t".to_string() });
+        assert_eq!(parsed.warnings, vec!["-o out.json: not supported, ignored".to_string()]);
+        let parsed = parse_curl("curl -Fa=1 -Fb=2 https://api.com").unwrap();
+        assert_eq!(parsed.form.unwrap().to_text(), "a=1\nb=2");
+    }
+
     #[test]
     fn test_parse_multiline_curl() {
         let curl = "curl -X PUT https://api.com \\\n -H 'Authorization: Bearer 123' \\\n -d 'data'";
         let parsed = parse_curl(curl).unwrap();
         assert_eq!(parsed.method, "PUT");
-        assert_eq!(parsed.headers.get("Authorization").unwrap(), "Bearer 123");
+        assert_eq!(header(&parsed, "Authorization"), "Bearer 123");
         assert_eq!(parsed.body.unwrap(), "data");
     }
+
+    #[test]
+    fn test_repeated_headers_keep_order() {
+        let parsed = parse_curl("curl https://api.com -H 'Accept: text/plain' -H 'X-A: 1' -H 'Accept: application/json' -A ua -A ua2").unwrap();
+        let pairs: Vec<String> = parsed.headers.iter().map(|h| format!("{}: {}", h.name, h.value)).collect();
+        assert_eq!(pairs, vec!["Accept: text/plain", "X-A: 1", "Accept: application/json", "User-Agent: ua2"]);
+        // `-H 'X-Empty;'` (header vacío en curl) o una errata sin `:` se avisan
+        let parsed = parse_curl("curl https://api.com -H 'X-Empty;' --header Accept").unwrap();
+        assert!(parsed.headers.is_empty());
+        assert_eq!(parsed.warnings, vec!["-H X-Empty;: not a 'Name: value' header, ignored".to_string(), "--header Accept: not a 'Name: value' header, ignored".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_browser_copy_as_curl() {
+        let curl = "curl 'https://api.com/search' -H 'accept: application/json' -b 'sid=abc; theme=dark' -A 'Mozilla/5.0' -e 'https://app.com/' -u ana:secret --compressed -sSL -k";
+        let parsed = parse_curl(curl).unwrap();
+        assert_eq!(parsed.method, "GET");
+        assert_eq!(header(&parsed, "Cookie"), "sid=abc; theme=dark");
+        assert_eq!(header(&parsed, "User-Agent"), "Mozilla/5.0");
+        assert_eq!(header(&parsed, "Referer"), "https://app.com/");
+        assert_eq!(parsed.auth, Auth::Basic { username: "ana".to_string(), password: "secret".to_string() });
+        assert!(parsed.headers.iter().all(|h| h.name != "Authorization"));
+        assert!(parsed.insecure);
+        assert_eq!(parsed.warnings, vec!["--compressed: compressed responses are not supported, ignored".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_get_with_urlencoded_query() {
+        let curl = "curl -G --url https://api.com/items?page=1 --data-urlencode 'q=hello world' -d limit=5";
+        let parsed = parse_curl(curl).unwrap();
+        assert_eq!(parsed.method, "GET");
+        assert_eq!(parsed.url, "https://api.com/items?page=1&q=hello%20world&limit=5");
+        assert!(parsed.body.is_none());
+        assert_eq!(parse_curl("curl -I https://api.com").unwrap().method, "HEAD");
+    }
+
+    #[test]
+    fn test_parse_form_and_unknown_flags() {
+        let curl = "curl -F 'avatar=@/tmp/me.png;type=image/png;filename=ana.png' -F 'name=ana' -F doc=@cv.pdf --max-time 10 --frobnicate https://api.com/upload";
+        let parsed = parse_curl(curl).unwrap();
+        assert_eq!(parsed.method, "POST");
+        // El valor de --max-time no se confunde con la URL
+        assert_eq!(parsed.url, "https://api.com/upload");
+        let form = parsed.form.unwrap();
+        assert_eq!(form.kind, FormKind::Multipart);
+        assert_eq!(form.to_text(), "avatar=@/tmp/me.png;type=image/png;filename=ana.png\nname=ana\ndoc=@cv.pdf");
+        assert_eq!(parsed.warnings.len(), 2, "{:?}", parsed.warnings);
+        assert!(parsed.warnings.iter().any(|w| w.starts_with("--max-time 10")));
+        assert!(parsed.warnings.iter().any(|w| w.starts_with("--frobnicate")));
+    }
+
+    #[test]
+    fn test_data_pairs_become_urlencoded_form() {
+        let parsed = parse_curl("curl https://api.com/login -d user=ana --data-urlencode 'pass=a&b c'").unwrap();
+        assert_eq!(parsed.method, "POST");
+        assert!(parsed.body.is_none());
+        assert_eq!(parsed.form.unwrap().to_text(), "user=ana\npass=a&b c");
+        // Con otro Content-Type o sin pares clave=valor se queda como body
+        assert!(parse_curl("curl https://api.com -H 'Content-Type: text/plain' -d a=1").unwrap().form.is_none());
+        assert!(parse_curl("curl https://api.com -d '{\"a\":1}'").unwrap().form.is_none());
+    }
+
+    #[test]
+    fn test_body_types_from_files_and_content_type() {
+        let parsed = parse_curl("curl https://api.com/blob --data-binary @/tmp/a.bin").unwrap();
+        assert_eq!((parsed.method.as_str(), parsed.body_type, parsed.body_file.as_deref()), ("POST", BodyType::Binary, Some("/tmp/a.bin")));
+        let parsed = parse_curl("curl -T photo.jpg https://api.com/put").unwrap();
+        assert_eq!((parsed.method.as_str(), parsed.body_file.as_deref()), ("PUT", Some("photo.jpg")));
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+        assert_eq!(parse_curl("curl https://api.com -H 'Content-Type: text/xml' -d '<a/>'").unwrap().body_type, BodyType::Xml);
+        assert_eq!(parse_curl("curl https://api.com -d '[1]'").unwrap().body_type, BodyType::Json);
+    }
 }
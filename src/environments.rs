@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    /// Archivo del que se cargó: se guarda ahí aunque su nombre no coincida con `name`
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+pub struct EnvironmentManager {
+    pub environments: Vec<Environment>,
+    pub active: Option<usize>,
+    pub base_path: String,
+    /// Variables creadas por scripts cuando no hay entorno activo (solo en memoria)
+    pub runtime: BTreeMap<String, String>,
+    /// `archivo: motivo` de los entornos que no se pudieron leer en la última carga
+    pub load_errors: Vec<String>,
+}
+
+impl EnvironmentManager {
+    pub fn new(base_path: &str) -> Self {
+        let env_path = format!("{}/environments", base_path);
+        if !Path::new(&env_path).exists() { let _ = fs::create_dir_all(&env_path); }
+        let mut manager = Self { environments: Vec::new(), active: None, base_path: base_path.to_string(), runtime: BTreeMap::new(), load_errors: Vec::new() };
+        manager.load_all();
+        manager
+    }
+
+    /// Relee `environments/*.json`. Los archivos ilegibles o con JSON inválido no paran la
+    /// carga: quedan en `load_errors`.
+    pub fn load_all(&mut self) {
+        let active_name = self.active_name().map(|s| s.to_string());
+        self.environments.clear();
+        self.load_errors.clear();
+        let env_path = format!("{}/environments", self.base_path);
+        if let Ok(entries) = fs::read_dir(env_path) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("json") { continue; }
+                match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|c| Ok(serde_json::from_str::<Environment>(&c)?)) {
+                    Ok(env) => self.environments.push(Environment { path: Some(path), ..env }),
+                    Err(e) => self.load_errors.push(format!("{}: {}", entry.file_name().to_string_lossy(), e)),
+                }
+            }
+        }
+        // read_dir no garantiza orden: ordenamos para que el ciclo sea estable
+        self.environments.sort_by(|a, b| a.name.cmp(&b.name));
+        self.load_errors.sort();
+        self.active = active_name.and_then(|n| self.environments.iter().position(|e| e.name == n));
+    }
+
+    /// Aviso de la última carga con los entornos que no se pudieron leer
+    pub fn load_report(&self) -> Option<String> {
+        if self.load_errors.is_empty() { return None; }
+        let mut lines = vec![format!("SYSTEM ERROR: {} environment file(s) could not be loaded:", self.load_errors.len())];
+        lines.extend(self.load_errors.iter().map(|e| format!("  ⚠ {}", e)));
+        Some(lines.join("\n"))
+    }
+
+    /// Ciclo: sin entorno -> primer entorno -> ... -> último -> sin entorno
+    pub fn cycle(&mut self) {
+        if self.environments.is_empty() { self.active = None; return; }
+        self.active = match self.active {
+            None => Some(0),
+            Some(i) if i + 1 < self.environments.len() => Some(i + 1),
+            Some(_) => None,
+        };
+    }
+
+    pub fn active_env(&self) -> Option<&Environment> { self.active.and_then(|i| self.environments.get(i)) }
+    pub fn active_name(&self) -> Option<&str> { self.active_env().map(|e| e.name.as_str()) }
+
+    pub fn save(&self, env: &Environment) -> Result<()> {
+        let path = env.path.clone().unwrap_or_else(|| Path::new(&self.base_path).join("environments").join(format!("{}.json", env.name.replace(" ", "_"))));
+        let content = serde_json::to_string_pretty(env)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Variables visibles: las de runtime, sobreescritas por las del entorno activo
+    pub fn variables(&self) -> BTreeMap<String, String> {
+        let mut vars = self.runtime.clone();
+        if let Some(env) = self.active_env() { vars.extend(env.variables.clone()); }
+        vars
+    }
+
+    /// Guarda variables (p.ej. capturadas por un script) en el entorno activo y lo persiste.
+    /// Sin entorno activo quedan en memoria hasta cerrar Arthema.
+    pub fn set_vars(&mut self, vars: BTreeMap<String, String>) -> Result<()> {
+        if vars.is_empty() { return Ok(()); }
+        match self.active.and_then(|i| self.environments.get_mut(i)) {
+            Some(env) => {
+                env.variables.extend(vars);
+                let env = env.clone();
+                self.save(&env)
+            }
+            None => { self.runtime.extend(vars); Ok(()) }
+        }
+    }
+}
+
+/// Reemplaza `{{nombre}}` por su valor. Los placeholders desconocidos se dejan tal cual
+/// para que el usuario vea en la respuesta/servidor qué variable faltó.
+pub fn substitute_vars(input: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let key = after[..end].trim();
+                match vars.get(key) {
+                    Some(v) => out.push_str(v),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => { out.push_str(&rest[start..]); rest = ""; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> BTreeMap<String, String> {
+        let mut v = BTreeMap::new();
+        v.insert("host".to_string(), "api.dev.com".to_string());
+        v.insert("token".to_string(), "abc123".to_string());
+        v
+    }
+
+    #[test]
+    fn test_substitute_known_and_unknown_vars() {
+        let out = substitute_vars("https://{{host}}/users?t={{ token }}&x={{missing}}", &vars());
+        assert_eq!(out, "https://api.dev.com/users?t=abc123&x={{missing}}");
+    }
+
+    #[test]
+    fn test_substitute_unclosed_placeholder() {
+        assert_eq!(substitute_vars("Bearer {{token", &vars()), "Bearer {{token");
+    }
+
+    #[test]
+    fn test_cycle_environments() {
+        let mut manager = EnvironmentManager { environments: Vec::new(), active: None, base_path: String::new(), runtime: BTreeMap::new(), load_errors: Vec::new() };
+        manager.environments.push(Environment { name: "Dev".to_string(), variables: vars(), path: None });
+        manager.environments.push(Environment { name: "Prod".to_string(), variables: BTreeMap::new(), path: None });
+
+        assert_eq!(substitute_vars("{{host}}", &manager.variables()), "{{host}}");
+        manager.cycle();
+        assert_eq!(manager.active_name(), Some("Dev"));
+        assert_eq!(substitute_vars("{{host}}", &manager.variables()), "api.dev.com");
+        manager.cycle();
+        assert_eq!(manager.active_name(), Some("Prod"));
+        manager.cycle();
+        assert_eq!(manager.active_name(), None);
+    }
+
+    #[test]
+    fn test_runtime_vars_without_active_env() {
+        let mut manager = EnvironmentManager { environments: Vec::new(), active: None, base_path: String::new(), runtime: BTreeMap::new(), load_errors: Vec::new() };
+        manager.set_vars(vars()).unwrap();
+        assert_eq!(substitute_vars("{{token}}", &manager.variables()), "abc123");
+    }
+
+    #[test]
+    fn test_set_vars_writes_back_to_the_loaded_file() {
+        let tmp = crate::test_util::TempDir::new("env_save");
+        fs::create_dir_all(tmp.join("environments")).unwrap();
+        fs::write(tmp.join("environments/dev-local.json"), r#"{"name": "Dev Local", "variables": {"host": "localhost"}}"#).unwrap();
+        let mut manager = EnvironmentManager::new(tmp.path().to_str().unwrap());
+        manager.cycle();
+        manager.set_vars(vars()).unwrap();
+        manager.load_all();
+        assert_eq!(manager.environments.len(), 1);
+        assert_eq!(manager.environments[0].variables.get("token").map(String::as_str), Some("abc123"));
+        assert!(!tmp.join("environments/Dev_Local.json").exists());
+    }
+
+    #[test]
+    fn test_bad_environment_files_are_reported() {
+        let tmp = crate::test_util::TempDir::new("env_errors");
+        fs::create_dir_all(tmp.join("environments/dir.json")).unwrap();
+        fs::write(tmp.join("environments/broken.json"), "{ not json").unwrap();
+        fs::write(tmp.join("environments/dev.json"), r#"{"name": "Dev"}"#).unwrap();
+        let manager = EnvironmentManager::new(tmp.path().to_str().unwrap());
+        assert_eq!(manager.environments.len(), 1);
+        assert_eq!(manager.load_errors.len(), 2, "{:?}", manager.load_errors);
+        assert!(manager.load_errors[0].starts_with("broken.json: ") && manager.load_errors[1].starts_with("dir.json: "));
+        assert!(manager.load_report().unwrap().starts_with("SYSTEM ERROR: 2 environment file(s) could not be loaded:"));
+    }
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::environments::substitute_vars;
+use crate::http::percent_encode;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FormKind { #[default] Multipart, UrlEncoded }
+
+/// Body de formulario. En el editor se escribe un campo por línea, con la sintaxis de `curl -F`:
+///
+/// ```text
+/// name=Ana
+/// # debug=1                                      (desactivado)
+/// avatar=@/tmp/me.png;type=image/png;filename=ana.png   (fichero, sólo multipart)
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Form {
+    pub kind: FormKind,
+    pub fields: Vec<FormField>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    /// Texto, o la ruta del fichero si `file`
+    pub value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub file: bool,
+    /// Nombre con el que se sube el fichero (por defecto el de la ruta)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// Por defecto se deduce de la extensión
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool { true }
+fn is_enabled(b: &bool) -> bool { *b }
+
+impl FormField {
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self { name: name.into(), value: value.into(), file: false, filename: None, content_type: None, enabled: true }
+    }
+
+    pub fn file(name: impl Into<String>, path: impl Into<String>) -> Self {
+        Self { file: true, ..Self::text(name, path) }
+    }
+
+    pub fn file_name(&self) -> String {
+        self.filename.clone().or_else(|| Path::new(&self.value).file_name().map(|n| n.to_string_lossy().to_string())).unwrap_or_else(|| "upload".to_string())
+    }
+
+    pub fn mime(&self) -> String {
+        self.content_type.clone().unwrap_or_else(|| guess_mime(&self.value).to_string())
+    }
+
+    /// Valor de `curl -F`: `@ruta;type=..;filename=..` para ficheros
+    pub fn curl_value(&self) -> String {
+        if !self.file { return self.value.clone(); }
+        let mut out = format!("@{}", self.value);
+        if let Some(t) = &self.content_type { out.push_str(&format!(";type={}", t)); }
+        if let Some(f) = &self.filename { out.push_str(&format!(";filename={}", f)); }
+        out
+    }
+}
+
+impl Form {
+    pub fn parse(text: &str, kind: FormKind) -> Self {
+        let fields = text.lines().filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() { return None; }
+            let (line, enabled) = match line.strip_prefix('#') { Some(rest) => (rest.trim_start(), false), None => (line, true) };
+            let (name, value) = line.split_once('=').map(|(k, v)| (k.trim(), v.trim())).unwrap_or((line, ""));
+            let field = if kind == FormKind::Multipart { parse_curl_field(name, value) } else { FormField::text(name, value) };
+            Some(FormField { enabled, ..field })
+        }).collect();
+        Self { kind, fields }
+    }
+
+    pub fn to_text(&self) -> String {
+        self.fields.iter().map(|f| format!("{}{}={}", if f.enabled { "" } else { "# " }, f.name, f.curl_value())).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &FormField> {
+        self.fields.iter().filter(|f| f.enabled && !f.name.is_empty())
+    }
+
+    /// Body `application/x-www-form-urlencoded` con los campos activos
+    pub fn encoded(&self) -> String {
+        self.active().map(|f| format!("{}={}", percent_encode(&f.name), percent_encode(&f.value))).collect::<Vec<_>>().join("&")
+    }
+
+    pub fn substitute(&mut self, vars: &BTreeMap<String, String>) {
+        for f in self.fields.iter_mut() {
+            f.name = substitute_vars(&f.name, vars);
+            f.value = substitute_vars(&f.value, vars);
+            f.filename = f.filename.as_deref().map(|n| substitute_vars(n, vars));
+        }
+    }
+
+    /// Partes multipart leyendo los ficheros del disco
+    pub fn to_multipart(&self) -> anyhow::Result<reqwest::multipart::Form> {
+        let mut form = reqwest::multipart::Form::new();
+        for f in self.active() {
+            form = if f.file {
+                let bytes = std::fs::read(&f.value).map_err(|e| anyhow::anyhow!("form file '{}' ({}): {}", f.name, f.value, e))?;
+                form.part(f.name.clone(), reqwest::multipart::Part::bytes(bytes).file_name(f.file_name()).mime_str(&f.mime())?)
+            } else {
+                form.text(f.name.clone(), f.value.clone())
+            };
+        }
+        Ok(form)
+    }
+}
+
+/// `@ruta;type=..;filename=..` → fichero; cualquier otro valor es texto
+pub fn parse_curl_field(name: &str, value: &str) -> FormField {
+    let Some(spec) = value.strip_prefix('@') else { return FormField::text(name, value) };
+    let mut options = spec.split(';');
+    let mut field = FormField::file(name, options.next().unwrap_or("").trim());
+    for opt in options {
+        match opt.trim().split_once('=') {
+            Some(("type", t)) => field.content_type = Some(t.trim().to_string()),
+            Some(("filename", f)) => field.filename = Some(f.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    field
+}
+
+/// Content-Type por extensión (lo que haría `curl -F`)
+pub fn guess_mime(path: &str) -> &'static str {
+    let ext = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_text_roundtrip() {
+        let text = "name=Ana\n# debug=1\navatar=@/tmp/me.png;type=image/webp;filename=ana.png\ndoc=@/tmp/cv.pdf";
+        let form = Form::parse(text, FormKind::Multipart);
+        assert_eq!(form.fields.len(), 4);
+        assert!(!form.fields[1].enabled);
+        let avatar = &form.fields[2];
+        assert!(avatar.file);
+        assert_eq!((avatar.value.as_str(), avatar.file_name(), avatar.mime()), ("/tmp/me.png", "ana.png".to_string(), "image/webp".to_string()));
+        assert_eq!((form.fields[3].file_name(), form.fields[3].mime()), ("cv.pdf".to_string(), "application/pdf".to_string()));
+        assert_eq!(form.to_text(), text);
+    }
+
+    #[test]
+    fn test_urlencoded_keeps_at_as_text() {
+        let form = Form::parse("q=a b&c\nmail=@ana\n# off=1", FormKind::UrlEncoded);
+        assert!(form.fields.iter().all(|f| !f.file));
+        assert_eq!(form.encoded(), "q=a%20b%26c&mail=%40ana");
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Body GraphQL tal como se guarda: query, variables (texto JSON, admite `{{var}}`) y operación
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct GraphQlBody {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub variables: String,
+    /// Necesaria si el documento define varias operaciones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_name: Option<String>,
+}
+
+impl GraphQlBody {
+    /// `{"query": .., "variables": .., "operationName": ..}`. Las variables se insertan tal cual
+    /// para que `{{var}}` se sustituya después; se validan ya sustituidas al enviar.
+    pub fn to_body(&self) -> String {
+        let mut out = format!("{{\"query\":{}", serde_json::to_string(&self.query).unwrap_or_default());
+        let vars = self.variables.trim();
+        if !vars.is_empty() { out.push_str(&format!(",\"variables\":{}", vars)); }
+        if let Some(op) = &self.operation_name { out.push_str(&format!(",\"operationName\":{}", serde_json::to_string(op).unwrap_or_default())); }
+        out.push('}');
+        out
+    }
+}
+
+/// Nombres de las operaciones con nombre del documento (`query Foo`, `mutation Bar`...)
+pub fn operation_names(query: &str) -> Vec<String> {
+    let words = top_level_words(query);
+    words.windows(2).filter(|w| matches!(w[0].as_str(), "query" | "mutation" | "subscription")).map(|w| w[1].clone())
+        .filter(|n| n.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')).collect()
+}
+
+/// Palabras fuera de llaves, paréntesis, strings y comentarios
+fn top_level_words(query: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let (mut depth, mut word) = (0i32, String::new());
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => { for c in chars.by_ref() { if c == '\n' { break; } } }
+            '"' => { while let Some(c) = chars.next() { if c == '\\' { chars.next(); } else if c == '"' { break; } } }
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => { word.push(c); continue; }
+            _ => {}
+        }
+        if !word.is_empty() { words.push(std::mem::take(&mut word)); }
+    }
+    if !word.is_empty() { words.push(word); }
+    words
+}
+
+/// Query de introspección reducida: sólo lo necesario para autocompletar campos
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery { __schema { queryType { name } mutationType { name } subscriptionType { name } types { name fields(includeDeprecated: true) { name type { name ofType { name ofType { name ofType { name ofType { name } } } } } } } } }";
+
+/// Tipos y campos del schema (cada campo con su tipo ya sin `!`/`[]`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub query: Option<String>,
+    pub mutation: Option<String>,
+    pub subscription: Option<String>,
+    pub types: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Schema {
+    pub fn from_introspection(json: &Value) -> Result<Self, String> {
+        let schema = json.pointer("/data/__schema").ok_or_else(|| match json.pointer("/errors/0/message").and_then(Value::as_str) {
+            Some(msg) => format!("introspection failed: {}", msg),
+            None => "response has no data.__schema".to_string(),
+        })?;
+        let root = |key: &str| schema.pointer(&format!("/{}/name", key)).and_then(Value::as_str).map(str::to_string);
+        let types = schema["types"].as_array().into_iter().flatten().filter_map(|t| {
+            let fields = t["fields"].as_array()?.iter().filter_map(|f| Some((f["name"].as_str()?.to_string(), named_type(&f["type"])?))).collect();
+            Some((t["name"].as_str()?.to_string(), fields))
+        }).collect();
+        Ok(Self { query: root("queryType"), mutation: root("mutationType"), subscription: root("subscriptionType"), types })
+    }
+
+    /// Campos que completan la palabra que hay justo antes del cursor
+    pub fn suggestions(&self, before_cursor: &str) -> Vec<&str> {
+        let prefix: String = before_cursor.chars().rev().take_while(|c| c.is_alphanumeric() || *c == '_').collect::<Vec<_>>().into_iter().rev().collect();
+        let Some(ty) = self.type_at(&before_cursor[..before_cursor.len() - prefix.len()]) else { return Vec::new() };
+        let lower = prefix.to_lowercase();
+        self.types.get(&ty).into_iter().flatten().map(|(name, _)| name.as_str())
+            .filter(|n| n.to_lowercase().starts_with(&lower) && n.len() > prefix.len() && !n.starts_with("__")).collect()
+    }
+
+    /// Tipo de la selección abierta al final del texto recorriendo llaves, alias y fragments
+    fn type_at(&self, text: &str) -> Option<String> {
+        let mut stack: Vec<Option<String>> = Vec::new();
+        let mut pending: Option<String> = None;
+        let mut prev = String::new();
+        let (mut parens, mut spread) = (0, false);
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if parens > 0 { match c { '(' => parens += 1, ')' => parens -= 1, _ => {} } continue; }
+            match c {
+                '#' => { for c in chars.by_ref() { if c == '\n' { break; } } }
+                '"' => { while let Some(c) = chars.next() { if c == '\\' { chars.next(); } else if c == '"' { break; } } }
+                '(' => parens = 1,
+                '.' => spread = true,
+                '{' => {
+                    let root = if stack.is_empty() && pending.is_none() { self.query.clone() } else { None };
+                    stack.push(pending.take().or(root));
+                    spread = false;
+                }
+                '}' => { stack.pop(); pending = None; }
+                c if c.is_alphanumeric() || c == '_' => {
+                    let mut word = c.to_string();
+                    while let Some(&n) = chars.peek() { if n.is_alphanumeric() || n == '_' { word.push(n); chars.next(); } else { break; } }
+                    let parent = stack.last().cloned().flatten();
+                    pending = match (stack.is_empty(), word.as_str()) {
+                        (true, "query") => self.query.clone(),
+                        (true, "mutation") => self.mutation.clone(),
+                        (true, "subscription") => self.subscription.clone(),
+                        // `fragment X on Tipo` / `... on Tipo`
+                        _ if prev == "on" => Some(word.clone()),
+                        (true, _) => pending.take(),
+                        (false, "on") if spread => None,
+                        // `...NombreFragment` no abre selección
+                        _ if spread => { spread = false; None }
+                        _ => parent.and_then(|p| self.field_type(&p, &word)),
+                    };
+                    prev = word;
+                }
+                _ => {}
+            }
+        }
+        stack.pop().flatten()
+    }
+
+    fn field_type(&self, parent: &str, field: &str) -> Option<String> {
+        self.types.get(parent)?.iter().find(|(n, _)| n == field).map(|(_, t)| t.clone())
+    }
+}
+
+/// `NON_NULL`/`LIST` → tipo con nombre
+fn named_type(t: &Value) -> Option<String> {
+    match t["name"].as_str() {
+        Some(name) => Some(name.to_string()),
+        None => named_type(t.get("ofType")?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        let json = serde_json::json!({"data": {"__schema": {
+            "queryType": {"name": "Query"}, "mutationType": {"name": "Mutation"}, "subscriptionType": null,
+            "types": [
+                {"name": "Query", "fields": [{"name": "user", "type": {"name": "User"}}, {"name": "users", "type": {"name": null, "ofType": {"name": null, "ofType": {"name": "User"}}}}]},
+                {"name": "Mutation", "fields": [{"name": "login", "type": {"name": "String"}}]},
+                {"name": "User", "fields": [{"name": "id", "type": {"name": "ID"}}, {"name": "name", "type": {"name": "String"}}, {"name": "friends", "type": {"name": null, "ofType": {"name": "User"}}}]},
+                {"name": "String", "fields": null}
+            ]
+        }}});
+        Schema::from_introspection(&json).unwrap()
+    }
+
+    #[test]
+    fn test_body_and_operation_names() {
+        let body = GraphQlBody { query: "query A { x }\nmutation B($id: ID) { y(id: $id) }".to_string(), variables: "{\"id\": {{id}}}".to_string(), operation_name: Some("B".to_string()) };
+        assert_eq!(body.to_body(), "{\"query\":\"query A { x }\\nmutation B($id: ID) { y(id: $id) }\",\"variables\":{\"id\": {{id}}},\"operationName\":\"B\"}");
+        assert_eq!(operation_names(&body.query), vec!["A", "B"]);
+        assert!(operation_names("{ users { id } }").is_empty());
+    }
+
+    #[test]
+    fn test_field_suggestions_follow_the_selection() {
+        let s = schema();
+        assert_eq!(s.suggestions("{ us"), vec!["user", "users"]);
+        assert_eq!(s.suggestions("query Q($id: ID) { user(id: \"}\") { friends { n"), vec!["name"]);
+        assert_eq!(s.suggestions("query { users { id } u"), vec!["user", "users"]);
+        assert_eq!(s.suggestions("mutation { lo"), vec!["login"]);
+        assert_eq!(s.suggestions("fragment F on User { fr"), vec!["friends"]);
+        assert_eq!(s.suggestions("{ user { a: fri"), vec!["friends"]);
+        assert!(s.suggestions("{ user { name { x").is_empty());
+    }
+}
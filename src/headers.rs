@@ -0,0 +1,153 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+/// Header de una petición. Se guardan como lista ordenada para conservar duplicados
+/// (`Accept`, `Cookie`...) y el orden en que se escribieron.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool { true }
+fn is_enabled(b: &bool) -> bool { *b }
+
+impl Header {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self { Self { name: name.into(), value: value.into(), enabled: true } }
+}
+
+/// Acepta la lista actual y el formato antiguo `{"Nombre": "valor"}`
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Header>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr { List(Vec<Header>), Map(BTreeMap<String, String>) }
+    Ok(match Repr::deserialize(d)? {
+        Repr::List(list) => list,
+        Repr::Map(map) => map.into_iter().map(|(k, v)| Header::new(k, v)).collect(),
+    })
+}
+
+/// Una línea `Nombre: valor` por header; `# Nombre: valor` lo desactiva
+pub fn parse_text(text: &str) -> Vec<Header> {
+    text.lines().filter_map(|line| {
+        let line = line.trim();
+        let (line, enabled) = match line.strip_prefix('#') { Some(rest) => (rest.trim_start(), false), None => (line, true) };
+        let (name, value) = line.split_once(':')?;
+        let name = name.trim();
+        if name.is_empty() { return None; }
+        Some(Header { name: name.to_string(), value: value.trim().to_string(), enabled })
+    }).collect()
+}
+
+pub fn to_text(headers: &[Header]) -> String {
+    headers.iter().map(|h| format!("{}{}: {}", if h.enabled { "" } else { "# " }, h.name, h.value)).collect::<Vec<_>>().join("\n")
+}
+
+pub fn enabled_pairs(headers: &[Header]) -> Vec<(String, String)> {
+    headers.iter().filter(|h| h.enabled).map(|h| (h.name.clone(), h.value.clone())).collect()
+}
+
+/// Reemplaza el primer header con ese nombre (sin distinguir mayúsculas) o lo añade al final
+pub fn set(headers: &mut Vec<Header>, name: &str, value: String) {
+    match headers.iter_mut().find(|h| h.name.eq_ignore_ascii_case(name)) {
+        Some(h) => { h.value = value; h.enabled = true; }
+        None => headers.push(Header::new(name, value)),
+    }
+}
+
+/// Activa/desactiva una línea del editor (añade o quita el `# `)
+pub fn toggle_line(line: &str) -> String {
+    match line.trim_start().strip_prefix('#') {
+        Some(rest) => rest.trim_start().to_string(),
+        None if line.trim().is_empty() => line.to_string(),
+        None => format!("# {}", line.trim_start()),
+    }
+}
+
+const HEADER_NAMES: &[&str] = &[
+    "Accept", "Accept-Charset", "Accept-Encoding", "Accept-Language", "Authorization", "Cache-Control", "Connection",
+    "Content-Disposition", "Content-Encoding", "Content-Language", "Content-Length", "Content-Type", "Cookie", "DNT",
+    "Forwarded", "From", "Host", "If-Match", "If-Modified-Since", "If-None-Match", "If-Unmodified-Since", "Origin",
+    "Pragma", "Prefer", "Range", "Referer", "TE", "Upgrade", "User-Agent", "Via",
+    "X-Api-Key", "X-Correlation-Id", "X-CSRF-Token", "X-Forwarded-For", "X-Forwarded-Host", "X-Forwarded-Proto", "X-Request-Id", "X-Requested-With",
+];
+
+fn common_values(name: &str) -> &'static [&'static str] {
+    match name.to_ascii_lowercase().as_str() {
+        "content-type" | "accept" => &["application/json", "application/xml", "application/x-www-form-urlencoded", "multipart/form-data", "text/plain", "text/html", "text/event-stream", "application/octet-stream", "*/*"],
+        "accept-encoding" | "content-encoding" => &["gzip", "deflate", "br", "identity", "gzip, deflate, br"],
+        "accept-language" => &["en-US", "es-ES", "en-US,en;q=0.9", "*"],
+        "authorization" => &["Bearer ", "Basic "],
+        "cache-control" => &["no-cache", "no-store", "max-age=0", "must-revalidate", "public", "private"],
+        "connection" => &["keep-alive", "close", "upgrade"],
+        "pragma" => &["no-cache"],
+        "prefer" => &["return=representation", "return=minimal", "respond-async"],
+        "x-requested-with" => &["XMLHttpRequest"],
+        "dnt" => &["1", "0"],
+        _ => &[],
+    }
+}
+
+/// Sugerencias para la línea en edición: nombres si el cursor está antes de `:`,
+/// valores comunes del header si está después.
+pub fn suggestions(line: &str, cursor_col: usize) -> Vec<&'static str> {
+    let before: String = line.chars().take(cursor_col).collect();
+    let before = before.trim_start().trim_start_matches('#').trim_start();
+    match before.split_once(':') {
+        None if before.is_empty() => Vec::new(),
+        None => HEADER_NAMES.iter().copied().filter(|n| n.len() > before.len() && n.to_ascii_lowercase().starts_with(&before.to_ascii_lowercase())).collect(),
+        Some((name, value)) => {
+            let value = value.trim_start().to_ascii_lowercase();
+            common_values(name.trim()).iter().copied().filter(|v| v.to_ascii_lowercase().starts_with(&value) && v.len() > value.len()).collect()
+        }
+    }
+}
+
+/// Línea resultante de aceptar una sugerencia (conserva el `# ` si lo había)
+pub fn complete_line(line: &str, suggestion: &str) -> String {
+    let prefix = if line.trim_start().starts_with('#') { "# " } else { "" };
+    let body = line.trim_start().trim_start_matches('#').trim_start();
+    match body.split_once(':') {
+        None => format!("{}{}: ", prefix, suggestion),
+        Some((name, _)) => format!("{}{}: {}", prefix, name.trim(), suggestion),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_keeps_duplicates_order_and_toggles() {
+        let text = "Accept: application/json\n# X-Debug: 1\nAccept: text/plain";
+        let headers = parse_text(text);
+        assert_eq!(headers.len(), 3);
+        assert!(!headers[1].enabled);
+        assert_eq!(to_text(&headers), text);
+        assert_eq!(enabled_pairs(&headers).iter().map(|(_, v)| v.as_str()).collect::<Vec<_>>(), vec!["application/json", "text/plain"]);
+        assert_eq!(toggle_line("X-Debug: 1"), "# X-Debug: 1");
+        assert_eq!(toggle_line("#X-Debug: 1"), "X-Debug: 1");
+    }
+
+    #[test]
+    fn test_deserialize_list_and_legacy_map() {
+        #[derive(Deserialize)]
+        struct R { #[serde(deserialize_with = "deserialize")] headers: Vec<Header> }
+        let legacy: R = serde_json::from_str(r#"{"headers": {"b": "2", "a": "1"}}"#).unwrap();
+        assert_eq!(legacy.headers, vec![Header::new("a", "1"), Header::new("b", "2")]);
+        let list: R = serde_json::from_str(r#"{"headers": [{"name": "b", "value": "2"}, {"name": "a", "value": "1", "enabled": false}]}"#).unwrap();
+        assert_eq!(list.headers[0], Header::new("b", "2"));
+        assert!(!list.headers[1].enabled);
+    }
+
+    #[test]
+    fn test_suggestions_and_completion() {
+        assert_eq!(suggestions("cont", 4), vec!["Content-Disposition", "Content-Encoding", "Content-Language", "Content-Length", "Content-Type"]);
+        assert_eq!(suggestions("Content-Type: application/j", 27), vec!["application/json"]);
+        assert!(suggestions("Content-Type: application/json", 30).is_empty());
+        assert_eq!(complete_line("# user-a", "User-Agent"), "# User-Agent: ");
+        assert_eq!(complete_line("Accept: te", "text/plain"), "Accept: text/plain");
+    }
+}
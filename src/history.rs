@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate};
+use crate::collections::ApiRequest;
+use crate::response::ApiResponse;
+
+/// Historial del workspace: una entrada JSON por línea, de la más vieja a la más nueva.
+/// Enviar solo añade una línea; el archivo se reescribe al borrar o al aplicar la retención.
+pub const HISTORY_FILE: &str = "history.jsonl";
+/// Formato anterior (un array con las peticiones), se migra al abrir
+const LEGACY_FILE: &str = "history.json";
+/// Bytes del body que se guardan con cada entrada
+pub const BODY_LIMIT: usize = 4096;
+
+/// Cuánto historial se conserva (`history` en la config)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Retention {
+    pub max_entries: usize,
+    /// Días; sin límite si es `None`
+    #[serde(default)]
+    pub max_days: Option<u32>,
+}
+
+impl Default for Retention {
+    fn default() -> Self { Self { max_entries: 1000, max_days: Some(90) } }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HistoryEntry {
+    pub id: String,
+    /// RFC 3339, hora local
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Tal como se envió pero con las plantillas ({{var}}) sin sustituir
+    pub request: ApiRequest,
+    /// `None` si no hubo respuesta (ver `error`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// Tamaño completo del body, aunque solo se guarden `BODY_LIMIT` bytes
+    #[serde(default)]
+    pub size: usize,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(request: ApiRequest, environment: Option<String>) -> Self {
+        Self { id: crate::collections::new_id(), timestamp: Local::now().to_rfc3339(), environment, request, ..Default::default() }
+    }
+
+    pub fn with_response(mut self, resp: &ApiResponse) -> Self {
+        self.status = Some(resp.status);
+        self.elapsed_ms = resp.elapsed.as_millis() as u64;
+        self.size = resp.body.len();
+        // Los bodies binarios no se guardan: solo su tamaño
+        if !resp.is_binary() {
+            let mut body = resp.body_text();
+            if body.len() > BODY_LIMIT {
+                let cut = (0..=BODY_LIMIT).rev().find(|&i| body.is_char_boundary(i)).unwrap_or(0);
+                body.truncate(cut);
+                self.truncated = true;
+            }
+            self.body = body;
+        }
+        self
+    }
+
+    pub fn with_error(mut self, error: String, elapsed: std::time::Duration) -> Self {
+        self.error = Some(error);
+        self.elapsed_ms = elapsed.as_millis() as u64;
+        self
+    }
+
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.timestamp).ok().map(|t| t.with_timezone(&Local))
+    }
+
+    /// "200 · 123 ms · 1.2 KB", o el error si no hubo respuesta
+    pub fn outcome(&self) -> String {
+        match (self.status, &self.error) {
+            (Some(s), _) => format!("{} · {} ms · {}", s, self.elapsed_ms, crate::response::format_size(self.size)),
+            (None, Some(e)) => format!("ERROR · {} ms · {}", self.elapsed_ms, e),
+            (None, None) => "NO RESPONSE RECORDED".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusFilter {
+    /// Primer dígito del status: 2 = 2xx...
+    Class(u16),
+    /// Sin respuesta (red, TLS, timeout...)
+    Error,
+}
+
+/// Filtro del panel History. Se escribe como una consulta:
+/// `2xx|4xx|5xx|error`, `GET` o `method:get`, `since:2024-05-01|7d|today`, `until:2024-05-31`;
+/// el resto son palabras que deben aparecer en la URL, el entorno, el body o el error.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryFilter {
+    pub query: String,
+    pub status: Option<StatusFilter>,
+    pub method: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub words: Vec<String>,
+}
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+impl HistoryFilter {
+    /// `today` resuelve las fechas relativas (`today`, `7d`)
+    pub fn parse(query: &str, today: NaiveDate) -> Result<Self, String> {
+        let mut filter = Self { query: query.trim().to_string(), ..Default::default() };
+        for token in query.split_whitespace() {
+            let (key, value) = token.split_once(':').unwrap_or(("", token));
+            match key.to_lowercase().as_str() {
+                "" | "status" if parse_status(value).is_some() => filter.status = parse_status(value),
+                "status" => return Err(format!("unknown status class '{}' (use 2xx, 4xx, 5xx or error)", value)),
+                "" if METHODS.contains(&value) => filter.method = Some(value.to_string()),
+                "method" => filter.method = Some(value.to_uppercase()),
+                "since" | "from" => filter.since = Some(parse_date(value, today)?),
+                "until" | "to" => filter.until = Some(parse_date(value, today)?),
+                _ => filter.words.push(token.to_lowercase()),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool { self.query.is_empty() }
+
+    pub fn matches(&self, e: &HistoryEntry) -> bool {
+        let status_ok = match self.status {
+            None => true,
+            Some(StatusFilter::Class(c)) => e.status.is_some_and(|s| s / 100 == c),
+            Some(StatusFilter::Error) => e.error.is_some(),
+        };
+        let method_ok = self.method.as_ref().is_none_or(|m| e.request.method.eq_ignore_ascii_case(m));
+        let day = e.time().map(|t| t.date_naive());
+        let date_ok = match day {
+            Some(d) => self.since.is_none_or(|s| d >= s) && self.until.is_none_or(|u| d <= u),
+            None => self.since.is_none() && self.until.is_none(),
+        };
+        let haystack = || format!("{} {} {} {} {}", e.request.url, e.request.name, e.environment.as_deref().unwrap_or(""), e.body, e.error.as_deref().unwrap_or("")).to_lowercase();
+        status_ok && method_ok && date_ok && (self.words.is_empty() || { let h = haystack(); self.words.iter().all(|w| h.contains(w.as_str())) })
+    }
+}
+
+fn parse_status(value: &str) -> Option<StatusFilter> {
+    match value.to_lowercase().as_str() {
+        "error" | "err" => Some(StatusFilter::Error),
+        v => v.strip_suffix("xx").and_then(|d| d.parse::<u16>().ok()).filter(|d| (1..=5).contains(d)).map(StatusFilter::Class),
+    }
+}
+
+fn parse_date(value: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    if value.eq_ignore_ascii_case("today") { return Ok(today); }
+    if let Some(days) = value.strip_suffix('d').and_then(|d| d.parse::<u64>().ok()) {
+        return today.checked_sub_days(chrono::Days::new(days)).ok_or_else(|| format!("date out of range: {}", value));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{}' (use YYYY-MM-DD, Nd or today)", value))
+}
+
+pub struct History {
+    /// La más reciente primero
+    pub entries: Vec<HistoryEntry>,
+    path: PathBuf,
+    /// Problema al abrirlo que hay que enseñar (el `history.json` antiguo no se pudo migrar)
+    pub warning: Option<String>,
+}
+
+impl History {
+    /// Carga `history.jsonl` del workspace (las líneas ilegibles se ignoran) y migra el
+    /// `history.json` de versiones anteriores
+    pub fn open(base: &Path) -> Self {
+        let path = base.join(HISTORY_FILE);
+        let mut entries: Vec<HistoryEntry> = fs::read_to_string(&path).unwrap_or_default().lines().filter_map(|l| serde_json::from_str(l).ok()).collect();
+        entries.reverse();
+        let mut history = Self { entries, path, warning: None };
+        let legacy = base.join(LEGACY_FILE);
+        if let Ok(content) = fs::read_to_string(&legacy) {
+            // Si no se entiende se deja donde está: borrarlo perdería el historial
+            let old: Vec<ApiRequest> = match serde_json::from_str(&content) {
+                Ok(old) => old,
+                Err(e) => { history.warning = Some(format!("{} not migrated ({}); fix or remove it", LEGACY_FILE, e)); return history; }
+            };
+            // Sin fecha: la del archivo es la del último envío, y con ella la retención por días
+            // borraría todo el historial migrado de quien lleve tiempo sin usar Arthema
+            history.entries.extend(old.into_iter().map(|request| HistoryEntry { timestamp: String::new(), ..HistoryEntry::new(request, None) }));
+            if history.rewrite().is_ok() { let _ = fs::remove_file(&legacy); }
+            // El `.gitignore` del workspace ignoraba el archivo viejo
+            let gitignore = base.join(".gitignore");
+            if let Ok(ignored) = fs::read_to_string(&gitignore) && ignored.lines().any(|l| l == LEGACY_FILE) {
+                let _ = fs::write(&gitignore, ignored.lines().map(|l| if l == LEGACY_FILE { HISTORY_FILE } else { l }).collect::<Vec<_>>().join("\n") + "\n");
+            }
+        }
+        history
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&HistoryEntry> { self.entries.get(idx) }
+
+    /// Añade una línea al final del archivo
+    pub fn push(&mut self, entry: HistoryEntry) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        self.entries.insert(0, entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Result<()> {
+        if idx < self.entries.len() {
+            self.entries.remove(idx);
+            self.rewrite()?;
+        }
+        Ok(())
+    }
+
+    /// Descarta lo que exceda `retention` y compacta el archivo si cambió algo.
+    /// Devuelve cuántas entradas se descartaron.
+    pub fn apply_retention(&mut self, retention: Retention, now: DateTime<Local>) -> Result<usize> {
+        let before = self.entries.len();
+        if let Some(days) = retention.max_days {
+            let cutoff = now - chrono::Duration::days(days as i64);
+            self.entries.retain(|e| e.time().is_none_or(|t| t >= cutoff));
+        }
+        self.entries.truncate(retention.max_entries);
+        let lines = fs::read_to_string(&self.path).map(|c| c.lines().count()).unwrap_or(0);
+        if lines != self.entries.len() { self.rewrite()?; }
+        Ok(before - self.entries.len())
+    }
+
+    fn rewrite(&self) -> Result<()> {
+        let mut content = String::new();
+        for e in self.entries.iter().rev() { content.push_str(&serde_json::to_string(e)?); content.push('\n'); }
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(method: &str, url: &str, status: Option<u16>, timestamp: &str) -> HistoryEntry {
+        let request = ApiRequest { method: method.to_string(), url: url.to_string(), ..Default::default() };
+        let e = HistoryEntry { timestamp: timestamp.to_string(), ..HistoryEntry::new(request, Some("Dev".to_string())) };
+        match status {
+            Some(s) => HistoryEntry { status: Some(s), ..e },
+            None => e.with_error("connection refused".to_string(), std::time::Duration::from_millis(5)),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_status_method_date_and_text() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 20).unwrap();
+        let ok = entry("GET", "https://api.test/users", Some(200), "2024-05-19T10:00:00+00:00");
+        let missing = entry("POST", "https://api.test/orders", Some(404), "2024-05-01T10:00:00+00:00");
+        let down = entry("GET", "https://down.test/", None, "2024-05-20T10:00:00+00:00");
+        let pick = |q: &str| { let f = HistoryFilter::parse(q, today).unwrap(); [&ok, &missing, &down].iter().filter(|e| f.matches(e)).map(|e| e.request.url.clone()).collect::<Vec<_>>() };
+
+        assert_eq!(pick("").len(), 3);
+        assert_eq!(pick("4xx"), vec!["https://api.test/orders"]);
+        assert_eq!(pick("status:error"), vec!["https://down.test/"]);
+        assert_eq!(pick("method:get users"), vec!["https://api.test/users"]);
+        assert_eq!(pick("POST"), vec!["https://api.test/orders"]);
+        assert_eq!(pick("since:7d"), vec!["https://api.test/users", "https://down.test/"]);
+        assert_eq!(pick("since:2024-04-30 until:2024-05-02"), vec!["https://api.test/orders"]);
+        assert_eq!(pick("refused"), vec!["https://down.test/"]);
+        assert!(HistoryFilter::parse("since:yesterday", today).is_err());
+        assert!(HistoryFilter::parse("status:9xx", today).is_err());
+    }
+
+    #[test]
+    fn test_append_retention_and_legacy_migration() {
+        let tmp = crate::test_util::TempDir::new("history");
+        let dir = tmp.path();
+        fs::write(dir.join(".gitignore"), "history.json\noauth_tokens.json\n").unwrap();
+        fs::write(dir.join(LEGACY_FILE), r#"[{"name": "old", "method": "GET", "url": "https://old.test"}]"#).unwrap();
+
+        let mut history = History::open(dir);
+        assert!(!dir.join(LEGACY_FILE).exists());
+        assert_eq!(fs::read_to_string(dir.join(".gitignore")).unwrap(), "history.jsonl\noauth_tokens.json\n");
+        assert_eq!(history.get(0).map(|e| e.request.url.as_str()), Some("https://old.test"));
+        // Sin fecha, la retención por días no las borra
+        assert!(history.get(0).unwrap().time().is_none());
+        assert_eq!(history.apply_retention(Retention::default(), Local::now() + chrono::Duration::days(365)).unwrap(), 0);
+
+        let resp = ApiResponse { status: 200, body: vec![b'x'; BODY_LIMIT + 10], ..Default::default() };
+        history.push(HistoryEntry::new(ApiRequest { url: "https://new.test".to_string(), ..Default::default() }, None).with_response(&resp)).unwrap();
+        let reopened = History::open(dir);
+        let newest = reopened.get(0).unwrap();
+        assert_eq!((newest.status, newest.size, newest.body.len(), newest.truncated), (Some(200), BODY_LIMIT + 10, BODY_LIMIT, true));
+        assert_eq!(fs::read_to_string(dir.join(HISTORY_FILE)).unwrap().lines().count(), 2);
+
+        let mut history = reopened;
+        assert_eq!(history.apply_retention(Retention { max_entries: 1, max_days: None }, Local::now()).unwrap(), 1);
+        assert_eq!(History::open(dir).entries.iter().map(|e| e.request.url.clone()).collect::<Vec<_>>(), vec!["https://new.test"]);
+    }
+
+    #[test]
+    fn test_unreadable_legacy_history_is_kept() {
+        let tmp = crate::test_util::TempDir::new("history_legacy");
+        fs::write(tmp.join(LEGACY_FILE), r#"[{"name": "old", "url": 42}]"#).unwrap();
+        let history = History::open(tmp.path());
+        assert!(history.entries.is_empty());
+        assert!(history.warning.as_deref().is_some_and(|w| w.contains(LEGACY_FILE)), "{:?}", history.warning);
+        assert!(tmp.join(LEGACY_FILE).exists() && !tmp.join(HISTORY_FILE).exists());
+    }
+}
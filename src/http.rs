@@ -0,0 +1,302 @@
+use reqwest::Method;
+use std::time::{Duration, Instant};
+
+use crate::auth::{Auth, TokenStore};
+use crate::collections::ApiRequest;
+use crate::body::BodyType;
+use crate::form::{Form, FormField, FormKind};
+use crate::graphql::GraphQlBody;
+use crate::opener::Opener;
+use crate::environments::substitute_vars;
+use crate::response::{ApiResponse, Progress};
+use std::collections::BTreeMap;
+
+/// Petición lista para enviar: lo que ven los scripts y lo que recibe reqwest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PreparedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Formulario; manda sobre `body` y `file_path`
+    pub form: Option<Form>,
+    /// Adjunto multipart (vacío si no hay)
+    pub file_path: String,
+    /// Fichero que se envía tal cual como body (tipo BINARY), leído en streaming
+    pub body_file: String,
+    pub insecure: bool,
+    /// Ya resuelta contra la carpeta; `Inherit` se trata como sin auth
+    pub auth: Auth,
+}
+
+impl PreparedRequest {
+    pub fn from_api_request(req: &ApiRequest) -> Self {
+        let mut prepared = Self {
+            method: req.method.clone(),
+            url: crate::params::apply_path_params(&req.url, &req.path_params),
+            headers: crate::headers::enabled_pairs(&req.headers),
+            insecure: req.insecure,
+            auth: req.auth.clone(),
+            ..Default::default()
+        };
+        let file = req.file_path.clone().unwrap_or_default();
+        match req.body_type {
+            BodyType::Form | BodyType::UrlEncoded => { prepared.form = req.form.clone(); prepared.file_path = file; }
+            BodyType::GraphQl => {
+                prepared.body = req.graphql.as_ref().map(GraphQlBody::to_body).unwrap_or_default();
+                if !prepared.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type")) { prepared.headers.push(("Content-Type".to_string(), "application/json".to_string())); }
+            }
+            BodyType::Binary => prepared.body_file = file,
+            BodyType::None => {}
+            _ => { prepared.body = req.body.clone().unwrap_or_default(); prepared.file_path = file; }
+        }
+        prepared
+    }
+
+    /// Expande `{{var}}` en URL, headers y body
+    pub fn substitute(&mut self, vars: &BTreeMap<String, String>) {
+        self.url = substitute_vars(&self.url, vars);
+        self.body = substitute_vars(&self.body, vars);
+        self.body_file = substitute_vars(&self.body_file, vars);
+        if let Some(form) = self.form.as_mut() { form.substitute(vars); }
+        for (k, v) in self.headers.iter_mut() { *k = substitute_vars(k, vars); *v = substitute_vars(v, vars); }
+        self.auth = self.auth.substitute(vars);
+    }
+
+    /// Formulario que se envía: el del editor o, si no hay, el adjunto como campo `file`
+    pub fn effective_form(&self) -> Option<Form> {
+        self.form.clone().or_else(|| (!self.file_path.is_empty()).then(|| Form { kind: FormKind::Multipart, fields: vec![FormField::file("file", self.file_path.clone())] }))
+    }
+}
+
+/// Percent-encoding RFC 3986: sólo se dejan tal cual los caracteres no reservados
+pub fn percent_encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// Inverso de `percent_encode`; también acepta `+` como espacio (form-urlencoded)
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() && hex(bytes[i + 1]).is_some() && hex(bytes[i + 2]).is_some() => {
+                out.push((hex(bytes[i + 1]).unwrap_or(0) * 16 + hex(bytes[i + 2]).unwrap_or(0)) as u8);
+                i += 2;
+            }
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+pub fn parse_method(m: &str) -> Method {
+    match m { "POST" => Method::POST, "PUT" => Method::PUT, "DELETE" => Method::DELETE, "PATCH" => Method::PATCH, "HEAD" => Method::HEAD, "OPTIONS" => Method::OPTIONS, _ => Method::GET }
+}
+
+/// Tiempo máximo hasta recibir los headers (y, en `send`, la respuesta completa)
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Envía la petición aplicando su auth: Basic/Bearer/API key directamente, OAuth2 con
+/// el token cacheado (o uno nuevo, abriendo el navegador con `opener` en el auth-code) y
+/// Digest respondiendo al reto 401 del servidor.
+pub async fn send(req: PreparedRequest, tokens: &TokenStore, opener: &Opener) -> anyhow::Result<ApiResponse> {
+    let client = reqwest::Client::builder().timeout(TIMEOUT).danger_accept_invalid_certs(req.insecure).build()?;
+    send_with(&client, req, tokens, opener, None).await
+}
+
+/// Como `send` pero avisando de los headers y de cada trozo del body según llegan (el body
+/// lo guarda quien escucha, ver `ApiResponse::read`). Sólo los headers tienen límite de
+/// tiempo: SSE y respuestas chunked largas duran lo que duren.
+pub async fn send_streaming(req: PreparedRequest, tokens: &TokenStore, opener: &Opener, on: &(dyn Fn(Progress) + Send + Sync)) -> anyhow::Result<ApiResponse> {
+    let client = reqwest::Client::builder().connect_timeout(TIMEOUT).danger_accept_invalid_certs(req.insecure).build()?;
+    send_with(&client, req, tokens, opener, Some(on)).await
+}
+
+async fn send_with(client: &reqwest::Client, mut req: PreparedRequest, tokens: &TokenStore, opener: &Opener, on: Option<&(dyn Fn(Progress) + Send + Sync)>) -> anyhow::Result<ApiResponse> {
+    match &req.auth {
+        Auth::OAuth2(cfg) => {
+            let token = crate::auth::oauth2_token(cfg, tokens, client, opener, TIMEOUT).await?;
+            crate::auth::set_header(&mut req, "Authorization", format!("Bearer {}", token));
+        }
+        auth => crate::auth::apply_static(&auth.clone(), &mut req),
+    }
+
+    let resp = execute(client, &req, on).await?;
+    if let Auth::Digest { username, password } = &req.auth
+        && resp.status == 401
+        && let Some(challenge) = resp.header("www-authenticate")
+    {
+        let uri = reqwest::Url::parse(&req.url).map(|u| match u.query() { Some(q) => format!("{}?{}", u.path(), q), None => u.path().to_string() }).unwrap_or_else(|_| "/".to_string());
+        if let Some(header) = crate::auth::digest_authorization(challenge, username, password, &req.method, &uri, &crate::auth::random_token(16)) {
+            crate::auth::set_header(&mut req, "Authorization", header);
+            return execute(client, &req, on).await;
+        }
+    }
+    Ok(resp)
+}
+
+async fn execute(client: &reqwest::Client, req: &PreparedRequest, on: Option<&(dyn Fn(Progress) + Send + Sync)>) -> anyhow::Result<ApiResponse> {
+    let method = parse_method(&req.method);
+    let mut rb = client.request(method.clone(), &req.url);
+    let form = req.effective_form();
+    let has_content_type = req.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type"));
+    // En multipart el Content-Type (con boundary) lo pone reqwest
+    for (k, v) in &req.headers {
+        if matches!(&form, Some(f) if f.kind == FormKind::Multipart) && k.eq_ignore_ascii_case("content-type") { continue; }
+        rb = rb.header(k, v);
+    }
+    match form {
+        Some(f) if f.kind == FormKind::Multipart => rb = rb.multipart(f.to_multipart()?),
+        Some(f) => {
+            if !has_content_type { rb = rb.header("Content-Type", "application/x-www-form-urlencoded"); }
+            rb = rb.body(f.encoded());
+        }
+        None if !req.body_file.is_empty() => {
+            let file = tokio::fs::File::open(&req.body_file).await.map_err(|e| anyhow::anyhow!("body file {}: {}", req.body_file, e))?;
+            let len = file.metadata().await?.len();
+            if !has_content_type { rb = rb.header("Content-Type", crate::form::guess_mime(&req.body_file)); }
+            rb = rb.header("Content-Length", len).body(reqwest::Body::from(file));
+        }
+        None if !req.body.is_empty() && method != Method::GET => rb = rb.body(req.body.clone()),
+        None => {}
+    }
+
+    let started = Instant::now();
+    // reqwest añade en vez de sustituir: el User-Agent propio sólo si el usuario no puso uno
+    if !req.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("user-agent")) { rb = rb.header("User-Agent", "Arthema"); }
+    let resp = tokio::time::timeout(TIMEOUT, rb.send()).await.map_err(|_| anyhow::anyhow!("no response after {}s", TIMEOUT.as_secs()))??;
+    Ok(ApiResponse::read(resp, started, on).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encoding_roundtrip() {
+        assert_eq!(percent_encode("a b&c=ñ"), "a%20b%26c%3D%C3%B1");
+        assert_eq!(percent_decode("a%20b%26c%3D%C3%B1+d"), "a b&c=ñ d");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_reports_chunks_before_the_body_ends() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let (more_tx, more_rx) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 4096]).await.unwrap();
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\nd\r\ndata: hello\n\n\r\n").await.unwrap();
+            // El resto sólo se manda cuando el cliente ya ha visto el primer trozo
+            more_rx.await.unwrap();
+            s.write_all(b"c\r\ndata: done\n\n\r\n0\r\n\r\n").await.unwrap();
+        });
+        let more = std::sync::Mutex::new(Some(more_tx));
+        let seen = std::sync::Mutex::new(Vec::new());
+        let resp = send_streaming(PreparedRequest { method: "GET".to_string(), url, ..Default::default() }, &TokenStore::in_memory(), &Opener::fake(), &|p| {
+            seen.lock().unwrap().push(match p { Progress::Headers(head) => format!("headers {} {}", head.status, head.body.len()), Progress::Chunk(c) => String::from_utf8_lossy(&c).to_string() });
+            if seen.lock().unwrap().len() == 2 && let Some(tx) = more.lock().unwrap().take() { let _ = tx.send(()); }
+        }).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["headers 200 0", "data: hello\n\n", "data: done\n\n"]);
+        // El body solo existe en quien escucha
+        assert!(resp.body.is_empty() && resp.is_event_stream());
+    }
+
+    #[tokio::test]
+    async fn test_user_agent_is_sent_once() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut heads = Vec::new();
+            for _ in 0..2 {
+                let (mut s, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = s.read(&mut buf).await.unwrap();
+                s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await.unwrap();
+                heads.push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+            }
+            heads
+        });
+        let imported = crate::curl::parse_curl(&format!("curl {} -A 'Mozilla/5.0'", url)).unwrap();
+        let headers = imported.headers.iter().map(|h| (h.name.clone(), h.value.clone())).collect();
+        send(PreparedRequest { method: "GET".to_string(), url: url.clone(), headers, ..Default::default() }, &TokenStore::in_memory(), &Opener::fake()).await.unwrap();
+        send(PreparedRequest { method: "GET".to_string(), url, ..Default::default() }, &TokenStore::in_memory(), &Opener::fake()).await.unwrap();
+        let heads = server.await.unwrap();
+        assert_eq!(heads[0].matches("user-agent:").count(), 1, "{}", heads[0]);
+        assert!(heads[0].contains("user-agent: mozilla/5.0"));
+        assert!(heads[1].contains("user-agent: arthema"), "{}", heads[1]);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_sends_named_fields_and_files() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let tmp = crate::test_util::TempDir::new("form");
+        let path = tmp.join("arthema_form_test.png");
+        std::fs::write(&path, b"PNGDATA").unwrap();
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/up", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !raw.ends_with(b"--\r\n") { let n = s.read(&mut buf).await.unwrap(); if n == 0 { break; } raw.extend_from_slice(&buf[..n]); }
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&raw).to_string()
+        });
+        let form = Form::parse(&format!("name=Ana\navatar=@{};filename=me.png\navatar=@{};type=image/webp", path.display(), path.display()), FormKind::Multipart);
+        let req = PreparedRequest { method: "POST".to_string(), url, headers: vec![("Content-Type".to_string(), "multipart/form-data".to_string())], form: Some(form), ..Default::default() };
+        let resp = send(req, &TokenStore::in_memory(), &Opener::fake()).await.unwrap();
+        assert_eq!(resp.status, 200);
+        let raw = server.await.unwrap();
+        // El Content-Type del usuario se sustituye por el de reqwest (con boundary)
+        assert!(raw.to_lowercase().contains("content-type: multipart/form-data; boundary="), "{}", raw);
+        assert!(raw.contains("name=\"name\"\r\n\r\nAna"));
+        assert!(raw.contains("name=\"avatar\"; filename=\"me.png\"\r\nContent-Type: image/png\r\n\r\nPNGDATA"), "{}", raw);
+        assert!(raw.contains("filename=\"arthema_form_test.png\"\r\nContent-Type: image/webp"));
+    }
+
+    #[tokio::test]
+    async fn test_binary_body_streams_the_file() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let tmp = crate::test_util::TempDir::new("binary_body");
+        let path = tmp.join("arthema_binary_test.pdf");
+        std::fs::write(&path, b"%PDF-1.4 bytes").unwrap();
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/blob", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut raw = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !raw.ends_with(b"bytes") { let n = s.read(&mut buf).await.unwrap(); if n == 0 { break; } raw.extend_from_slice(&buf[..n]); }
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&raw).to_string()
+        });
+        let api = ApiRequest { method: "PUT".to_string(), url, body: Some("ignored".to_string()), body_type: BodyType::Binary, file_path: Some(path.display().to_string()), ..Default::default() };
+        let resp = send(PreparedRequest::from_api_request(&api), &TokenStore::in_memory(), &Opener::fake()).await.unwrap();
+        assert_eq!(resp.status, 200);
+        let raw = server.await.unwrap().to_lowercase();
+        assert!(raw.contains("content-type: application/pdf") && raw.contains("content-length: 14"), "{}", raw);
+        assert!(raw.ends_with("\r\n\r\n%pdf-1.4 bytes"), "{}", raw);
+    }
+
+    #[test]
+    fn test_graphql_body_adds_json_content_type() {
+        let api = ApiRequest { method: "POST".to_string(), body_type: BodyType::GraphQl, graphql: Some(GraphQlBody { query: "{ me }".to_string(), ..Default::default() }), ..Default::default() };
+        let prepared = PreparedRequest::from_api_request(&api);
+        assert_eq!(prepared.body, "{\"query\":\"{ me }\"}");
+        assert_eq!(prepared.headers, vec![("Content-Type".to_string(), "application/json".to_string())]);
+        let none = PreparedRequest::from_api_request(&ApiRequest { body: Some("x".to_string()), body_type: BodyType::None, ..Default::default() });
+        assert!(none.body.is_empty());
+    }
+}
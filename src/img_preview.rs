@@ -1,20 +1,238 @@
+use std::collections::BTreeMap;
+use std::io::Write;
 use base64::{Engine as _, engine::general_purpose};
+use image::{DynamicImage, Rgba, RgbaImage, imageops::FilterType};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
 
-pub fn generate_hifi_preview(bytes: &[u8], width: u32) -> String {
-    // Si estamos en iTerm2, usamos su protocolo de imágenes
-    let term = std::env::var("TERM_PROGRAM").unwrap_or_default();
-    
-    if term == "iTerm.app" {
-        let b64_data = general_purpose::STANDARD.encode(bytes);
-        // Protocolo iTerm2: ^]1337;File=inline=1;width=WIDTHpx:BASE64_DATA^G
-        // Ajustamos el width al ancho del panel (cada carácter mide aprox 10px de ancho)
-        let pixel_width = width * 8; 
-        format!(
-            "\x1b]1337;File=inline=1;width={}px;preserveAspectRatio=1:{}\x07\n",
-            pixel_width, b64_data
-        )
-    } else {
-        // Fallback para otras terminales
-        "🖼️ [Binary Image Data]\n\nTerminal does not support inline images.\nPress 'o' to open in System Preview.".to_string()
+/// Cómo dibuja imágenes la terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol { Kitty, Sixel, Iterm2, HalfBlocks }
+
+/// Tamaño de celda si la terminal no informa píxeles
+const DEFAULT_CELL: (u32, u32) = (10, 20);
+
+/// Lo que se detecta al arrancar: protocolo y tamaño de celda en píxeles
+#[derive(Debug, Clone, Copy)]
+pub struct Graphics {
+    pub protocol: Protocol,
+    pub cell: (u32, u32),
+}
+
+/// Sin protocolo de imágenes: medios bloques con la celda por defecto
+impl Default for Graphics {
+    fn default() -> Self { Self { protocol: Protocol::HalfBlocks, cell: DEFAULT_CELL } }
+}
+
+impl Graphics {
+    pub fn detect() -> Self {
+        let cell = crossterm::terminal::window_size().ok()
+            .filter(|w| w.width > 0 && w.height > 0 && w.columns > 0 && w.rows > 0)
+            .map(|w| ((w.width / w.columns) as u32, (w.height / w.rows) as u32))
+            .unwrap_or(DEFAULT_CELL);
+        Self { protocol: detect_protocol(|k| std::env::var(k).ok()), cell }
+    }
+}
+
+/// `ARTHEMA_IMAGES` (kitty, sixel, iterm, blocks) o lo que anuncia la terminal en su entorno.
+/// Dentro de tmux/screen las secuencias no llegan a la terminal real: medios bloques.
+fn detect_protocol(var: impl Fn(&str) -> Option<String>) -> Protocol {
+    let has = |k: &str| var(k).is_some_and(|v| !v.is_empty());
+    match var("ARTHEMA_IMAGES").unwrap_or_default().as_str() {
+        "kitty" => return Protocol::Kitty,
+        "sixel" => return Protocol::Sixel,
+        "iterm" => return Protocol::Iterm2,
+        "blocks" => return Protocol::HalfBlocks,
+        _ => {}
+    }
+    let (term, program) = (var("TERM").unwrap_or_default(), var("TERM_PROGRAM").unwrap_or_default());
+    if has("TMUX") || term.starts_with("screen") || term.starts_with("tmux") { Protocol::HalfBlocks }
+    else if has("KITTY_WINDOW_ID") || term == "xterm-kitty" || term == "xterm-ghostty" || program == "ghostty" { Protocol::Kitty }
+    else if program == "iTerm.app" || program == "WezTerm" { Protocol::Iterm2 }
+    else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") || program == "mlterm" || program == "contour" { Protocol::Sixel }
+    else { Protocol::HalfBlocks }
+}
+
+/// Vista previa calculada para un tamaño de panel; la pestaña la guarda hasta la próxima respuesta
+#[derive(Debug)]
+pub struct Preview {
+    pub area: (u16, u16),
+    pub protocol: Protocol,
+    pub content: Content,
+}
+
+#[derive(Debug)]
+pub enum Content {
+    /// Medios bloques: se dibuja como texto
+    Blocks(Text<'static>),
+    /// Secuencia de escape a escribir en la esquina del panel, fuera de ratatui
+    Sequence(String),
+    Error(String),
+}
+
+impl Preview {
+    /// Sirve para el panel actual; si no, hay que calcular otra (cambió el tamaño o el protocolo)
+    pub fn fits(&self, protocol: Protocol, width: u16, height: u16) -> bool { self.area == (width, height) && self.protocol == protocol }
+}
+
+/// Decodifica, escala y codifica: es lento (sobre todo Sixel), así que se llama fuera del
+/// dibujado, ver `App::start_image_previews`
+pub fn render(graphics: &Graphics, protocol: Protocol, bytes: &[u8], width: u16, height: u16) -> Preview {
+    let content = match image::load_from_memory(bytes) {
+        Err(e) => Content::Error(format!("🖼️ Cannot decode image: {}\n\nPress 'o' to open it in the system viewer.", e)),
+        Ok(img) => match protocol {
+            Protocol::HalfBlocks => Content::Blocks(half_blocks(&img, width, height)),
+            Protocol::Iterm2 => Content::Sequence(iterm2(bytes, &img, graphics.cell, width, height)),
+            Protocol::Kitty => kitty(&img, graphics.cell, width, height).map_or_else(|e| Content::Error(e.to_string()), Content::Sequence),
+            Protocol::Sixel => Content::Sequence(sixel(&scaled(&img, graphics.cell, width, height))),
+        },
+    };
+    Preview { area: (width, height), protocol, content }
+}
+
+/// Mayor tamaño con las proporciones de `w`×`h` que entra en `max_w`×`max_h`
+fn fit(w: u32, h: u32, max_w: u32, max_h: u32) -> (u32, u32) {
+    if w == 0 || h == 0 { return (1, 1); }
+    let scale = (max_w as f64 / w as f64).min(max_h as f64 / h as f64);
+    (((w as f64 * scale) as u32).max(1), ((h as f64 * scale) as u32).max(1))
+}
+
+/// La imagen escalada a los píxeles del panel
+fn scaled(img: &DynamicImage, cell: (u32, u32), width: u16, height: u16) -> RgbaImage {
+    let (w, h) = fit(img.width(), img.height(), width as u32 * cell.0, height as u32 * cell.1);
+    img.resize_exact(w, h, FilterType::Triangle).to_rgba8()
+}
+
+/// Cada celda pinta dos píxeles: `▀` con el de arriba como color de texto y el de abajo como fondo
+pub fn half_blocks(img: &DynamicImage, width: u16, height: u16) -> Text<'static> {
+    let (w, h) = fit(img.width(), img.height(), width as u32, height as u32 * 2);
+    let pixels = img.resize_exact(w, h, FilterType::Triangle).to_rgba8();
+    // Transparencia sobre negro
+    let color = |p: &Rgba<u8>| { let a = p[3] as u32; Color::Rgb((p[0] as u32 * a / 255) as u8, (p[1] as u32 * a / 255) as u8, (p[2] as u32 * a / 255) as u8) };
+    (0..h).step_by(2).map(|y| {
+        Line::from((0..w).map(|x| {
+            let top = color(pixels.get_pixel(x, y));
+            let bottom = if y + 1 < h { color(pixels.get_pixel(x, y + 1)) } else { Color::Reset };
+            Span::styled("▀", Style::default().fg(top).bg(bottom))
+        }).collect::<Vec<_>>())
+    }).collect::<Vec<_>>().into()
+}
+
+/// iTerm2 (también WezTerm): el archivo original, la terminal lo escala a las celdas pedidas
+fn iterm2(bytes: &[u8], img: &DynamicImage, cell: (u32, u32), width: u16, height: u16) -> String {
+    let (w, h) = fit(img.width(), img.height(), width as u32 * cell.0, height as u32 * cell.1);
+    let (cols, rows) = (w.div_ceil(cell.0).min(width as u32), h.div_ceil(cell.1).min(height as u32));
+    format!("\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07", bytes.len(), cols, rows, general_purpose::STANDARD.encode(bytes))
+}
+
+/// Kitty: PNG ya escalado, en trozos de 4096 bytes de base64. `C=1` no mueve el cursor.
+fn kitty(img: &DynamicImage, cell: (u32, u32), width: u16, height: u16) -> anyhow::Result<String> {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(scaled(img, cell, width, height)).write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    let data = general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        let head = if i == 0 { format!("a=T,f=100,q=2,C=1,m={}", more) } else { format!("m={}", more) };
+        out.push_str(&format!("\x1b_G{};{}\x1b\\", head, String::from_utf8_lossy(chunk)));
+    }
+    Ok(out)
+}
+
+/// Sixel con una paleta fija de 6×6×6 colores (sin cuantizar). Los píxeles transparentes no se pintan.
+fn sixel(pixels: &RgbaImage) -> String {
+    let level = |v: u8| (v as usize * 5 + 127) / 255;
+    let index = |p: &Rgba<u8>| if p[3] < 128 { None } else { Some(level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) };
+    let (w, h) = pixels.dimensions();
+    let mut out = format!("\x1bP0;1q\"1;1;{};{}", w, h);
+    for i in 0..216 { out.push_str(&format!("#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20)); }
+    for band in (0..h).step_by(6) {
+        // Una pasada por banda: los bits de cada columna, por color de la paleta
+        let mut columns: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for y in band..(band + 6).min(h) {
+            for x in 0..w {
+                if let Some(color) = index(pixels.get_pixel(x, y)) { columns.entry(color).or_insert_with(|| vec![0; w as usize])[x as usize] |= 1 << (y - band); }
+            }
+        }
+        for (n, (color, bits)) in columns.into_iter().enumerate() {
+            if n > 0 { out.push('$'); }
+            out.push_str(&format!("#{}", color));
+            // Repeticiones comprimidas: `!<n><sixel>`
+            for run in bits.chunk_by(|a, b| a == b) {
+                let c = (63 + run[0]) as char;
+                if run.len() > 3 { out.push_str(&format!("!{}{}", run.len(), c)); } else { run.iter().for_each(|_| out.push(c)); }
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Imagen de protocolo en pantalla: pestaña, body (dirección y tamaño) y celdas que ocupa
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub tab: usize,
+    pub body: (usize, usize),
+    pub rect: Rect,
+}
+
+/// Borra lo que quede de imágenes anteriores (Kitty las guarda en una capa propia)
+pub fn clear_sequence(protocol: Protocol) -> &'static str {
+    if protocol == Protocol::Kitty { "\x1b_Ga=d,q=2\x1b\\" } else { "" }
+}
+
+/// Escribe la secuencia en la esquina del panel sin mover el cursor de ratatui
+pub fn emit(out: &mut impl Write, rect: Rect, sequence: &str) -> std::io::Result<()> {
+    crossterm::queue!(out, crossterm::cursor::SavePosition, crossterm::cursor::MoveTo(rect.x, rect.y), crossterm::style::Print(sequence), crossterm::cursor::RestorePosition)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, y| if (x + y) % 2 == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 0]) }))
+    }
+
+    #[test]
+    fn test_detect_protocol_from_environment() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| move |k: &str| pairs.iter().find(|(n, _)| *n == k).map(|(_, v)| v.to_string());
+        assert_eq!(detect_protocol(env(&[("TERM", "xterm-kitty")])), Protocol::Kitty);
+        assert_eq!(detect_protocol(env(&[("TERM_PROGRAM", "iTerm.app")])), Protocol::Iterm2);
+        assert_eq!(detect_protocol(env(&[("TERM", "foot")])), Protocol::Sixel);
+        assert_eq!(detect_protocol(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux-1000/default,1,0")])), Protocol::HalfBlocks);
+        assert_eq!(detect_protocol(env(&[("TERM", "xterm-256color"), ("ARTHEMA_IMAGES", "sixel")])), Protocol::Sixel);
+        assert_eq!(detect_protocol(env(&[("TERM", "xterm-256color")])), Protocol::HalfBlocks);
+    }
+
+    #[test]
+    fn test_half_blocks_fit_the_area_keeping_proportions() {
+        // 4×4 en 10×3 celdas: 6×6 píxeles → 6 columnas y 3 filas
+        let text = half_blocks(&checker(), 10, 3);
+        assert_eq!(text.lines.len(), 3);
+        assert!(text.lines.iter().all(|l| l.spans.len() == 6 && l.spans.iter().all(|s| s.content == "▀")));
+    }
+
+    #[test]
+    fn test_protocol_sequences() {
+        let graphics = Graphics { protocol: Protocol::Sixel, cell: (2, 4) };
+        let sixel = match render(&graphics, Protocol::Sixel, &png(&checker()), 2, 1).content { Content::Sequence(s) => s, _ => panic!("expected a sixel sequence") };
+        assert!(sixel.starts_with("\x1bP0;1q\"1;1;4;4") && sixel.ends_with("-\x1b\\"), "{:?}", sixel);
+        // Solo el rojo (índice 5·36) es opaco: columnas alternas con las filas 0+2 (D) y 1+3 (I)
+        assert!(sixel.ends_with("#215;2;100;100;100#180DIDI-\x1b\\"), "{:?}", sixel);
+
+        let kitty = match render(&graphics, Protocol::Kitty, &png(&checker()), 2, 1).content { Content::Sequence(s) => s, _ => panic!("expected a kitty sequence") };
+        assert!(kitty.starts_with("\x1b_Ga=T,f=100,q=2,C=1,m=0;") && kitty.ends_with("\x1b\\"));
+        assert!(matches!(render(&graphics, Protocol::Kitty, b"not an image", 2, 1).content, Content::Error(_)));
+    }
+
+    fn png(img: &DynamicImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut out), image::ImageOutputFormat::Png).unwrap();
+        out
     }
 }
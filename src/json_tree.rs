@@ -0,0 +1,174 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Estado del árbol JSON interactivo de una pestaña.
+/// Los nodos expandidos se identifican por su JSON Pointer (RFC 6901), así que el
+/// estado sobrevive a cambios de selección y solo se recorre lo que está visible.
+pub struct JsonTree {
+    pub root: Value,
+    pub expanded: HashSet<String>,
+    pub selected: usize,
+    pub offset: usize,
+}
+
+pub struct TreeRow<'v> {
+    pub pointer: String,
+    pub depth: usize,
+    pub label: Option<String>,
+    pub value: &'v Value,
+    pub parent: Option<usize>,
+}
+
+impl TreeRow<'_> {
+    pub fn is_container(&self) -> bool { matches!(self.value, Value::Object(_) | Value::Array(_)) }
+}
+
+impl JsonTree {
+    pub fn new(root: Value) -> Self {
+        let mut expanded = HashSet::new();
+        expanded.insert(String::new()); // raíz abierta por defecto
+        Self { root, expanded, selected: 0, offset: 0 }
+    }
+
+    pub fn rows(&self) -> Vec<TreeRow<'_>> {
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, String::new(), 0, None, None, &mut rows);
+        rows
+    }
+
+    fn push_rows<'v>(&'v self, value: &'v Value, pointer: String, depth: usize, label: Option<String>, parent: Option<usize>, rows: &mut Vec<TreeRow<'v>>) {
+        let idx = rows.len();
+        let open = self.expanded.contains(&pointer);
+        rows.push(TreeRow { pointer: pointer.clone(), depth, label, value, parent });
+        if !open { return; }
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    self.push_rows(v, format!("{}/{}", pointer, escape_pointer(k)), depth + 1, Some(k.clone()), Some(idx), rows);
+                }
+            }
+            Value::Array(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    self.push_rows(v, format!("{}/{}", pointer, i), depth + 1, Some(i.to_string()), Some(idx), rows);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        let count = self.rows().len();
+        if count == 0 { return; }
+        self.selected = (self.selected as i64 + delta as i64).clamp(0, count as i64 - 1) as usize;
+    }
+
+    /// Derecha: expande el nodo o, si ya está abierto, baja a su primer hijo.
+    pub fn expand(&mut self) {
+        let rows = self.rows();
+        let Some(row) = rows.get(self.selected) else { return };
+        if !row.is_container() { return; }
+        if self.expanded.contains(&row.pointer) {
+            if rows.get(self.selected + 1).is_some_and(|r| r.parent == Some(self.selected)) { self.selected += 1; }
+        } else {
+            let p = row.pointer.clone();
+            self.expanded.insert(p);
+        }
+    }
+
+    /// Izquierda: colapsa el nodo o, si ya está cerrado (o es hoja), salta al padre.
+    pub fn collapse(&mut self) {
+        let rows = self.rows();
+        let Some(row) = rows.get(self.selected) else { return };
+        if row.is_container() && self.expanded.contains(&row.pointer) {
+            let p = row.pointer.clone();
+            self.expanded.remove(&p);
+        } else if let Some(parent) = row.parent {
+            self.selected = parent;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let rows = self.rows();
+        let Some(row) = rows.get(self.selected) else { return };
+        if !row.is_container() { return; }
+        let p = row.pointer.clone();
+        if !self.expanded.remove(&p) { self.expanded.insert(p); }
+    }
+
+    pub fn jump_to_parent(&mut self) {
+        if let Some(parent) = self.rows().get(self.selected).and_then(|r| r.parent) { self.selected = parent; }
+    }
+
+    pub fn jump_sibling(&mut self, forward: bool) {
+        let rows = self.rows();
+        let Some(parent) = rows.get(self.selected).map(|r| r.parent) else { return };
+        let siblings: Vec<usize> = rows.iter().enumerate().filter(|(_, r)| r.parent == parent).map(|(i, _)| i).collect();
+        let Some(pos) = siblings.iter().position(|&i| i == self.selected) else { return };
+        let target = if forward { siblings.get(pos + 1) } else { pos.checked_sub(1).and_then(|p| siblings.get(p)) };
+        if let Some(&t) = target { self.selected = t; }
+    }
+
+    /// Ajusta `offset` para que la fila seleccionada quede dentro de una ventana de `height` filas.
+    pub fn scroll_into_view(&mut self, height: usize) {
+        if height == 0 { return; }
+        if self.selected < self.offset { self.offset = self.selected; }
+        else if self.selected >= self.offset + height { self.offset = self.selected + 1 - height; }
+    }
+}
+
+/// Resumen mostrado junto a objetos/arrays: número de claves o longitud.
+pub fn summarize(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!("{{…}} {} {}", map.len(), if map.len() == 1 { "key" } else { "keys" }),
+        Value::Array(items) => format!("[…] {} {}", items.len(), if items.len() == 1 { "item" } else { "items" }),
+        other => other.to_string(),
+    }
+}
+
+fn escape_pointer(key: &str) -> String { key.replace('~', "~0").replace('/', "~1") }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> JsonTree {
+        JsonTree::new(json!({ "a": { "x": 1, "y": 2 }, "b": [1, 2, 3], "c/d": "s" }))
+    }
+
+    #[test]
+    fn test_only_root_children_visible_initially() {
+        let tree = sample();
+        let rows = tree.rows();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].pointer, "/c~1d");
+        assert_eq!(summarize(rows[2].value), "[…] 3 items");
+    }
+
+    #[test]
+    fn test_expand_collapse_and_parent_jump() {
+        let mut tree = sample();
+        tree.move_selection(1); // "a"
+        tree.expand();
+        assert_eq!(tree.rows().len(), 6);
+        tree.expand(); // baja al primer hijo
+        assert_eq!(tree.rows()[tree.selected].label.as_deref(), Some("x"));
+        tree.collapse(); // hoja -> padre
+        assert_eq!(tree.selected, 1);
+        tree.collapse();
+        assert_eq!(tree.rows().len(), 4);
+    }
+
+    #[test]
+    fn test_sibling_navigation() {
+        let mut tree = sample();
+        tree.move_selection(1);
+        tree.expand();
+        tree.jump_sibling(true);
+        assert_eq!(tree.rows()[tree.selected].label.as_deref(), Some("b"));
+        tree.jump_sibling(false);
+        assert_eq!(tree.rows()[tree.selected].label.as_deref(), Some("a"));
+        tree.jump_sibling(false);
+        assert_eq!(tree.selected, 1);
+    }
+}
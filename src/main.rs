@@ -2,6 +2,12 @@ mod config;
 mod curl;
 mod img_preview;
 mod openapi;
+mod params;
+mod headers;
+mod form;
+mod body;
+mod graphql;
+mod xml;
 mod postman;
 mod bruno;
 use anyhow::Result;
@@ -18,21 +24,52 @@ use std::io;
 use std::time::Duration;
 
 mod app;
+mod auth;
+mod clipboard;
+mod opener;
 mod ui;
 mod ai;
+mod assertions;
+mod codegen;
 mod collections;
+mod environments;
+mod history;
+mod http;
+mod json_tree;
+mod response;
+mod runner;
+mod scripting;
+mod sse;
+mod workspace;
+#[cfg(test)]
+mod test_util;
 
 use crate::app::App;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("run") {
+        // Modo headless para CI: sin raw mode ni pantalla alternativa
+        std::process::exit(runner::run_cli(&args[1..]).await);
+    }
+    if args.first().map(String::as_str) == Some("migrate") { std::process::exit(workspace::migrate_cli(&args[1..])); }
+    let workspace = match args.as_slice() {
+        [] => None,
+        [flag, path] if flag == "--workspace" || flag == "-w" => Some(std::path::PathBuf::from(path)),
+        _ => {
+            eprintln!("Usage: arthema [--workspace <DIR>]\n       arthema run [PATH] [options]   (see arthema run --help)\n       arthema migrate [--workspace <DIR>]");
+            std::process::exit(2);
+        }
+    };
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new();
+    let mut app = App::with_workspace(workspace);
     let res = run_app(&mut terminal, &mut app).await;
 
     disable_raw_mode()?;
@@ -51,18 +88,28 @@ async fn main() -> Result<()> {
 }
 
 async fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App<'_>) -> io::Result<()> {
+    // Imagen de protocolo (Kitty, Sixel, iTerm2) que está en pantalla
+    let mut shown_image: Option<img_preview::Placement> = None;
     loop {
         app.update();
         // Pasamos app como mutable
         terminal.draw(|f| ui::draw(f, app))?;
+        if app.image_placement != shown_image {
+            // ratatui no repinta las celdas que ocupaba la imagen: se redibuja todo
+            if shown_image.is_some() {
+                img_preview::emit(&mut io::stdout(), Default::default(), img_preview::clear_sequence(app.graphics.protocol))?;
+                terminal.clear()?;
+                terminal.draw(|f| ui::draw(f, app))?;
+            }
+            if let (Some(p), Some(seq)) = (&app.image_placement, app.image_sequence()) { img_preview::emit(&mut io::stdout(), p.rect, seq)?; }
+            shown_image = app.image_placement.clone();
+        }
 
         if event::poll(Duration::from_millis(16))? {
             let (width, height) = size()?;
             match event::read()? {
                 Event::Key(key) => {
-                    if let KeyCode::Char('q') = key.code {
-                        if !app.is_input_active() { return Ok(()); }
-                    }
+                    if key.code == KeyCode::Char('q') && !app.is_input_active() { return Ok(()); }
                     app.handle_key(key);
                 }
                 Event::Mouse(mouse) => {
@@ -1,6 +1,8 @@
-use openapiv3::OpenAPI;
+use openapiv3::{OpenAPI, ReferenceOr, Schema, SchemaKind, StringFormat, Type, VariantOrUnknownOrEmpty};
 use crate::collections::ApiRequest;
-use std::collections::HashMap;
+use crate::body::BodyType;
+use crate::form::{Form, FormField, FormKind};
+use crate::headers::Header;
 
 pub fn parse_swagger(content: &str) -> Vec<ApiRequest> {
     let spec: OpenAPI = if let Ok(s) = serde_json::from_str(content) {
@@ -15,11 +17,26 @@ pub fn parse_swagger(content: &str) -> Vec<ApiRequest> {
     let base_url = spec.servers.first().map(|s| s.url.clone()).unwrap_or_else(|| "http://localhost".to_string());
 
     for (path, item) in spec.paths.iter() {
-        if let Some(ref path_item) = item.as_item() {
+        if let Some(path_item) = item.as_item() {
             // Helper para procesar cada operación
             let mut process_op = |method: &str, op: &openapiv3::Operation| {
-                let mut headers = HashMap::new();
-                headers.insert("Content-Type".to_string(), "application/json".to_string());
+                let request_body = op.request_body.as_ref().and_then(|b| b.as_item());
+                let form = request_body.and_then(|b| form_from_body(&spec, b));
+                // Sin formulario: JSON si el spec lo acepta, si no el primer tipo declarado
+                let media = request_body.and_then(|b| if b.content.contains_key("application/json") { None } else { b.content.keys().next() });
+                let body_type = match (&form, media) {
+                    (Some(f), _) => BodyType::from_form_kind(f.kind),
+                    (None, Some(ct)) if ct.starts_with("application/octet-stream") => BodyType::Binary,
+                    (None, Some(ct)) => BodyType::from_content_type(ct).unwrap_or_default(),
+                    (None, None) => BodyType::Json,
+                };
+                let headers = match (form.as_ref().map(|f| f.kind), media) {
+                    // El boundary de multipart lo pone el cliente al enviar
+                    (Some(FormKind::Multipart), _) => Vec::new(),
+                    (Some(FormKind::UrlEncoded), _) => vec![Header::new("Content-Type", "application/x-www-form-urlencoded")],
+                    (None, Some(ct)) => vec![Header::new("Content-Type", ct.clone())],
+                    (None, None) => vec![Header::new("Content-Type", "application/json")],
+                };
 
                 // Intentar extraer el grupo de los tags
                 let group = op.tags.first().cloned();
@@ -31,7 +48,10 @@ pub fn parse_swagger(content: &str) -> Vec<ApiRequest> {
                     method: method.to_string(),
                     headers,
                     body: None, // Por ahora simplificado, se puede mejorar con examples
+                    body_type,
+                    form,
                     group,
+                    ..Default::default()
                 });
             };
 
@@ -46,6 +66,27 @@ pub fn parse_swagger(content: &str) -> Vec<ApiRequest> {
     requests
 }
 
+/// Campos del schema de un body `multipart/form-data` o `application/x-www-form-urlencoded`;
+/// las propiedades `format: binary` son ficheros (ruta vacía a rellenar).
+fn form_from_body(spec: &OpenAPI, body: &openapiv3::RequestBody) -> Option<Form> {
+    let (kind, media) = match body.content.get("multipart/form-data") {
+        Some(m) => (FormKind::Multipart, m),
+        None => (FormKind::UrlEncoded, body.content.get("application/x-www-form-urlencoded")?),
+    };
+    let schema = match media.schema.as_ref()? {
+        ReferenceOr::Item(s) => s,
+        ReferenceOr::Reference { reference } => spec.components.as_ref()?.schemas.get(reference.trim_start_matches("#/components/schemas/"))?.as_item()?,
+    };
+    let SchemaKind::Type(Type::Object(obj)) = &schema.schema_kind else { return None };
+    let fields = obj.properties.iter().map(|(name, prop)| {
+        let prop: Option<&Schema> = prop.as_item().map(|b| b.as_ref());
+        let binary = prop.is_some_and(|p| matches!(&p.schema_kind, SchemaKind::Type(Type::String(t)) if t.format == VariantOrUnknownOrEmpty::Item(StringFormat::Binary)));
+        let example = prop.and_then(|p| p.schema_data.example.as_ref()).map(|e| e.as_str().map(str::to_string).unwrap_or_else(|| e.to_string())).unwrap_or_default();
+        if binary && kind == FormKind::Multipart { FormField::file(name.as_str(), "") } else { FormField::text(name.as_str(), example) }
+    }).collect();
+    Some(Form { kind, fields })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +113,39 @@ mod tests {
         assert_eq!(reqs[0].name, "List Users");
         assert_eq!(reqs[0].group.as_ref().unwrap(), "Admin");
     }
+
+    #[test]
+    fn test_form_request_bodies() {
+        let yaml = r##"
+openapi: 3.0.0
+info: {title: Test, version: "1.0"}
+paths:
+  /avatar:
+    post:
+      requestBody:
+        content:
+          multipart/form-data:
+            schema: {$ref: "#/components/schemas/Upload"}
+      responses: {"200": {description: OK}}
+  /soap:
+    post:
+      requestBody:
+        content:
+          text/xml:
+            schema: {type: string}
+      responses: {"200": {description: OK}}
+components:
+  schemas:
+    Upload:
+      type: object
+      properties:
+        user: {type: string, example: ana}
+        file: {type: string, format: binary}
+"##;
+        let reqs = parse_swagger(yaml);
+        assert_eq!(reqs[0].form.as_ref().unwrap().to_text(), "user=ana\nfile=@");
+        assert!(reqs[0].headers.is_empty());
+        assert_eq!(reqs[0].body_type, BodyType::Form);
+        assert_eq!((reqs[1].body_type, reqs[1].headers[0].value.as_str()), (BodyType::Xml, "text/xml"));
+    }
 }
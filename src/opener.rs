@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use anyhow::{anyhow, Result};
+
+/// Abre archivos y URLs con la aplicación por defecto del sistema. Se clona para
+/// llevarlo a las tareas que lo necesitan (el login OAuth2 abre el navegador).
+#[derive(Clone)]
+pub enum Opener {
+    /// `open` en macOS, `rundll32 url.dll,FileProtocolHandler` en Windows y `xdg-open` en el resto
+    System,
+    /// Sin pantalla (`arthema run`): no abre nada y lo dice
+    Headless,
+    /// No lanza nada: guarda lo que se pidió abrir (compartido entre clones)
+    #[cfg(test)]
+    Fake(Arc<Mutex<Vec<String>>>),
+}
+
+impl Opener {
+    #[cfg(test)]
+    pub fn fake() -> Self { Opener::Fake(Arc::default()) }
+
+    /// Lo que se pidió abrir con el opener falso
+    #[cfg(test)]
+    pub fn opened(&self) -> Vec<String> {
+        match self {
+            Opener::Fake(opened) => opened.lock().map(|o| o.clone()).unwrap_or_default(),
+            _ => panic!("tests use the fake opener"),
+        }
+    }
+
+    pub fn open(&self, target: &str) -> Result<()> {
+        match self {
+            Opener::System => {
+                let (program, args) = system_command();
+                let mut child = Command::new(program).args(args).arg(target).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().map_err(|e| anyhow!("{}: {}", program, e))?;
+                // Se espera en otro hilo: sin `wait` quedaría como zombi hasta cerrar Arthema
+                std::thread::spawn(move || { let _ = child.wait(); });
+                Ok(())
+            }
+            Opener::Headless => Err(anyhow!("cannot open {} in headless mode", target)),
+            #[cfg(test)]
+            Opener::Fake(opened) => { opened.lock().map_err(|_| anyhow!("fake opener poisoned"))?.push(target.to_string()); Ok(()) }
+        }
+    }
+}
+
+fn system_command() -> (&'static str, &'static [&'static str]) {
+    if cfg!(target_os = "macos") { ("open", &[]) }
+    // Sin pasar por `cmd`: un `&` de la URL allí separa comandos
+    else if cfg!(windows) { ("rundll32", &["url.dll,FileProtocolHandler"]) }
+    else { ("xdg-open", &[]) }
+}
+
+/// Extensión para guardar un body con este Content-Type (sin punto)
+pub fn extension_for(content_type: &str) -> &'static str {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    match mime.as_str() {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "text/css" => "css",
+        "application/javascript" | "text/javascript" => "js",
+        "application/yaml" | "application/x-yaml" | "text/yaml" => "yaml",
+        m if m == "application/json" || m.ends_with("+json") => "json",
+        m if m.ends_with("/xml") || m.ends_with("+xml") => "xml",
+        m if m.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
+
+/// Archivo temporal que se borra al soltarse: cada pestaña guarda el de su respuesta
+/// para no reescribirlo cada vez que se abre
+pub struct TempFile(PathBuf);
+
+static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+impl TempFile {
+    /// `<tmp>/arthema-<pid>/response-<n>.<ext>`: nombres distintos para no pisar lo que
+    /// un visor tenga todavía abierto
+    pub fn write(bytes: &[u8], extension: &str) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("arthema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("response-{}.{}", NEXT_TEMP.fetch_add(1, Ordering::Relaxed), extension));
+        std::fs::write(&path, bytes)?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path { &self.0 }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) { let _ = std::fs::remove_file(&self.0); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_from_content_type() {
+        assert_eq!(extension_for("image/jpeg"), "jpg");
+        assert_eq!(extension_for("application/pdf"), "pdf");
+        assert_eq!(extension_for("application/problem+json; charset=utf-8"), "json");
+        assert_eq!(extension_for("text/plain"), "txt");
+        assert_eq!(extension_for(""), "bin");
+    }
+
+    #[test]
+    fn test_temp_file_is_removed_on_drop() {
+        let file = TempFile::write(b"%PDF-1.4", "pdf").unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.is_file() && path.extension().unwrap() == "pdf");
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use crate::http::{percent_decode, percent_encode};
+
+/// Fila del editor de parámetros. En el editor se escribe una por línea:
+///
+/// ```text
+/// page=1
+/// # limit=10        (desactivada: no va en la URL)
+/// :id=42            (parámetro de ruta, `/users/:id` o `/users/{id}`)
+/// ```
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Params {
+    /// Query en orden: (clave, valor sin codificar, activa)
+    pub query: Vec<(String, String, bool)>,
+    pub path: BTreeMap<String, String>,
+}
+
+impl Params {
+    pub fn parse(text: &str) -> Self {
+        let mut params = Params::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            let (line, enabled) = match line.strip_prefix('#') { Some(rest) => (rest.trim_start(), false), None => (line, true) };
+            let (key, value) = line.split_once('=').map(|(k, v)| (k.trim(), v.trim())).unwrap_or((line, ""));
+            match key.strip_prefix(':') {
+                Some(name) if enabled => { params.path.insert(name.to_string(), value.to_string()); }
+                _ => params.query.push((key.to_string(), value.to_string(), enabled)),
+            }
+        }
+        params
+    }
+
+    pub fn to_text(&self) -> String {
+        let path = self.path.iter().map(|(k, v)| format!(":{}={}", k, v));
+        let query = self.query.iter().map(|(k, v, on)| format!("{}{}={}", if *on { "" } else { "# " }, k, v));
+        path.chain(query).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Lo que las filas ponen en la URL: la query activa (sin codificar) y los nombres de ruta
+    pub fn url_parts(&self) -> (Vec<(String, String)>, Vec<&String>) {
+        (self.query.iter().filter(|(k, _, on)| *on && !k.is_empty()).map(|(k, v, _)| (k.clone(), v.clone())).collect(), self.path.keys().collect())
+    }
+
+    pub fn disabled(&self) -> Vec<(String, String)> {
+        self.query.iter().filter(|(_, _, on)| !on).map(|(k, v, _)| (k.clone(), v.clone())).collect()
+    }
+
+    /// Rehace las filas a partir de la URL: la query sale de la URL, las desactivadas
+    /// se conservan y los parámetros de ruta mantienen su valor si siguen en la URL.
+    pub fn from_url(url: &str, previous: &Params) -> Self {
+        let mut query: Vec<(String, String, bool)> = parse_query(url).into_iter().map(|(k, v)| (k, v, true)).collect();
+        query.extend(previous.query.iter().filter(|(_, _, on)| !on).cloned());
+        let path = path_param_names(url).into_iter().map(|name| {
+            let value = previous.path.get(&name).cloned().unwrap_or_default();
+            (name, value)
+        }).collect();
+        Params { query, path }
+    }
+}
+
+/// (base, query, fragment) de una URL sin validarla (puede contener `{{var}}`)
+fn split_url(url: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (rest, fragment) = match url.split_once('#') { Some((r, f)) => (r, Some(f)), None => (url, None) };
+    match rest.split_once('?') {
+        Some((base, query)) => (base, Some(query), fragment),
+        None => (rest, None, fragment),
+    }
+}
+
+pub fn parse_query(url: &str) -> Vec<(String, String)> {
+    let (_, query, _) = split_url(url);
+    query.unwrap_or("").split('&').filter(|p| !p.is_empty()).map(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        (percent_decode(k), percent_decode(v))
+    }).collect()
+}
+
+/// Sustituye la query de `url` por las filas activas, codificadas
+pub fn build_url(url: &str, params: &Params) -> String {
+    let (base, _, fragment) = split_url(url);
+    let query: Vec<String> = params.query.iter().filter(|(k, _, on)| *on && !k.is_empty()).map(|(k, v, _)| {
+        if v.is_empty() { encode_component(k) } else { format!("{}={}", encode_component(k), encode_component(v)) }
+    }).collect();
+    let mut out = base.to_string();
+    if !query.is_empty() { out.push('?'); out.push_str(&query.join("&")); }
+    if let Some(f) = fragment { out.push('#'); out.push_str(f); }
+    out
+}
+
+/// Percent-encoding que respeta los `{{var}}` para que se sustituyan después
+pub fn encode_component(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else { break };
+        out.push_str(&percent_encode(&rest[..start]));
+        out.push_str(&rest[start..start + len + 2]);
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(&percent_encode(rest));
+    out
+}
+
+/// Segmentos `:id` y `{id}` de la ruta (no confunde `{{var}}` con `{id}`)
+pub fn path_param_names(url: &str) -> Vec<String> {
+    let (base, _, _) = split_url(url);
+    let path = base.find("://").map(|i| &base[i + 3..]).map(|r| r.find('/').map(|j| &r[j..]).unwrap_or("")).unwrap_or(base);
+    let mut names: Vec<String> = Vec::new();
+    for segment in path.split('/') {
+        let name = if let Some(n) = segment.strip_prefix(':') { Some(n) }
+            else if segment.starts_with('{') && !segment.starts_with("{{") && segment.ends_with('}') { Some(&segment[1..segment.len() - 1]) }
+            else { None };
+        if let Some(n) = name.filter(|n| !n.is_empty()) && !names.iter().any(|x| x == n) { names.push(n.to_string()); }
+    }
+    names
+}
+
+/// Rellena `:id`/`{id}` con su valor codificado; los que no tienen valor se quedan tal cual
+pub fn apply_path_params(url: &str, values: &BTreeMap<String, String>) -> String {
+    if values.values().all(String::is_empty) { return url.to_string(); }
+    let (base, query, fragment) = split_url(url);
+    let (scheme_host, path) = match base.find("://") {
+        Some(i) => match base[i + 3..].find('/') { Some(j) => base.split_at(i + 3 + j), None => (base, "") },
+        None => ("", base),
+    };
+    let path: Vec<String> = path.split('/').map(|segment| {
+        let name = segment.strip_prefix(':').or_else(|| segment.strip_prefix('{').filter(|s| !s.starts_with('{')).and_then(|s| s.strip_suffix('}')));
+        match name.and_then(|n| values.get(n)).filter(|v| !v.is_empty()) {
+            Some(v) => encode_component(v),
+            None => segment.to_string(),
+        }
+    }).collect();
+    let mut out = format!("{}{}", scheme_host, path.join("/"));
+    if let Some(q) = query { out.push('?'); out.push_str(q); }
+    if let Some(f) = fragment { out.push('#'); out.push_str(f); }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_roundtrip_and_url_sync() {
+        let url = "https://api.com/users?page=2&q=hello%20world#top";
+        let params = Params::from_url(url, &Params::parse("# limit=10"));
+        assert_eq!(params.to_text(), "page=2\nq=hello world\n# limit=10");
+
+        let mut edited = Params::parse("page=3\nq=a&b\n# limit=10\nflag");
+        assert_eq!(build_url(url, &edited), "https://api.com/users?page=3&q=a%26b&flag#top");
+        edited.query.iter_mut().for_each(|p| p.2 = false);
+        assert_eq!(build_url(url, &edited), "https://api.com/users#top");
+    }
+
+    #[test]
+    fn test_placeholders_are_not_encoded() {
+        assert_eq!(encode_component("{{token}} x"), "{{token}}%20x");
+        let params = Params::parse("key={{api_key}}");
+        assert_eq!(build_url("{{base}}/items", &params), "{{base}}/items?key={{api_key}}");
+    }
+
+    #[test]
+    fn test_path_params() {
+        let url = "{{base}}/users/:id/posts/{postId}?x=1";
+        assert_eq!(path_param_names(url), vec!["id".to_string(), "postId".to_string()]);
+        assert_eq!(path_param_names("https://api.com/v1/{{version}}/items"), Vec::<String>::new());
+
+        let params = Params::from_url(url, &Params::parse(":id=7\n:gone=1"));
+        assert_eq!(params.path.get("id").map(String::as_str), Some("7"));
+        assert!(!params.path.contains_key("gone"));
+        let mut values = params.path.clone();
+        values.insert("postId".to_string(), "a b".to_string());
+        assert_eq!(apply_path_params("https://api.com/users/:id/posts/{postId}?x=1", &values), "https://api.com/users/7/posts/a%20b?x=1");
+    }
+}
@@ -1,6 +1,9 @@
 use serde_json::Value;
 use crate::collections::ApiRequest;
-use std::collections::HashMap;
+use crate::body::BodyType;
+use crate::form::{Form, FormField, FormKind};
+use crate::graphql::GraphQlBody;
+use crate::headers::Header;
 
 pub fn parse_postman(content: &str) -> Vec<ApiRequest> {
     let json: Value = serde_json::from_str(content).unwrap_or(Value::Null);
@@ -20,9 +23,10 @@ fn process_items(items: &[Value], group: Option<String>, requests: &mut Vec<ApiR
     for item in items {
         // Caso 1: Es una carpeta (tiene campo 'item')
         if let Some(sub_items) = item["item"].as_array() {
-            let folder_name = item["name"].as_str().unwrap_or("Folder").to_string();
-            // Mantener jerarquía simple uniendo nombres si es necesario o solo el último tag
-            process_items(sub_items, Some(folder_name), requests);
+            let folder_name = crate::collections::folder_segment(item["name"].as_str().unwrap_or("Folder"));
+            // Las subcarpetas cuelgan de la ruta de su padre ("Users/Admin")
+            let path = match &group { Some(parent) => format!("{}/{}", parent, folder_name), None => folder_name };
+            process_items(sub_items, Some(path), requests);
         } 
         // Caso 2: Es una petición (tiene campo 'request')
         else if item.get("request").is_some() {
@@ -42,32 +46,80 @@ fn process_items(items: &[Value], group: Option<String>, requests: &mut Vec<ApiR
                 else { u["raw"].as_str().unwrap_or("").to_string() }
             } else { "".to_string() };
 
-            let mut headers = HashMap::new();
+            let mut headers = Vec::new();
             if let Some(h_list) = req_obj.get("header").and_then(|h| h.as_array()) {
                 for h in h_list {
                     if let (Some(k), Some(v)) = (h["key"].as_str(), h["value"].as_str()) {
-                        headers.insert(k.to_string(), v.to_string());
+                        headers.push(Header { name: k.to_string(), value: v.to_string(), enabled: !h["disabled"].as_bool().unwrap_or(false) });
                     }
                 }
             }
 
-            let body = req_obj.get("body")
-                .and_then(|b| b.get("raw"))
-                .and_then(|r| r.as_str())
-                .map(|s| s.to_string());
-
-            requests.push(ApiRequest {
+            let mut req = ApiRequest {
                 name,
                 url,
                 method,
                 headers,
-                body,
                 group: group.clone(),
-            });
+                ..Default::default()
+            };
+            if let Some(body) = req_obj.get("body") { apply_body(&mut req, body); }
+            requests.push(req);
         }
     }
 }
 
+/// `body.mode`: raw (con su `language`), graphql, file, urlencoded y formdata
+fn apply_body(req: &mut ApiRequest, body: &Value) {
+    match body["mode"].as_str().unwrap_or("raw") {
+        "graphql" => {
+            let gql = &body["graphql"];
+            req.body_type = BodyType::GraphQl;
+            req.graphql = Some(GraphQlBody { query: gql["query"].as_str().unwrap_or("").to_string(), variables: gql["variables"].as_str().unwrap_or("").to_string(), operation_name: None });
+        }
+        "file" => {
+            req.body_type = BodyType::Binary;
+            req.file_path = body.pointer("/file/src").and_then(Value::as_str).map(str::to_string);
+        }
+        "urlencoded" | "formdata" => {
+            req.form = parse_form(body);
+            if let Some(form) = &req.form { req.body_type = BodyType::from_form_kind(form.kind); }
+        }
+        _ => {
+            req.body = body["raw"].as_str().map(str::to_string);
+            req.body_type = match body.pointer("/options/raw/language").and_then(Value::as_str) {
+                Some("xml") => BodyType::Xml,
+                Some("text") => BodyType::Text,
+                Some("javascript" | "html") => BodyType::Raw,
+                _ => BodyType::Json,
+            };
+        }
+    }
+}
+
+/// `body.mode` `urlencoded` y `formdata` (campos `text` y `file` con su `src`)
+fn parse_form(body: &Value) -> Option<Form> {
+    let (kind, items) = match body["mode"].as_str()? {
+        "urlencoded" => (FormKind::UrlEncoded, body["urlencoded"].as_array()?),
+        "formdata" => (FormKind::Multipart, body["formdata"].as_array()?),
+        _ => return None,
+    };
+    let fields = items.iter().filter_map(|item| {
+        let name = item["key"].as_str()?;
+        let mut field = if item["type"].as_str() == Some("file") {
+            // `src` puede ser una ruta o una lista de rutas; se toma la primera
+            let src = item["src"].as_str().or_else(|| item["src"].as_array().and_then(|a| a.first()).and_then(|v| v.as_str())).unwrap_or("");
+            FormField::file(name, src)
+        } else {
+            FormField::text(name, item["value"].as_str().unwrap_or(""))
+        };
+        field.content_type = item["contentType"].as_str().filter(|c| !c.is_empty()).map(str::to_string);
+        field.enabled = !item["disabled"].as_bool().unwrap_or(false);
+        Some(field)
+    }).collect();
+    Some(Form { kind, fields })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,14 +139,52 @@ mod tests {
                                 "header": [{"key": "Content-Type", "value": "application/json"}],
                                 "body": {"raw": "{\"user\":\"test\"}"}
                             }
+                        },
+                        {
+                            "name": "Admin/Ops",
+                            "item": [{"name": "Sudo", "request": {"method": "POST", "url": "https://api.com/sudo"}}]
                         }
                     ]
                 }
             ]
         }"#;
         let reqs = parse_postman(json);
-        assert!(reqs.len() > 0, "Debería haber al menos una petición");
+        assert!(!reqs.is_empty(), "Debería haber al menos una petición");
         assert_eq!(reqs[0].name, "Login");
         assert_eq!(reqs[0].group.as_ref().unwrap(), "Auth");
+        // Las carpetas anidadas se conservan como ruta
+        assert_eq!(reqs[1].group.as_deref(), Some("Auth/Admin-Ops"));
+    }
+
+    #[test]
+    fn test_parse_postman_form_bodies() {
+        let json = r#"{"item": [
+            {"name": "Upload", "request": {"method": "POST", "url": "https://api.com/up", "body": {"mode": "formdata", "formdata": [
+                {"key": "title", "value": "cv", "type": "text"},
+                {"key": "doc", "src": "/tmp/cv.pdf", "type": "file", "contentType": "application/pdf"},
+                {"key": "old", "value": "1", "type": "text", "disabled": true}
+            ]}}},
+            {"name": "Login", "request": {"method": "POST", "url": "https://api.com/login", "body": {"mode": "urlencoded", "urlencoded": [{"key": "user", "value": "ana"}]}}}
+        ]}"#;
+        let reqs = parse_postman(json);
+        let upload = reqs[0].form.as_ref().unwrap();
+        assert_eq!(upload.to_text(), "title=cv\ndoc=@/tmp/cv.pdf;type=application/pdf\n# old=1");
+        assert_eq!(reqs[1].form.as_ref().unwrap().kind, FormKind::UrlEncoded);
+        assert_eq!(reqs[1].form.as_ref().unwrap().encoded(), "user=ana");
+        assert_eq!((reqs[0].body_type, reqs[1].body_type), (BodyType::Form, BodyType::UrlEncoded));
+    }
+
+    #[test]
+    fn test_parse_postman_graphql_xml_and_file_bodies() {
+        let json = r#"{"item": [
+            {"name": "Q", "request": {"method": "POST", "url": "https://api.com/gql", "body": {"mode": "graphql", "graphql": {"query": "{ me { id } }", "variables": "{\"a\": 1}"}}}},
+            {"name": "X", "request": {"method": "POST", "url": "https://api.com/soap", "body": {"mode": "raw", "raw": "<a/>", "options": {"raw": {"language": "xml"}}}}},
+            {"name": "F", "request": {"method": "PUT", "url": "https://api.com/blob", "body": {"mode": "file", "file": {"src": "/tmp/a.bin"}}}}
+        ]}"#;
+        let reqs = parse_postman(json);
+        assert_eq!(reqs[0].body_type, BodyType::GraphQl);
+        assert_eq!(reqs[0].graphql.as_ref().unwrap().variables, "{\"a\": 1}");
+        assert_eq!((reqs[1].body_type, reqs[1].body.as_deref()), (BodyType::Xml, Some("<a/>")));
+        assert_eq!((reqs[2].body_type, reqs[2].file_path.as_deref()), (BodyType::Binary, Some("/tmp/a.bin")));
     }
 }
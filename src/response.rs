@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+/// Respuesta HTTP completa tal como la recibió el cliente.
+#[derive(Clone, Debug, Default)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub reason: String,
+    pub http_version: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub final_url: String,
+    pub remote_addr: Option<String>,
+    /// Tiempo hasta recibir los headers (TTFB aproximado)
+    pub time_to_headers: Duration,
+    /// Tiempo total incluyendo la descarga del body
+    pub elapsed: Duration,
+}
+
+/// Avance de una respuesta que todavía se está recibiendo
+#[derive(Debug)]
+pub enum Progress {
+    /// Status y headers; el body llega después
+    Headers(ApiResponse),
+    Chunk(Vec<u8>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResponseView { Body, Headers, Timing }
+
+impl ApiResponse {
+    /// Lee la respuesta completa. Con `on` avisa de cada paso (primero los headers, luego
+    /// cada trozo según llega) y el body no se acumula aquí: se queda con él quien escucha y
+    /// la respuesta devuelta solo trae status, headers y tiempos
+    pub async fn read(mut resp: reqwest::Response, started: std::time::Instant, on: Option<&(dyn Fn(Progress) + Send + Sync)>) -> reqwest::Result<Self> {
+        let time_to_headers = started.elapsed();
+        let status = resp.status();
+        let mut head = Self {
+            status: status.as_u16(),
+            reason: status.canonical_reason().unwrap_or("").to_string(),
+            http_version: format!("{:?}", resp.version()),
+            headers: resp.headers().iter().map(|(k, v)| (k.as_str().to_string(), String::from_utf8_lossy(v.as_bytes()).to_string())).collect(),
+            body: Vec::new(),
+            final_url: resp.url().to_string(),
+            remote_addr: resp.remote_addr().map(|a| a.to_string()),
+            time_to_headers,
+            elapsed: time_to_headers,
+        };
+        if let Some(on) = on { on(Progress::Headers(head.clone())); }
+        while let Some(chunk) = resp.chunk().await? {
+            match on {
+                Some(on) => on(Progress::Chunk(chunk.to_vec())),
+                None => head.body.extend_from_slice(&chunk),
+            }
+        }
+        head.elapsed = started.elapsed();
+        Ok(head)
+    }
+
+    pub fn status_line(&self) -> String { format!("{} {}", self.status, self.reason).trim().to_string() }
+
+    pub fn is_success(&self) -> bool { (200..300).contains(&self.status) }
+
+    /// Búsqueda de header sin distinguir mayúsculas (devuelve el primero)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn content_type(&self) -> &str { self.header("content-type").unwrap_or("") }
+
+    /// Tipo MIME sin parámetros; si el servidor no lo dice (o manda `octet-stream`) el que
+    /// indiquen los primeros bytes
+    pub fn mime(&self) -> &str {
+        let declared = self.content_type().split(';').next().unwrap_or("").trim();
+        match sniff(&self.body) {
+            Some(sniffed) if declared.is_empty() || declared.eq_ignore_ascii_case("application/octet-stream") => sniffed,
+            _ => declared,
+        }
+    }
+
+    /// SVG es texto: se muestra como XML
+    pub fn is_image(&self) -> bool { let m = self.mime(); m.starts_with("image/") && !m.contains("svg") }
+
+    /// Body que no se puede mostrar como texto: firma binaria conocida, tipo binario declarado
+    /// o, si el tipo no es de texto, bytes que no son UTF-8
+    pub fn is_binary(&self) -> bool {
+        let mime = self.mime().to_lowercase();
+        let textual = mime.starts_with("text/") || ["json", "xml", "javascript", "yaml", "x-www-form-urlencoded", "graphql", "csv", "html"].iter().any(|t| mime.contains(t));
+        if sniff(&self.body).is_some() || self.is_image() { return true; }
+        if textual { return false; }
+        let head = &self.body[..self.body.len().min(1024)];
+        mime.starts_with("audio/") || mime.starts_with("video/") || mime.starts_with("font/") || ["octet-stream", "pdf", "zip", "protobuf", "grpc", "msgpack"].iter().any(|t| mime.contains(t))
+            || head.contains(&0) || std::str::from_utf8(head).is_err_and(|e| e.error_len().is_some())
+    }
+
+    pub fn is_event_stream(&self) -> bool { self.mime().eq_ignore_ascii_case("text/event-stream") }
+
+    pub fn is_xml(&self) -> bool { self.content_type().contains("xml") }
+
+    pub fn body_text(&self) -> String { String::from_utf8_lossy(&self.body).to_string() }
+
+    /// Body formateado para mostrar: JSON o XML indentado si se puede, texto plano si no.
+    pub fn pretty_body(&self) -> String {
+        let text = self.body_text();
+        if self.is_xml() { return crate::xml::pretty(&text).unwrap_or(text); }
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(val) => serde_json::to_string_pretty(&val).unwrap_or(text),
+            Err(_) => text,
+        }
+    }
+
+    /// Resumen de una línea para títulos y prompts de IA
+    pub fn summary(&self) -> String {
+        format!("{} · {} ms · {}", self.status_line(), self.elapsed.as_millis(), format_size(self.body.len()))
+    }
+}
+
+/// Firmas de formatos binarios comunes: (primeros bytes, tipo MIME)
+const MAGIC: [(&[u8], &str); 11] = [
+    (b"%PDF-", "application/pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"\0asm", "application/wasm"),
+    (b"\x7fELF", "application/x-elf"),
+    (b"OggS", "audio/ogg"),
+];
+
+/// Tipo MIME según los primeros bytes del body
+pub fn sniff(body: &[u8]) -> Option<&'static str> {
+    if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" { return Some("image/webp"); }
+    MAGIC.iter().find(|(magic, _)| body.starts_with(magic)).map(|(_, mime)| *mime)
+}
+
+/// Volcado hexadecimal con columna ASCII de los primeros `limit` bytes:
+/// `00000000  25 50 44 46 2d 31 2e 34  0a 25 c3 a4 fc b6 0a 31  |%PDF-1.4.%.....1|`
+pub fn hex_dump(bytes: &[u8], limit: usize) -> String {
+    let mut out = String::new();
+    for (n, row) in bytes[..bytes.len().min(limit)].chunks(16).enumerate() {
+        let hex: Vec<String> = (0..16).map(|i| row.get(i).map(|b| format!("{:02x}", b)).unwrap_or_else(|| "  ".to_string())).collect();
+        let ascii: String = row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        out.push_str(&format!("{:08x}  {}  {}  |{}|\n", n * 16, hex[..8].join(" "), hex[8..].join(" "), ascii));
+    }
+    if bytes.len() > limit { out.push_str(&format!("… {} more bytes (w saves the whole body)\n", bytes.len() - limit)); }
+    out
+}
+
+pub fn format_size(bytes: usize) -> String {
+    if bytes < 1024 { format!("{} B", bytes) }
+    else if bytes < 1024 * 1024 { format!("{:.1} KB", bytes as f64 / 1024.0) }
+    else { format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ApiResponse {
+        ApiResponse {
+            status: 201,
+            reason: "Created".to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: br#"{"id":1}"#.to_vec(),
+            elapsed: Duration::from_millis(42),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_header_lookup_is_case_insensitive() {
+        let r = sample();
+        assert_eq!(r.header("content-type"), Some("application/json"));
+        assert_eq!(r.header("x-missing"), None);
+        assert!(!r.is_image());
+    }
+
+    #[test]
+    fn test_pretty_body_and_summary() {
+        let r = sample();
+        assert_eq!(r.pretty_body(), "{\n  \"id\": 1\n}");
+        assert_eq!(r.summary(), "201 Created · 42 ms · 8 B");
+        assert!(r.is_success());
+    }
+
+    #[test]
+    fn test_binary_sniffing_and_hex_dump() {
+        let with = |content_type: &str, body: &[u8]| ApiResponse { headers: vec![("Content-Type".to_string(), content_type.to_string())], body: body.to_vec(), ..Default::default() };
+        let pdf = with("application/octet-stream", b"%PDF-1.4\n%\xc3\xa4");
+        assert_eq!(pdf.mime(), "application/pdf");
+        assert!(pdf.is_binary() && !pdf.is_image());
+        assert!(with("", b"\x89PNG\r\n\x1a\n....").is_image());
+        assert!(with("application/x-protobuf", b"\x08\x96\x01").is_binary());
+        assert!(with("", b"\x08\x00\x01").is_binary());
+        assert!(!sample().is_binary() && !with("text/plain; charset=utf-8", "ñandú".as_bytes()).is_binary());
+        assert!(!with("image/svg+xml", b"<svg/>").is_binary());
+
+        assert_eq!(hex_dump(b"%PDF-1.4\n\x00", 64), "00000000  25 50 44 46 2d 31 2e 34  0a 00                    |%PDF-1.4..|\n");
+        assert!(hex_dump(&[0u8; 40], 16).ends_with("… 24 more bytes (w saves the whole body)\n"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(2048), "2.0 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,385 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::assertions::AssertionResult;
+use crate::auth::TokenStore;
+use crate::collections::ApiRequest;
+use crate::environments::Environment;
+use crate::http::PreparedRequest;
+use crate::opener::Opener;
+use crate::response::ApiResponse;
+
+const USAGE: &str = "Usage: arthema run [PATH] [options]
+
+Runs every request of a collection in order and exits non-zero if any fails.
+PATH is a collections directory, an exported request JSON or a Postman collection
+(default: the collections of the workspace).
+
+Options:
+  -w, --workspace <DIR>   Workspace to use (default: ./.clicaude if present, else the last one opened)
+  -e, --env <NAME|FILE>   Environment name (in the workspace's environments) or JSON file
+  -g, --group <NAME>      Only run requests of this folder/group and its subfolders
+      --junit <FILE>      Write a JUnit XML report
+      --json <FILE>       Write a JSON report
+      --allow-skipped     Run the rest when some collection files cannot be read
+                          (by default they stop the run with exit code 2)
+  -h, --help              Show this help";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RunOptions {
+    pub source: Option<PathBuf>,
+    pub workspace: Option<PathBuf>,
+    pub env: Option<String>,
+    pub group: Option<String>,
+    pub junit: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+    pub allow_skipped: bool,
+}
+
+/// Resultado de ejecutar una petición de la colección
+#[derive(Serialize, Debug, Clone)]
+pub struct RequestRun {
+    pub name: String,
+    pub group: Option<String>,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub elapsed_ms: u128,
+    pub assertions: Vec<AssertionResult>,
+    pub logs: Vec<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub response: Option<ApiResponse>,
+}
+
+impl RequestRun {
+    pub fn passed(&self) -> bool { self.error.is_none() && self.assertions.iter().all(|a| a.passed) }
+}
+
+pub fn parse_args(args: &[String]) -> Result<RunOptions, String> {
+    let mut opts = RunOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| iter.next().cloned().ok_or_else(|| format!("missing value for {}", flag));
+        match arg.as_str() {
+            "-w" | "--workspace" => opts.workspace = Some(PathBuf::from(value(arg)?)),
+            "-e" | "--env" => opts.env = Some(value(arg)?),
+            "-g" | "--group" => opts.group = Some(value(arg)?),
+            "--junit" => opts.junit = Some(PathBuf::from(value(arg)?)),
+            "--json" => opts.json = Some(PathBuf::from(value(arg)?)),
+            "--allow-skipped" => opts.allow_skipped = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+            path if opts.source.is_none() => opts.source = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+    Ok(opts)
+}
+
+/// Directorio, array de `ApiRequest`, una sola `ApiRequest` o colección de Postman. Los
+/// archivos del directorio que no se pueden leer son un error salvo con `allow_skipped`
+pub fn load_source(path: &Path, allow_skipped: bool) -> Result<Vec<ApiRequest>> {
+    if path.is_dir() {
+        // En CI no se reescriben archivos: sólo se migran en memoria
+        let loaded = crate::collections::load_dir(path)?;
+        if !allow_skipped && !loaded.errors.is_empty() {
+            return Err(anyhow!("{} collection file(s) could not be loaded (use --allow-skipped to run the rest):\n  {}", loaded.errors.len(), loaded.errors.join("\n  ")));
+        }
+        for e in &loaded.errors { eprintln!("warning: skipped {}", e); }
+        return Ok(loaded.requests);
+    }
+    let content = fs::read_to_string(path)?;
+    if let Ok(reqs) = serde_json::from_str::<Vec<ApiRequest>>(&content) { return Ok(reqs); }
+    if let Ok((req, _)) = crate::collections::from_json(&content) { return Ok(vec![req]); }
+    let reqs = crate::postman::parse_postman(&content);
+    if reqs.is_empty() { Err(anyhow!("{} is not a collection", path.display())) } else { Ok(reqs) }
+}
+
+fn load_env(name_or_file: &str, base_path: &str) -> Result<BTreeMap<String, String>> {
+    let path = Path::new(name_or_file);
+    if path.extension().and_then(|s| s.to_str()) == Some("json") && path.exists() {
+        let env: Environment = serde_json::from_str(&fs::read_to_string(path)?)?;
+        return Ok(env.variables);
+    }
+    let manager = crate::environments::EnvironmentManager::new(base_path);
+    for e in &manager.load_errors { eprintln!("warning: skipped environment {}", e); }
+    manager.environments.into_iter().find(|e| e.name == name_or_file).map(|e| e.variables).ok_or_else(|| anyhow!("environment '{}' not found", name_or_file))
+}
+
+pub fn filter_group(requests: Vec<ApiRequest>, group: Option<&str>) -> Vec<ApiRequest> {
+    match group {
+        Some(g) => requests.into_iter().filter(|r| crate::collections::in_folder(r.group.as_deref().unwrap_or("UNGROUPED"), g)).collect(),
+        None => requests,
+    }
+}
+
+/// Sustituye `Inherit` por la auth de la carpeta de cada petición
+pub fn resolve_folder_auth(requests: Vec<ApiRequest>, folder_auth: &BTreeMap<String, crate::auth::Auth>) -> Vec<ApiRequest> {
+    requests.into_iter().map(|mut r| {
+        r.auth = crate::auth::resolve(&r.auth, crate::collections::folder_auth_for(folder_auth, r.group.as_deref()).map(|(_, a)| a));
+        r
+    }).collect()
+}
+
+/// Ejecuta una petición completa: pre-script, variables, envío, post-script y aserciones.
+/// Las variables capturadas se quedan en `vars` para las siguientes peticiones.
+pub async fn run_request(req: &ApiRequest, vars: &mut BTreeMap<String, String>, tokens: &TokenStore, opener: &Opener) -> RequestRun {
+    let mut prepared = PreparedRequest::from_api_request(req);
+    let mut run = RequestRun {
+        name: req.name.clone(), group: req.group.clone(), method: req.method.clone(), url: req.url.clone(),
+        status: None, elapsed_ms: 0, assertions: Vec::new(), logs: Vec::new(), error: None, response: None,
+    };
+    if let Some(script) = &req.pre_request_script {
+        match crate::scripting::run_pre_request(script, &mut prepared, vars) {
+            Ok(out) => { vars.extend(out.vars); run.logs.extend(out.logs); }
+            Err(e) => { run.error = Some(format!("pre-request script: {}", e)); return run; }
+        }
+    }
+    prepared.substitute(vars);
+    run.method = prepared.method.clone();
+    run.url = prepared.url.clone();
+
+    let resp = match crate::http::send(prepared, tokens, opener).await {
+        Ok(r) => r,
+        Err(e) => { run.error = Some(e.to_string()); return run; }
+    };
+    run.status = Some(resp.status);
+    run.elapsed_ms = resp.elapsed.as_millis();
+    if let Some(script) = &req.post_response_script {
+        match crate::scripting::run_post_response(script, &resp, vars) {
+            Ok(out) => { vars.extend(out.vars); run.logs.extend(out.logs); }
+            Err(e) => run.error = Some(format!("post-response script: {}", e)),
+        }
+    }
+    run.assertions = crate::assertions::evaluate_all(&req.assertions, &resp);
+    run.response = Some(resp);
+    run
+}
+
+/// Ejecuta una lista de peticiones y entrega cada resultado en cuanto llega.
+/// Con `concurrency <= 1` van en orden y comparten las variables capturadas;
+/// en paralelo cada petición parte de una copia de `vars`.
+pub async fn run_all(requests: Vec<ApiRequest>, mut vars: BTreeMap<String, String>, concurrency: usize, tokens: &TokenStore, opener: &Opener, mut on_result: impl FnMut(usize, RequestRun)) {
+    if concurrency <= 1 {
+        for (i, req) in requests.iter().enumerate() { on_result(i, run_request(req, &mut vars, tokens, opener).await); }
+        return;
+    }
+    use futures::StreamExt;
+    let mut stream = futures::stream::iter(requests.into_iter().enumerate().map(|(i, req)| {
+        let mut vars = vars.clone();
+        async move { (i, run_request(&req, &mut vars, tokens, opener).await) }
+    })).buffer_unordered(concurrency);
+    while let Some((i, run)) = stream.next().await { on_result(i, run); }
+}
+
+pub struct RunnerRow {
+    pub request: ApiRequest,
+    pub result: Option<RequestRun>,
+}
+
+/// Estado de una ejecución de carpeta en la TUI
+pub struct CollectionRun {
+    /// Identifica la ejecución para descartar resultados de una anterior
+    pub id: u64,
+    pub folder: String,
+    pub concurrency: usize,
+    pub rows: Vec<RunnerRow>,
+    pub selected: usize,
+    pub started: Instant,
+    pub finished: Option<Duration>,
+    /// Tarea que ejecuta las peticiones; reemplazar la ejecución (relanzarla) la cancela
+    pub task: Option<tokio::task::AbortHandle>,
+}
+
+impl Drop for CollectionRun {
+    fn drop(&mut self) { if let Some(task) = &self.task { task.abort(); } }
+}
+
+impl CollectionRun {
+    pub fn new(id: u64, folder: String, requests: Vec<ApiRequest>, concurrency: usize) -> Self {
+        let rows = requests.into_iter().map(|request| RunnerRow { request, result: None }).collect();
+        Self { id, folder, concurrency, rows, selected: 0, started: Instant::now(), finished: None, task: None }
+    }
+
+    pub fn record(&mut self, index: usize, run: RequestRun) {
+        if let Some(row) = self.rows.get_mut(index) { row.result = Some(run); }
+        if self.finished.is_none() && self.completed() == self.rows.len() { self.finished = Some(self.started.elapsed()); }
+    }
+
+    pub fn completed(&self) -> usize { self.rows.iter().filter(|r| r.result.is_some()).count() }
+
+    pub fn failed(&self) -> usize { self.rows.iter().filter(|r| r.result.as_ref().is_some_and(|run| !run.passed())).count() }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        if self.rows.is_empty() { return; }
+        self.selected = (self.selected as i32 + delta).rem_euclid(self.rows.len() as i32) as usize;
+    }
+
+    pub fn selected_row(&self) -> Option<&RunnerRow> { self.rows.get(self.selected) }
+}
+
+pub async fn run_cli(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "-h" || a == "--help") { println!("{}", USAGE); return 0; }
+    let opts = match parse_args(args) {
+        Ok(o) => o,
+        Err(e) => { eprintln!("error: {}\n\n{}", e, USAGE); return 2; }
+    };
+    match run_collection(&opts).await {
+        Ok(runs) => if runs.iter().all(RequestRun::passed) { 0 } else { 1 },
+        Err(e) => { eprintln!("error: {}", e); 2 }
+    }
+}
+
+async fn run_collection(opts: &RunOptions) -> Result<Vec<RequestRun>> {
+    let workspace = crate::workspace::resolve(&crate::config::Config::load(), opts.workspace.as_deref());
+    let base_path = &*workspace.to_string_lossy();
+    let source = opts.source.clone().unwrap_or_else(|| PathBuf::from(format!("{}/collections", base_path)));
+    let requests = filter_group(load_source(&source, opts.allow_skipped)?, opts.group.as_deref());
+    let requests = resolve_folder_auth(requests, &crate::collections::load_folder_auth(base_path));
+    let tokens = TokenStore::new(base_path);
+    if requests.is_empty() { return Err(anyhow!("no requests to run in {}", source.display())); }
+    let mut vars = match &opts.env { Some(e) => load_env(e, base_path)?, None => BTreeMap::new() };
+
+    let mut runs = Vec::new();
+    for req in &requests {
+        let run = run_request(req, &mut vars, &tokens, &Opener::Headless).await;
+        print!("{}", format_run(&run));
+        runs.push(run);
+    }
+    println!("{}", summary(&runs));
+
+    if let Some(path) = &opts.junit { fs::write(path, junit_report(&runs))?; }
+    if let Some(path) = &opts.json { fs::write(path, json_report(&runs)?)?; }
+    Ok(runs)
+}
+
+pub fn format_run(run: &RequestRun) -> String {
+    let icon = if run.passed() { "✔" } else { "✘" };
+    let name = match &run.group { Some(g) => format!("{} / {}", g, run.name), None => run.name.clone() };
+    let status = run.status.map(|s| s.to_string()).unwrap_or_else(|| "---".to_string());
+    let mut out = format!("{} {}  {} {}  [{}] {} ms\n", icon, name, run.method, run.url, status, run.elapsed_ms);
+    for l in &run.logs { out.push_str(&format!("    > {}\n", l)); }
+    for a in &run.assertions { out.push_str(&format!("    {} {} ({})\n", if a.passed { "✔" } else { "✘" }, a.label, a.detail)); }
+    if let Some(e) = &run.error { out.push_str(&format!("    ERROR: {}\n", e)); }
+    out
+}
+
+pub fn summary(runs: &[RequestRun]) -> String {
+    let failed = runs.iter().filter(|r| !r.passed()).count();
+    let total_asserts: usize = runs.iter().map(|r| r.assertions.len()).sum();
+    let passed_asserts: usize = runs.iter().map(|r| r.assertions.iter().filter(|a| a.passed).count()).sum();
+    format!("\n{} requests, {} failed | {}/{} assertions passed", runs.len(), failed, passed_asserts, total_asserts)
+}
+
+pub fn junit_report(runs: &[RequestRun]) -> String {
+    let failures = runs.iter().filter(|r| !r.passed()).count();
+    let total_time: u128 = runs.iter().map(|r| r.elapsed_ms).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"arthema\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n", runs.len(), failures, total_time as f64 / 1000.0));
+    xml.push_str(&format!("  <testsuite name=\"arthema\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n", runs.len(), failures, total_time as f64 / 1000.0));
+    for run in runs {
+        let class = run.group.as_deref().unwrap_or("UNGROUPED");
+        xml.push_str(&format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"", xml_escape(&run.name), xml_escape(class), run.elapsed_ms as f64 / 1000.0));
+        if run.passed() { xml.push_str("/>\n"); continue; }
+        xml.push_str(">\n");
+        let mut reasons: Vec<String> = run.assertions.iter().filter(|a| !a.passed).map(|a| format!("{} ({})", a.label, a.detail)).collect();
+        if let Some(e) = &run.error { reasons.insert(0, e.clone()); }
+        xml.push_str(&format!("      <failure message=\"{}\">{}</failure>\n", xml_escape(&reasons[0]), xml_escape(&reasons.join("\n"))));
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+pub fn json_report(runs: &[RequestRun]) -> Result<String> {
+    let failed = runs.iter().filter(|r| !r.passed()).count();
+    let report = serde_json::json!({ "total": runs.len(), "failed": failed, "passed": runs.len() - failed, "results": runs });
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> { s.split_whitespace().map(String::from).collect() }
+
+    fn run(name: &str, passed: bool) -> RequestRun {
+        RequestRun {
+            name: name.to_string(), group: Some("Auth".to_string()), method: "GET".to_string(), url: "https://api.com".to_string(),
+            status: Some(200), elapsed_ms: 1500, logs: Vec::new(), error: None, response: None,
+            assertions: vec![AssertionResult { label: "status == 200".to_string(), passed, detail: "got 200".to_string() }],
+        }
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let opts = parse_args(&args("api.json -e Dev --group Auth --junit out.xml -w ../team")).unwrap();
+        assert_eq!(opts.source, Some(PathBuf::from("api.json")));
+        assert_eq!(opts.workspace, Some(PathBuf::from("../team")));
+        assert_eq!(opts.env.as_deref(), Some("Dev"));
+        assert_eq!(opts.group.as_deref(), Some("Auth"));
+        assert_eq!(opts.junit, Some(PathBuf::from("out.xml")));
+        assert!(!opts.allow_skipped && parse_args(&args("--allow-skipped")).unwrap().allow_skipped);
+        assert!(parse_args(&args("--env")).is_err());
+        assert!(parse_args(&args("--bogus")).is_err());
+    }
+
+    #[test]
+    fn test_unreadable_collection_files_stop_the_run() {
+        let tmp = crate::test_util::TempDir::new("runner_skipped");
+        fs::write(tmp.join("ok.json"), r#"{"name": "Ok", "method": "GET", "url": "https://api.com"}"#).unwrap();
+        fs::write(tmp.join("broken.json"), "{ not json").unwrap();
+        let err = load_source(tmp.path(), false).unwrap_err().to_string();
+        assert!(err.contains("broken.json") && err.contains("--allow-skipped"), "{}", err);
+        assert_eq!(load_source(tmp.path(), true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_junit_report_marks_failures() {
+        let xml = junit_report(&[run("Login <ok>", true), run("Me", false)]);
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("name=\"Login &lt;ok&gt;\" classname=\"Auth\" time=\"1.500\"/>"));
+        assert!(xml.contains("<failure message=\"status == 200 (got 200)\">"));
+    }
+
+    #[test]
+    fn test_collection_run_progress() {
+        let reqs = vec![ApiRequest { name: "a".to_string(), ..Default::default() }, ApiRequest { name: "b".to_string(), ..Default::default() }];
+        let mut cr = CollectionRun::new(1, "Auth".to_string(), reqs, 2);
+        cr.record(1, run("b", false));
+        assert_eq!((cr.completed(), cr.failed()), (1, 1));
+        assert!(cr.finished.is_none());
+        cr.record(0, run("a", true));
+        assert!(cr.finished.is_some());
+        cr.move_selection(-1);
+        assert_eq!(cr.selected_row().unwrap().request.name, "b");
+    }
+
+    #[test]
+    fn test_filter_group() {
+        let reqs = vec![
+            ApiRequest { name: "a".to_string(), group: Some("Auth".to_string()), ..Default::default() },
+            ApiRequest { name: "b".to_string(), ..Default::default() },
+            ApiRequest { name: "c".to_string(), group: Some("Auth/Admin".to_string()), ..Default::default() },
+            ApiRequest { name: "d".to_string(), group: Some("Authors".to_string()), ..Default::default() },
+        ];
+        assert_eq!(filter_group(reqs.clone(), Some("UNGROUPED"))[0].name, "b");
+        let names: Vec<String> = filter_group(reqs.clone(), Some("Auth")).into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["a", "c"]);
+        assert_eq!(filter_group(reqs.clone(), None).len(), 4);
+
+        // La auth se hereda de la carpeta más cercana que la tenga
+        let folder_auth = BTreeMap::from([("Auth".to_string(), crate::auth::Auth::Bearer { token: "t".to_string() })]);
+        let resolved = resolve_folder_auth(reqs, &folder_auth);
+        assert!(matches!(resolved[2].auth, crate::auth::Auth::Bearer { .. }));
+        assert!(matches!(resolved[3].auth, crate::auth::Auth::None));
+    }
+}
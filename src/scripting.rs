@@ -0,0 +1,152 @@
+use rhai::{Dynamic, Engine, Map, Scope};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::http::PreparedRequest;
+use crate::response::ApiResponse;
+
+/// Límite de operaciones por script para que un bucle infinito no congele la TUI
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Resultado de un script: variables nuevas o modificadas en `env` y salida de `print`/`debug`.
+#[derive(Debug, Default)]
+pub struct ScriptOutput {
+    pub vars: BTreeMap<String, String>,
+    pub logs: Vec<String>,
+}
+
+/// Ejecuta el script pre-request. Puede modificar `request.url`, `request.method`,
+/// `request.headers` y `request.body`, y escribir variables en `env`. En `request.headers`
+/// cada nombre tiene un solo valor: sólo se reescriben los headers que el script cambió,
+/// el resto conserva su orden y sus duplicados.
+pub fn run_pre_request(script: &str, req: &mut PreparedRequest, vars: &BTreeMap<String, String>) -> Result<ScriptOutput, String> {
+    let mut request = Map::new();
+    request.insert("method".into(), req.method.clone().into());
+    request.insert("url".into(), req.url.clone().into());
+    request.insert("body".into(), req.body.clone().into());
+    let before: BTreeMap<String, String> = req.headers.iter().cloned().collect();
+    request.insert("headers".into(), before.iter().map(|(k, v)| (k.as_str().into(), Dynamic::from(v.clone()))).collect::<Map>().into());
+
+    let mut scope = Scope::new();
+    scope.push("request", request);
+    let output = run(script, &mut scope, vars)?;
+
+    if let Some(request) = scope.get_value::<Map>("request") {
+        let field = |name: &str| request.get(name).map(dynamic_to_string).unwrap_or_default();
+        req.method = field("method").to_uppercase();
+        req.url = field("url");
+        req.body = field("body");
+        if let Some(headers) = request.get("headers").and_then(|h| h.read_lock::<Map>()) {
+            let after: BTreeMap<String, String> = headers.iter().map(|(k, v)| (k.to_string(), dynamic_to_string(v))).collect();
+            req.headers.retain(|(k, _)| after.contains_key(k));
+            for (k, v) in after.iter().filter(|(k, v)| before.get(*k) != Some(*v)) { crate::auth::set_header(req, k, v.clone()); }
+        }
+    }
+    Ok(output)
+}
+
+/// Ejecuta el script post-response. Expone `response` (status, headers, body, json, time)
+/// en modo lectura; lo útil es capturar valores en `env`, p.ej. `env.token = response.json.token;`
+pub fn run_post_response(script: &str, resp: &ApiResponse, vars: &BTreeMap<String, String>) -> Result<ScriptOutput, String> {
+    let mut response = Map::new();
+    response.insert("status".into(), (resp.status as i64).into());
+    response.insert("body".into(), resp.body_text().into());
+    response.insert("time".into(), (resp.elapsed.as_millis() as i64).into());
+    // Headers en minúsculas para que el acceso no dependa del servidor
+    response.insert("headers".into(), resp.headers.iter().map(|(k, v)| (k.to_lowercase().into(), Dynamic::from(v.clone()))).collect::<Map>().into());
+    let json = serde_json::from_slice::<serde_json::Value>(&resp.body).ok().and_then(|v| rhai::serde::to_dynamic(v).ok()).unwrap_or(Dynamic::UNIT);
+    response.insert("json".into(), json);
+
+    let mut scope = Scope::new();
+    scope.push_constant("response", response);
+    run(script, &mut scope, vars)
+}
+
+fn run(script: &str, scope: &mut Scope, vars: &BTreeMap<String, String>) -> Result<ScriptOutput, String> {
+    let logs = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let l = logs.clone();
+    engine.on_print(move |s| l.borrow_mut().push(s.to_string()));
+    let l = logs.clone();
+    engine.on_debug(move |s, _, _| l.borrow_mut().push(s.to_string()));
+
+    let env: Map = vars.iter().map(|(k, v)| (k.as_str().into(), Dynamic::from(v.clone()))).collect();
+    scope.push("env", env);
+    engine.run_with_scope(scope, script).map_err(|e| e.to_string())?;
+
+    let mut changed = BTreeMap::new();
+    if let Some(env) = scope.get_value::<Map>("env") {
+        for (k, v) in env {
+            let value = dynamic_to_string(&v);
+            if vars.get(k.as_str()) != Some(&value) { changed.insert(k.to_string(), value); }
+        }
+    }
+    let logs = logs.borrow().clone();
+    Ok(ScriptOutput { vars: changed, logs })
+}
+
+fn dynamic_to_string(value: &Dynamic) -> String {
+    if value.is_unit() { String::new() } else { value.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pre_request_modifies_request_and_env() {
+        let mut req = PreparedRequest { method: "get".to_string(), url: "https://api.com".to_string(), ..Default::default() };
+        let mut vars = BTreeMap::new();
+        vars.insert("token".to_string(), "abc".to_string());
+        let script = r#"
+            request.headers["Authorization"] = "Bearer " + env.token;
+            request.method = "post";
+            env.request_id = 42;
+            print("ready");
+        "#;
+        let out = run_pre_request(script, &mut req, &vars).unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.headers, vec![("Authorization".to_string(), "Bearer abc".to_string())]);
+        assert_eq!(out.vars.get("request_id").map(|s| s.as_str()), Some("42"));
+        assert!(!out.vars.contains_key("token"));
+        assert_eq!(out.logs, vec!["ready".to_string()]);
+    }
+
+    #[test]
+    fn test_pre_request_keeps_untouched_duplicate_headers() {
+        let headers = vec![("X-B".to_string(), "1".to_string()), ("Accept".to_string(), "text/plain".to_string()), ("Accept".to_string(), "application/json".to_string())];
+        let mut req = PreparedRequest { method: "GET".to_string(), headers: headers.clone(), ..Default::default() };
+        run_pre_request("env.x = 1;", &mut req, &BTreeMap::new()).unwrap();
+        assert_eq!(req.headers, headers);
+        // Cambiar otro header no toca los `Accept`
+        run_pre_request(r#"request.headers["X-B"] = "2"; request.headers["X-C"] = "3";"#, &mut req, &BTreeMap::new()).unwrap();
+        let names: Vec<String> = req.headers.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+        assert_eq!(names, ["Accept: text/plain", "Accept: application/json", "X-B: 2", "X-C: 3"]);
+        run_pre_request(r#"request.headers.remove("Accept");"#, &mut req, &BTreeMap::new()).unwrap();
+        assert_eq!(req.headers.len(), 2);
+    }
+
+    #[test]
+    fn test_post_response_captures_token() {
+        let resp = ApiResponse {
+            status: 200,
+            headers: vec![("X-Trace".to_string(), "t-1".to_string())],
+            body: br#"{"data":{"token":"xyz"}}"#.to_vec(),
+            elapsed: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let script = r#"if response.status == 200 { env.token = response.json.data.token; env.trace = response.headers["x-trace"]; }"#;
+        let out = run_post_response(script, &resp, &BTreeMap::new()).unwrap();
+        assert_eq!(out.vars.get("token").map(|s| s.as_str()), Some("xyz"));
+        assert_eq!(out.vars.get("trace").map(|s| s.as_str()), Some("t-1"));
+    }
+
+    #[test]
+    fn test_runaway_script_is_aborted() {
+        let mut req = PreparedRequest::default();
+        assert!(run_pre_request("loop {}", &mut req, &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+/// Un evento de `text/event-stream` ya despachado
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    /// `event:`; sin él es `message`
+    pub event: Option<String>,
+    /// Último `id:` visto en el stream (se hereda entre eventos, como en el navegador)
+    pub id: Option<String>,
+    /// Líneas `data:` unidas con `\n`
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+impl SseEvent {
+    pub fn name(&self) -> &str { self.event.as_deref().unwrap_or("message") }
+}
+
+/// Parser incremental: recibe los trozos tal como llegan (pueden cortar líneas o caracteres)
+/// y devuelve los eventos completos. Lo que queda sin línea en blanco al final no se despacha.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+    event: Option<String>,
+    last_id: Option<String>,
+    data: Vec<String>,
+    retry: Option<u64>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(pos) = self.buf[start..].iter().position(|&b| b == b'\n' || b == b'\r').map(|p| start + p) {
+            // Un `\r` al final puede ser la mitad de un `\r\n`: se espera al próximo trozo
+            if self.buf[pos] == b'\r' && pos + 1 == self.buf.len() { break; }
+            let line = String::from_utf8_lossy(&self.buf[start..pos]).to_string();
+            start = pos + if self.buf[pos] == b'\r' && self.buf.get(pos + 1) == Some(&b'\n') { 2 } else { 1 };
+            if let Some(event) = self.line(&line) { events.push(event); }
+        }
+        self.buf.drain(..start);
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = (!self.data.is_empty()).then(|| SseEvent { event: self.event.clone(), id: self.last_id.clone(), data: self.data.join("\n"), retry: self.retry });
+            (self.event, self.data, self.retry) = (None, Vec::new(), None);
+            return event;
+        }
+        // `:` al principio es un comentario (keep-alive)
+        if line.starts_with(':') { return None; }
+        let (field, value) = line.split_once(':').map(|(f, v)| (f, v.strip_prefix(' ').unwrap_or(v))).unwrap_or((line, ""));
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => self.retry = value.parse().ok(),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Todos los eventos de un body completo
+pub fn parse(body: &[u8]) -> Vec<SseEvent> { SseParser::default().push(body) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let stream = b": keep-alive\r\nevent: delta\r\nid: 1\r\ndata: {\"text\":\"Hol\r\n\r\ndata: a\ndata: mundo\n\nretry: 3000\nevent: done\ndata: \n\n";
+        let mut parser = SseParser::default();
+        // Cortes en cualquier byte, incluso entre `\r` y `\n`
+        let events: Vec<SseEvent> = stream.chunks(3).flat_map(|c| parser.push(c)).collect();
+        assert_eq!(events, parse(stream));
+        assert_eq!(events.len(), 3);
+        assert_eq!((events[0].name(), events[0].id.as_deref(), events[0].data.as_str()), ("delta", Some("1"), "{\"text\":\"Hol"));
+        assert_eq!((events[1].name(), events[1].id.as_deref(), events[1].data.as_str()), ("message", Some("1"), "a\nmundo"));
+        assert_eq!((events[2].name(), events[2].retry, events[2].data.as_str()), ("done", Some(3000), ""));
+    }
+
+    #[test]
+    fn test_incomplete_event_is_not_dispatched() {
+        assert!(parse(b"data: partial\n").is_empty());
+        assert!(parse(b"event: ping\n\n").is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Carpeta temporal de un test: nombre único por proceso y llamada (los tests corren en
+/// paralelo) y borrada al soltarse, también cuando falla una aserción
+pub struct TempDir(PathBuf);
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+impl TempDir {
+    pub fn new(label: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("arthema_{}_{}_{}", label, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path { &self.0 }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf { self.0.join(path) }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) { let _ = std::fs::remove_dir_all(&self.0); }
+}
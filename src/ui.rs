@@ -2,11 +2,13 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect, Alignment},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph, Wrap, List, ListItem, Tabs, Clear},
+    widgets::{block::Title, Block, Borders, Paragraph, Wrap, List, ListItem, Tabs, Clear, Table, Row, Cell},
     Frame,
 };
 
-use crate::app::{ActivePanel, App, EditorFocus, BodyType};
+use crate::app::{ActivePanel, App, EditorFocus};
+use crate::body::BodyType;
+use crate::response::{ApiResponse, ResponseView};
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
@@ -47,28 +49,52 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
                 match item {
                     crate::app::CollectionItem::Folder(name) => {
-                        let icon = if app.expanded_groups.contains(&name) { "▼ 📂" } else { "▶ 📁" };
-                        ListItem::new(format!("{} {}", icon, name)).style(style)
+                        let icon = if app.collections.tree.expanded.contains(&name) { "▼ 📂" } else { "▶ 📁" };
+                        let indent = "  ".repeat(name.matches(crate::collections::FOLDER_SEP).count());
+                        ListItem::new(format!("{}{} {}", indent, icon, crate::collections::folder_leaf(&name))).style(style)
                     }
                     crate::app::CollectionItem::Request(real_idx) => {
                         let r = &app.collections.requests[real_idx];
-                        ListItem::new(format!("  └─ {}", r.name)).style(style)
+                        let indent = "  ".repeat(r.group.as_deref().map_or(0, |g| g.matches(crate::collections::FOLDER_SEP).count()));
+                        ListItem::new(format!("{}  └─ {}", indent, r.name)).style(style)
                     }
                 }
             }).collect()
         },
-        crate::app::LeftPanelTab::History => app.collections.history.iter().enumerate().map(|(i, r)| {
-            let style = if i == app.selected_idx && matches!(app.active_panel, ActivePanel::Collections) { Style::default().fg(Color::Black).bg(Color::Cyan) } else { Style::default().fg(Color::DarkGray) };
-            ListItem::new(format!(" [{}] {}", r.method, r.url)).style(style)
-        }).collect(),
+        crate::app::LeftPanelTab::History => {
+            let today = chrono::Local::now().date_naive();
+            app.visible_history().into_iter().enumerate().map(|(i, idx)| {
+                let e = &app.collections.history.entries[idx];
+                // Color por resultado: verde 2xx, amarillo 4xx, rojo 5xx o sin respuesta
+                let color = match (e.status, &e.error) {
+                    (Some(s), _) if s < 300 => Color::Green,
+                    (Some(s), _) if s < 400 => Color::Cyan,
+                    (Some(s), _) if s < 500 => Color::Yellow,
+                    (Some(_), _) | (None, Some(_)) => Color::Red,
+                    (None, None) => Color::DarkGray,
+                };
+                let style = if i == app.selected_idx && matches!(app.active_panel, ActivePanel::Collections) { Style::default().fg(Color::Black).bg(Color::Cyan) } else { Style::default().fg(color) };
+                let when = e.time().map(|t| if t.date_naive() == today { t.format("%H:%M").to_string() } else { t.format("%m-%d %H:%M").to_string() }).unwrap_or_default();
+                let status = e.status.map(|s| s.to_string()).unwrap_or_else(|| if e.error.is_some() { "ERR".to_string() } else { "---".to_string() });
+                ListItem::new(format!(" {} [{}] {} {}ms {}", when, e.request.method, status, e.elapsed_ms, e.request.url)).style(style)
+            }).collect()
+        },
     };
-    f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).border_style(get_border_style(app.active_panel, ActivePanel::Collections))), left_chunks[1]);
+    let workspace = app.collections.manifest.as_ref().map(|m| m.name.clone()).unwrap_or_else(|| app.collections.base_path.clone());
+    let title = match app.left_panel_tab {
+        crate::app::LeftPanelTab::History if !app.history_filter.is_empty() => format!(" 🗂 {} (W) · / {} ", workspace, app.history_filter.query),
+        crate::app::LeftPanelTab::History => format!(" 🗂 {} (W) · / filter ", workspace),
+        crate::app::LeftPanelTab::Collections => format!(" 🗂 {} (W) ", workspace),
+    };
+    f.render_widget(List::new(items).block(Block::default().title(title).borders(Borders::ALL).border_style(get_border_style(app.active_panel, ActivePanel::Collections))), left_chunks[1]);
 
     // 2. Editor Panel con Pestañas
     let editor_root = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(3), Constraint::Min(0)]).split(main_chunks[1]);
     let tab_titles: Vec<Line> = app.tabs.iter().enumerate().map(|(i, t)| {
-        if i == app.active_tab { Line::from(vec![Span::styled(format!(" {} ", t.name), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))]) }
-        else { Line::from(vec![Span::styled(format!(" {} ", t.name), Style::default().fg(Color::DarkGray))]) }
+        let name = if i == app.active_tab { Span::styled(format!(" {} ", t.name), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)) }
+        else { Span::styled(format!(" {} ", t.name), Style::default().fg(Color::DarkGray)) };
+        // ● = cambios sin guardar respecto a la colección
+        if t.is_dirty() { Line::from(vec![name, Span::styled("● ", Style::default().fg(Color::Magenta))]) } else { Line::from(vec![name]) }
     }).collect();
     f.render_widget(Tabs::new(tab_titles).block(Block::default().borders(Borders::ALL).title(" 📂 OPEN REQUESTS ").border_style(get_border_style(app.active_panel, ActivePanel::Editor))).select(app.active_tab).highlight_style(Style::default().fg(Color::Yellow)), editor_root[0]);
 
@@ -83,50 +109,199 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     app.url_rect = editor_area[0]; app.headers_rect = editor_area[2]; app.body_rect = editor_area[3]; app.attach_rect = editor_area[4];
 
     let (input_mode, active_panel) = (app.input_mode, app.active_panel);
+    // Con un modal abierto la imagen de protocolo lo taparía: medios bloques
+    let (graphics, active_tab) = (app.graphics, app.active_tab);
+    let image_protocol = if app.is_input_active() { crate::img_preview::Protocol::HalfBlocks } else { graphics.protocol };
+    let mut placement = None;
+    let inherited_auth = crate::collections::folder_auth_for(&app.collections.folder_auth, app.tabs[app.active_tab].group.as_deref())
+        .map(|(folder, a)| format!("{} from {}", a.to_text().lines().next().unwrap_or("").trim_start_matches("type: "), folder));
     let tab = &mut app.tabs[app.active_tab];
 
     // URL
-    tab.url_area.set_block(Block::default().title(format!(" ⚡ {} URL ", tab.method)).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Url)));
+    let url_title = if tab.insecure { format!(" ⚡ {} URL ⚠ TLS UNVERIFIED ", tab.method) } else { format!(" ⚡ {} URL ", tab.method) };
+    tab.url_area.set_block(Block::default().title(url_title).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Url)));
     configure_cursor(tab, input_mode, EditorFocus::Url);
     f.render_widget(tab.url_area.widget(), editor_area[0]);
 
     // Body Type Selector
-    let bt_titles = vec![" JSON ", " TEXT ", " FORM "];
-    let bt_idx = match tab.body_type { BodyType::Json => 0, BodyType::Text => 1, BodyType::Form => 2 };
+    let bt_titles: Vec<String> = BodyType::ALL.iter().map(|t| format!(" {} ", t.label())).collect();
+    let bt_idx = BodyType::ALL.iter().position(|t| *t == tab.body_type).unwrap_or(0);
     let bt_tabs = Tabs::new(bt_titles)
-        .block(Block::default().title(" ⚙️ BODY TYPE ").borders(Borders::ALL).border_style(Style::default().fg(Color::DarkGray)))
+        .block(Block::default().title(" ⚙️ BODY TYPE (b/B) ").borders(Borders::ALL).border_style(Style::default().fg(Color::DarkGray)))
         .select(bt_idx)
         .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
     f.render_widget(bt_tabs, editor_area[1]);
 
-    // Headers
-    tab.headers_area.set_block(Block::default().title(" 📋 HEADERS ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Headers)));
-    configure_cursor(tab, input_mode, EditorFocus::Headers);
-    f.render_widget(tab.headers_area.widget(), editor_area[2]);
+    // Headers: tabla fuera de edición, texto `Nombre: valor` al editar
+    let headers_border = get_editor_border(active_panel, tab.editor_focus, EditorFocus::Headers);
+    if input_mode && tab.editor_focus == EditorFocus::Headers {
+        let suggestions = tab.header_suggestions();
+        let mut block = Block::default().title(" 📋 HEADERS (Name: value | Ctrl+O on/off) ").borders(Borders::ALL).border_style(headers_border);
+        if !suggestions.is_empty() {
+            let hint = suggestions.iter().take(4).copied().collect::<Vec<_>>().join(" | ");
+            block = block.title(Title::from(Span::styled(format!(" Tab ⇥ {} ", hint), Style::default().fg(Color::Yellow))).position(ratatui::widgets::block::Position::Bottom));
+        }
+        tab.headers_area.set_block(block);
+        configure_cursor(tab, input_mode, EditorFocus::Headers);
+        f.render_widget(tab.headers_area.widget(), editor_area[2]);
+    } else {
+        let headers = tab.headers();
+        let active = headers.iter().filter(|h| h.enabled).count();
+        let rows: Vec<Row> = headers.iter().map(|h| {
+            let style = if h.enabled { Style::default() } else { Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT) };
+            Row::new(vec![Cell::from(if h.enabled { "✓" } else { "✗" }), Cell::from(h.name.clone()).style(Style::default().fg(if h.enabled { Color::Cyan } else { Color::DarkGray })), Cell::from(h.value.clone())]).style(style)
+        }).collect();
+        let table = Table::new(rows, [Constraint::Length(2), Constraint::Percentage(35), Constraint::Percentage(65)])
+            .header(Row::new(vec!["", "NAME", "VALUE"]).style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD)))
+            .block(Block::default().title(format!(" 📋 HEADERS ({}/{}) ", active, headers.len())).borders(Borders::ALL).border_style(headers_border));
+        f.render_widget(table, editor_area[2]);
+    }
 
-    // Body
-    tab.body_area.set_block(Block::default().title(" 📦 BODY ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Body)));
-    configure_cursor(tab, input_mode, EditorFocus::Body);
-    f.render_widget(tab.body_area.widget(), editor_area[3]);
+    // Body (o el script enfocado, que comparte el mismo hueco)
+    match tab.editor_focus {
+        EditorFocus::PreScript => {
+            tab.pre_script_area.set_block(Block::default().title(" 📜 PRE-REQUEST SCRIPT (Rhai) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::PreScript)));
+            configure_cursor(tab, input_mode, EditorFocus::PreScript);
+            f.render_widget(tab.pre_script_area.widget(), editor_area[3]);
+        }
+        EditorFocus::PostScript => {
+            tab.post_script_area.set_block(Block::default().title(" 📜 POST-RESPONSE SCRIPT (Rhai) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::PostScript)));
+            configure_cursor(tab, input_mode, EditorFocus::PostScript);
+            f.render_widget(tab.post_script_area.widget(), editor_area[3]);
+        }
+        EditorFocus::Params => {
+            tab.params_area.set_block(Block::default().title(" 🔎 PARAMS (key=value | # key=value off | :id=value path) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Params)));
+            configure_cursor(tab, input_mode, EditorFocus::Params);
+            f.render_widget(tab.params_area.widget(), editor_area[3]);
+        }
+        EditorFocus::Auth => {
+            let title = match &inherited_auth {
+                Some(hint) => format!(" 🔐 AUTH (empty = inherit: {}) ", hint),
+                None => " 🔐 AUTH (type: none | basic | bearer | apikey | digest | oauth2) ".to_string(),
+            };
+            tab.auth_area.set_block(Block::default().title(title).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Auth)));
+            configure_cursor(tab, input_mode, EditorFocus::Auth);
+            f.render_widget(tab.auth_area.widget(), editor_area[3]);
+        }
+        EditorFocus::Tests => {
+            tab.tests_area.set_block(Block::default().title(" ✅ ASSERTIONS (status == 200 | $.path == value | header Name | time < ms) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Tests)));
+            configure_cursor(tab, input_mode, EditorFocus::Tests);
+            f.render_widget(tab.tests_area.widget(), editor_area[3]);
+        }
+        EditorFocus::Description => {
+            tab.description_area.set_block(Block::default().title(" 📝 DESCRIPTION (Markdown, saved with the request) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Description)));
+            configure_cursor(tab, input_mode, EditorFocus::Description);
+            f.render_widget(tab.description_area.widget(), editor_area[3]);
+        }
+        _ if tab.body_type == BodyType::GraphQl => {
+            let panes = Layout::default().direction(Direction::Vertical).constraints([Constraint::Percentage(60), Constraint::Percentage(40)]).split(editor_area[3]);
+            let schema = if tab.graphql_schema.is_some() { "schema ✓ Tab completes" } else { "G loads schema" };
+            let title = format!(" ◈ GRAPHQL QUERY · op: {} (O) · {} ", tab.graphql_operation.as_deref().unwrap_or("-"), schema);
+            let mut block = Block::default().title(title).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Body));
+            let suggestions = if input_mode && tab.editor_focus == EditorFocus::Body { tab.graphql_suggestions() } else { Vec::new() };
+            if !suggestions.is_empty() {
+                let hint = suggestions.iter().take(5).copied().collect::<Vec<_>>().join(" | ");
+                block = block.title(Title::from(Span::styled(format!(" Tab ⇥ {} ", hint), Style::default().fg(Color::Yellow))).position(ratatui::widgets::block::Position::Bottom));
+            }
+            tab.body_area.set_block(block);
+            configure_cursor(tab, input_mode, EditorFocus::Body);
+            f.render_widget(tab.body_area.widget(), panes[0]);
+            tab.graphql_vars_area.set_block(Block::default().title(" ◈ VARIABLES (JSON · F format) ").borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Variables)));
+            configure_cursor(tab, input_mode, EditorFocus::Variables);
+            f.render_widget(tab.graphql_vars_area.widget(), panes[1]);
+        }
+        _ if matches!(tab.body_type, BodyType::None | BodyType::Binary) => {
+            let text = match (tab.body_type, tab.file_path.is_empty()) {
+                (BodyType::None, _) => "This request is sent without a body. Press b/B to choose a body type.".to_string(),
+                (_, true) => "No file selected. Focus ATTACHMENT (f) and press ENTER to choose the file sent as the body.".to_string(),
+                (_, false) => format!("The file is streamed as the raw body:\n\n  {}\n\nContent-Type comes from Headers (or the file extension if missing).", tab.file_path),
+            };
+            let block = Block::default().title(format!(" 📦 BODY · {} ", tab.body_type.label())).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Body));
+            f.render_widget(Paragraph::new(text).style(Style::default().fg(Color::DarkGray)).block(block).wrap(Wrap { trim: false }), editor_area[3]);
+        }
+        _ => {
+            let body_title = match tab.body_type {
+                BodyType::Form => " 📦 BODY · MULTIPART (name=value | name=@path;type=mime;filename=x | # off) ",
+                BodyType::UrlEncoded => " 📦 BODY · URLENCODED (name=value | # name=value off) ",
+                BodyType::Json => " 📦 BODY · JSON (F format) ",
+                BodyType::Xml => " 📦 BODY · XML (F format) ",
+                BodyType::Raw => " 📦 BODY · RAW (Content-Type from Headers) ",
+                _ => " 📦 BODY ",
+            };
+            tab.body_area.set_block(Block::default().title(body_title).borders(Borders::ALL).border_style(get_editor_border(active_panel, tab.editor_focus, EditorFocus::Body)));
+            configure_cursor(tab, input_mode, EditorFocus::Body);
+            f.render_widget(tab.body_area.widget(), editor_area[3]);
+        }
+    }
 
     // Attachment
     let att_style = if tab.editor_focus == EditorFocus::Attachment { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::Cyan) };
-    let att_content = if tab.file_path.is_empty() { "Press ENTER to browse...".to_string() } else { format!("📎 {}", tab.file_path) };
-    f.render_widget(Paragraph::new(att_content).style(att_style).block(Block::default().title(" 🖇 ATTACHMENT ").borders(Borders::ALL).border_style(att_style)), editor_area[4]);
+    let att_content = if !tab.file_path.is_empty() { format!("📎 {}", tab.file_path) } else if tab.body_type == BodyType::Form { "Press ENTER to add a file part to the form...".to_string() } else { "Press ENTER to browse...".to_string() };
+    let att_title = if tab.body_type == BodyType::Binary { " 🖇 BINARY BODY FILE " } else { " 🖇 ATTACHMENT " };
+    f.render_widget(Paragraph::new(att_content).style(att_style).block(Block::default().title(att_title).borders(Borders::ALL).border_style(att_style)), editor_area[4]);
 
     // 3. Response & AI
-    let right_chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Percentage(60), Constraint::Percentage(40)]).split(main_chunks[2]);
+    let right_chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Percentage(55), Constraint::Percentage(15), Constraint::Percentage(30)]).split(main_chunks[2]);
     app.response_rect = right_chunks[0];
     
-    let response_content = if let Some(bytes) = &tab.response_bytes {
-        let preview = crate::img_preview::generate_hifi_preview(bytes, right_chunks[0].width.saturating_sub(4) as u32);
-        Text::raw(preview)
-    } else {
-        highlight_json(&tab.response)
-    };
-
-    f.render_widget(Paragraph::new(response_content).block(Block::default().title(" 📡 RESPONSE ").borders(Borders::ALL).border_style(get_border_style(active_panel, ActivePanel::Response))).scroll((tab.response_scroll, 0)).wrap(Wrap { trim: false }), right_chunks[0]);
-    f.render_widget(Paragraph::new(app.ai_response.as_str()).style(Style::default().fg(Color::Magenta)).block(Block::default().title(" 🧠 AI AGENT ").borders(Borders::ALL).border_style(get_border_style(active_panel, ActivePanel::AI))).wrap(Wrap { trim: true }), right_chunks[1]);
+    let view_spans: Vec<Span> = [(ResponseView::Body, "1 BODY"), (ResponseView::Headers, "2 HEADERS"), (ResponseView::Timing, "3 TIMING")].into_iter().map(|(v, label)| {
+        if tab.response_view == v { Span::styled(format!(" {} ", label), Style::default().fg(Color::Black).bg(Color::Cyan)) } else { Span::styled(format!(" {} ", label), Style::default().fg(Color::DarkGray)) }
+    }).collect();
+    let mut response_block = Block::default().title(" 📡 RESPONSE ").title(Line::from(view_spans)).borders(Borders::ALL).border_style(get_border_style(active_panel, ActivePanel::Response));
+    if let Some(resp) = &tab.api_response {
+        let status_color = if resp.is_success() { Color::Green } else if resp.status >= 400 { Color::Red } else { Color::Yellow };
+        let summary = if resp.is_event_stream() { format!("{} · {} events{}", resp.summary(), tab.sse_events.len() + tab.sse_dropped, if tab.sse_dropped > 0 { format!(" (last {} kept)", tab.sse_events.len()) } else { String::new() }) } else if resp.is_binary() && !resp.is_image() { format!("{} · {} HEX (w save)", resp.summary(), resp.mime()) } else { resp.summary() };
+        response_block = response_block.title(Title::from(Span::styled(format!(" {} ", summary), Style::default().fg(status_color).add_modifier(Modifier::BOLD))).alignment(Alignment::Right));
+    }
+    if tab.in_flight.is_some() {
+        response_block = response_block.title(Title::from(Span::styled(if tab.api_response.is_some() { " ● STREAMING [^X] Stop " } else { " ● WAITING [^X] Stop " }, Style::default().fg(Color::Black).bg(Color::LightRed))).position(ratatui::widgets::block::Position::Bottom).alignment(Alignment::Right));
+    }
+    match (tab.response_view, &tab.api_response) {
+        (ResponseView::Headers, Some(resp)) => {
+            f.render_widget(Paragraph::new(response_headers_text(resp)).block(response_block).scroll((tab.response_scroll, 0)).wrap(Wrap { trim: false }), right_chunks[0]);
+        }
+        (ResponseView::Timing, Some(resp)) => {
+            f.render_widget(Paragraph::new(response_timing_text(resp)).block(response_block).wrap(Wrap { trim: false }), right_chunks[0]);
+        }
+        _ => {
+            if let (true, Some(tree)) = (tab.is_tree_mode, tab.json_tree.as_mut()) {
+                draw_json_tree(f, tree, response_block.title(" 🌳 [←/→] Fold [ [ ] ] Sibling [⌫] Parent "), right_chunks[0]);
+            } else {
+                let inner = response_block.inner(right_chunks[0]);
+                let mut scroll = tab.response_scroll;
+                let response_content = match &tab.api_response {
+                    Some(resp) if resp.is_event_stream() => {
+                        let text = sse_events_text(&tab.sse_events);
+                        // Mientras llega (y sin scroll manual) se sigue al último evento
+                        if tab.in_flight.is_some() && scroll == 0 { scroll = (text.lines.len() as u16).saturating_sub(inner.height); }
+                        text
+                    }
+                    // La vista previa se calcula fuera del dibujado (`App::start_image_previews`)
+                    Some(resp) if resp.is_image() && tab.in_flight.is_none() => match tab.image_preview.as_ref().filter(|p| p.fits(image_protocol, inner.width, inner.height)).map(|p| &p.content) {
+                        Some(crate::img_preview::Content::Blocks(text)) => text.clone(),
+                        Some(crate::img_preview::Content::Error(e)) => Text::raw(e.clone()),
+                        Some(crate::img_preview::Content::Sequence(_)) => {
+                            placement = Some(crate::img_preview::Placement { tab: active_tab, body: (resp.body.as_ptr() as usize, resp.body.len()), rect: inner });
+                            Text::default()
+                        }
+                        None => {
+                            tab.preview_wanted = Some((image_protocol, inner.width, inner.height));
+                            Text::styled("🖼️ Rendering preview...", Style::default().fg(Color::DarkGray))
+                        }
+                    },
+                    Some(resp) if resp.is_binary() => Text::styled(tab.response.clone(), Style::default().fg(Color::Gray)),
+                    Some(resp) if resp.is_xml() => highlight_xml(&tab.response),
+                    _ => highlight_json(&tab.response),
+                };
+                f.render_widget(Paragraph::new(response_content).block(response_block).scroll((scroll, 0)).wrap(Wrap { trim: false }), right_chunks[0]);
+                // La imagen de protocolo la escribe `main` después del frame: ratatui no debe pisar esas celdas
+                if placement.is_some() { for y in inner.top()..inner.bottom() { for x in inner.left()..inner.right() { f.buffer_mut().get_mut(x, y).set_skip(true); } } }
+            }
+        }
+    }
+    draw_test_results(f, &tab.test_results, right_chunks[1]);
+    app.image_placement = placement;
+    f.render_widget(Paragraph::new(app.ai_response.as_str()).style(Style::default().fg(Color::Magenta)).block(Block::default().title(" 🧠 AI AGENT ").borders(Borders::ALL).border_style(get_border_style(active_panel, ActivePanel::AI))).wrap(Wrap { trim: true }), right_chunks[2]);
 
     // Footer
     let footer_chunks = Layout::default()
@@ -134,8 +309,12 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         .constraints([Constraint::Min(0), Constraint::Length(75)])
         .split(chunks[1]);
 
-    let footer_text = " [?] Help | [^D] Donate (arthema.co) ";
-    f.render_widget(Paragraph::new(footer_text).style(Style::default().fg(Color::DarkGray)).block(Block::default().borders(Borders::TOP).border_style(Style::default().fg(Color::Magenta))), footer_chunks[0]);
+    let env_style = if app.environments.active.is_some() { Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::DarkGray) };
+    let footer_text = Line::from(vec![
+        Span::styled(format!(" 🌐 ENV: {} ", app.environments.active_name().unwrap_or("NONE")), env_style),
+        Span::styled(" [v] Switch | [?] Help | [^D] Donate (arthema.co) ", Style::default().fg(Color::DarkGray)),
+    ]);
+    f.render_widget(Paragraph::new(footer_text).block(Block::default().borders(Borders::TOP).border_style(Style::default().fg(Color::Magenta))), footer_chunks[0]);
 
     // Dashboard de Sistema - ARTHEMA alineado a la derecha
     let sys_metrics = Line::from(vec![
@@ -153,9 +332,36 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     if app.show_file_picker {
         let area = centered_rect(60, 60, f.size());
         f.render_widget(Clear, area);
+        let saving = matches!(app.current_import_type, crate::app::ImportType::SaveResponse);
+        // Guardando: debajo de la lista, el nombre del archivo
+        let (list_area, title) = if saving {
+            let chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Min(0), Constraint::Length(3)]).split(area);
+            app.save_file_name.set_block(Block::default().title(" 💾 FILE NAME (Ctrl+S save here, Enter on a file overwrites it) ").borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+            app.save_file_name.set_cursor_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+            f.render_widget(app.save_file_name.widget(), chunks[1]);
+            (chunks[0], format!(" 💾 SAVE RESPONSE BODY IN {} ", app.current_dir.display()))
+        } else { (area, " 📁 SELECT FILE ".to_string()) };
         let items: Vec<ListItem> = app.file_entries.iter().map(|fi| ListItem::new(fi.as_str()).style(Style::default().fg(Color::White))).collect();
-        let list = List::new(items).block(Block::default().title(" 📁 SELECT FILE ").borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow))).highlight_style(Style::default().fg(Color::Black).bg(Color::Yellow)).highlight_symbol(">> ");
-        f.render_stateful_widget(list, area, &mut app.file_picker_state);
+        let list = List::new(items).block(Block::default().title(title).borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow))).highlight_style(Style::default().fg(Color::Black).bg(Color::Yellow)).highlight_symbol(">> ");
+        f.render_stateful_widget(list, list_area, &mut app.file_picker_state);
+    }
+
+    // MODAL: Workspaces
+    if app.show_workspace_picker {
+        let area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, area);
+        let chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Min(0), Constraint::Length(3)]).split(area);
+        let items: Vec<ListItem> = app.workspace_entries().into_iter().enumerate().map(|(i, (name, path))| {
+            let marker = if app.is_current_workspace(&path) { "●" } else { " " };
+            let missing = if path.is_dir() { "" } else { "  (missing)" };
+            let style = if i == app.workspace_selected { Style::default().fg(Color::Black).bg(Color::Yellow) } else { Style::default().fg(Color::White) };
+            ListItem::new(format!("{} {}  {}{}", marker, name, path.display(), missing)).style(style)
+        }).collect();
+        f.render_widget(List::new(items).block(Block::default().title(" 🗂 WORKSPACES (ENTER open, a add/create, d forget, ESC close) ").borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow))), chunks[0]);
+        let input_style = if app.workspace_adding { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::DarkGray) };
+        app.workspace_input.set_block(Block::default().title(" ➕ WORKSPACE DIRECTORY (created if empty, ENTER open) ").borders(Borders::ALL).border_style(input_style));
+        app.workspace_input.set_cursor_style(if app.workspace_adding { Style::default().bg(Color::Yellow).fg(Color::Black) } else { Style::default() });
+        f.render_widget(app.workspace_input.widget(), chunks[1]);
     }
 
     // MODAL: API Key Input
@@ -185,6 +391,63 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         f.render_widget(app.rename_input.widget(), area);
     }
 
+    // MODAL: Save As
+    if app.show_save_as {
+        let area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, area);
+        let chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)]).split(area);
+        let focused = |on: bool| Style::default().fg(if on { Color::Yellow } else { Color::DarkGray });
+        app.save_as_name.set_block(Block::default().title(" 💾 SAVE AS: NAME (TAB switch field, ENTER save, ESC cancel) ").borders(Borders::ALL).border_style(focused(!app.save_as_focus_folder)));
+        app.save_as_name.set_cursor_style(if app.save_as_focus_folder { Style::default() } else { Style::default().bg(Color::Yellow).fg(Color::Black) });
+        f.render_widget(app.save_as_name.widget(), chunks[0]);
+        app.save_as_folder.set_block(Block::default().title(" 📁 FOLDER (↑/↓ pick existing, type a new one, a/b nests, empty = UNGROUPED) ").borders(Borders::ALL).border_style(focused(app.save_as_focus_folder)));
+        app.save_as_folder.set_cursor_style(if app.save_as_focus_folder { Style::default().bg(Color::Yellow).fg(Color::Black) } else { Style::default() });
+        f.render_widget(app.save_as_folder.widget(), chunks[1]);
+        let current = app.save_as_folder.lines()[0].trim().to_string();
+        let folders = app.collections.folders();
+        let is_new = !current.is_empty() && current != "UNGROUPED" && !folders.contains(&current);
+        let mut items: Vec<ListItem> = folders.iter().map(|g| {
+            if *g == current { ListItem::new(format!("▶ 📁 {}", g)).style(Style::default().fg(Color::Black).bg(Color::Yellow)) } else { ListItem::new(format!("  📁 {}", g)) }
+        }).collect();
+        if is_new { items.push(ListItem::new(format!("+ 📁 {} (new folder)", current)).style(Style::default().fg(Color::Green))); }
+        f.render_widget(List::new(items).block(Block::default().title(" EXISTING FOLDERS ").borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow))), chunks[2]);
+    }
+
+    // MODAL: Folder (crear / mover)
+    if let Some(prompt) = &app.folder_prompt {
+        let area = centered_rect(60, 20, f.size());
+        f.render_widget(Clear, area);
+        let title = match prompt {
+            crate::app::FolderPrompt::NewFolder(parent) => format!(" 📁 NEW FOLDER IN {} (a/b nests, ENTER create, ESC cancel) ", parent.as_deref().unwrap_or("/")),
+            crate::app::FolderPrompt::MoveRequest(id) => {
+                let name = app.collections.find_by_id(id).map(|i| app.collections.requests[i].name.as_str()).unwrap_or("REQUEST");
+                format!(" ➜ MOVE {} TO FOLDER (↑/↓ existing, empty = UNGROUPED) ", name)
+            }
+            crate::app::FolderPrompt::MoveFolder(path) => format!(" ➜ MOVE FOLDER {} INTO (↑/↓ existing, empty = top level) ", path),
+        };
+        app.folder_prompt_input.set_block(Block::default().title(title).borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+        app.folder_prompt_input.set_cursor_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+        f.render_widget(app.folder_prompt_input.widget(), area);
+    }
+
+    // MODAL: Filtro del historial
+    if app.show_history_filter {
+        let area = centered_rect(70, 20, f.size());
+        f.render_widget(Clear, area);
+        app.history_filter_input.set_block(Block::default().title(" 🔎 FILTER HISTORY (2xx 4xx 5xx error · GET · since:7d until:2024-05-31 · text; empty = all) ").borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+        app.history_filter_input.set_cursor_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+        f.render_widget(app.history_filter_input.widget(), area);
+    }
+
+    // MODAL: Folder Auth
+    if let Some(folder) = &app.folder_auth_target {
+        let area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, area);
+        app.folder_auth_input.set_block(Block::default().title(format!(" 🔐 AUTH FOR FOLDER {} (Ctrl+S to save, ESC to cancel) ", folder)).borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)));
+        app.folder_auth_input.set_cursor_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+        f.render_widget(app.folder_auth_input.widget(), area);
+    }
+
     // MODAL: Import Menu
     if app.show_import_menu {
         let area = centered_rect(40, 20, f.size());
@@ -199,6 +462,27 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         f.render_widget(Paragraph::new(content).block(Block::default().title(" 📥 MASS IMPORT ").borders(Borders::ALL).border_style(Style::default().fg(Color::Cyan))), area);
     }
 
+    // MODAL: Collection Runner
+    if app.show_runner && let Some(cr) = app.runner.as_ref() {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+        draw_runner(f, cr, app.runner_concurrency, area);
+    }
+
+    // MODAL: Code Export
+    if let Some(code) = app.code_snippet() {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+        let langs: Vec<Line> = crate::codegen::Language::ALL.iter().enumerate().map(|(i, l)| Line::from(format!(" {} {} ", i + 1, l.label()))).collect();
+        let selected = crate::codegen::Language::ALL.iter().position(|l| *l == app.code_language).unwrap_or(0);
+        let chunks = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(3), Constraint::Min(0)]).split(area);
+        f.render_widget(Tabs::new(langs).select(selected).style(Style::default().fg(Color::DarkGray)).highlight_style(Style::default().fg(Color::Black).bg(Color::Magenta))
+            .block(Block::default().title(if app.code_resolved { " </> EXPORT CODE · RESOLVED VALUES " } else { " </> EXPORT CODE · {{var}} " }).borders(Borders::ALL).border_style(Style::default().fg(Color::Magenta))), chunks[0]);
+        f.render_widget(Paragraph::new(code).style(Style::default().fg(Color::LightGreen))
+            .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Magenta))
+                .title(Title::from(Span::styled(" [←/→ 1-6] Language · [r] Resolve vars · [Enter] Copy · [Esc] Close ", Style::default().fg(Color::DarkGray))).position(ratatui::widgets::block::Position::Bottom))), chunks[1]);
+    }
+
     // MODAL: Neural Link Help Manual
     if app.show_help {
         let area = centered_rect(80, 80, f.size());
@@ -220,17 +504,30 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             ("📝 EDITING", vec![
                 ("i", "Insert Mode (Edit field)"),
                 ("Esc", "Navigation Mode / Close Modals"),
-                ("f", "Cycle Focus (URL -> Headers -> Body -> Attachment)"),
+                ("f", "Cycle Focus (URL -> Params -> Headers -> Body -> [GQL Variables] -> Auth -> Scripts -> Tests -> Description -> Attachment)"),
                 ("m / M", "Cycle HTTP Method (Forward / Backward)"),
-                ("b", "Cycle Body Type (JSON, TEXT, FORM)"),
+                ("b / B", "Cycle Body Type (JSON, TEXT, XML, GQL, FORM, URLENC, BIN file, RAW, NONE)"),
+                ("F", "FORMAT body (JSON / XML) or GraphQL variables"),
+                ("G / O", "GraphQL: load schema by introspection / cycle operation name"),
+                ("Ctrl+O", "Toggle header/param row ON/OFF (Insert Mode)"),
+                ("Tab", "Autocomplete header name/value or GraphQL field (Insert Mode)"),
                 ("Ctrl+Z", "Undo last text change"),
-                ("Ctrl+C/V", "Copy section / Paste from System"),
+                ("Ctrl+C/V", "Copy section / Paste (system clipboard, wl-copy, xclip or OSC 52 over SSH)"),
             ]),
             ("📁 COLLECTIONS", vec![
-                ("s", "SAVE current request to Collections"),
-                ("r", "RENAME selected request in Collections"),
-                ("d", "DELETE request, folder or attachment"),
+                ("s", "SAVE tab: overwrites its collection request, or asks name/folder if new (● = unsaved)"),
+                ("S", "SAVE AS: new name and existing or new folder"),
+                ("r", "RENAME selected request or folder in Collections"),
+                ("d", "DELETE request, attachment or tab (folders: press twice, deletes contents)"),
+                ("N", "NEW FOLDER inside the selected one (a/b creates nested folders)"),
+                ("X", "MOVE selected request or folder to another folder"),
+                ("D", "DUPLICATE selected request in its folder"),
                 ("h", "Toggle between COLLECTIONS and HISTORY"),
+                ("/", "FILTER HISTORY by status (2xx, 4xx, 5xx, error), method, dates (since:/until:) and text"),
+                ("W", "WORKSPACES: switch, add/create (a) or forget (d); each has its own collections, envs and history"),
+                ("v", "Cycle active ENVIRONMENT ({{var}} substitution)"),
+                ("A", "Edit AUTH of the selected folder (inherited by its requests)"),
+                ("R", "RUN selected folder (Enter opens result, r re-run, +/- parallel)"),
             ]),
             ("🧠 AI AGENT", vec![
                 ("a", "AI Suggest: Suggest an API based on URL"),
@@ -242,7 +539,12 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 ("Ctrl+I", "Open MASS IMPORT (Postman, Bruno, Swagger)"),
                 ("g", "Direct SWAGGER/OpenAPI import"),
                 ("Ctrl+D", "Support Arthema: Donate (arthema.co)"),
-                ("o", "OPEN response or attachment in System Viewer"),
+                ("Ctrl+X", "STOP the request in flight (keeps what arrived; SSE events stay listed)"),
+                ("w", "WRITE response body to a file (file picker: type the name, Ctrl+S saves)"),
+                ("o", "OPEN response (file type from Content-Type) or the attachment with the system app (open, xdg-open, start)"),
+                ("y", "EXPORT request as code (curl, reqwest, fetch, axios, Python, HTTPie)"),
+                ("1/2/3", "Response view: BODY / HEADERS / TIMING"),
+                ("t", "Toggle JSON TREE view (←/→ fold, [ ] sibling, ⌫ parent)"),
                 ("k", "Configure Gemini API Key"),
                 ("?", "Toggle this Help Manual"),
                 ("q", "Exit Arthema (Only in Navigation Mode)"),
@@ -294,8 +596,151 @@ fn highlight_json(text: &str) -> Text<'_> {
     Text::from(lines)
 }
 
+fn highlight_xml(text: &str) -> Text<'_> {
+    let lines: Vec<Line> = text.lines().map(|line| {
+        let Some(tokens) = crate::xml::tokenize(line) else { return Line::styled(line, Style::default().fg(Color::Gray)) };
+        Line::from(tokens.into_iter().map(|t| match t {
+            crate::xml::Token::Open(s) | crate::xml::Token::Close(s) => Span::styled(s, Style::default().fg(Color::LightBlue)),
+            crate::xml::Token::Single(s) if s.starts_with("<!--") => Span::styled(s, Style::default().fg(Color::DarkGray)),
+            crate::xml::Token::Single(s) => Span::styled(s, Style::default().fg(Color::Magenta)),
+            crate::xml::Token::Text(s) => Span::styled(s, Style::default().fg(Color::LightYellow)),
+        }).collect::<Vec<_>>())
+    }).collect();
+    Text::from(lines)
+}
+
+fn draw_test_results(f: &mut Frame, results: &[crate::assertions::AssertionResult], area: Rect) {
+    let passed = results.iter().filter(|r| r.passed).count();
+    let (title, border) = if results.is_empty() { (" ✅ TESTS ".to_string(), Style::default().fg(Color::Rgb(60, 60, 60))) }
+        else if passed == results.len() { (format!(" ✅ TESTS {}/{} ", passed, results.len()), Style::default().fg(Color::Green)) }
+        else { (format!(" ❌ TESTS {}/{} ", passed, results.len()), Style::default().fg(Color::Red)) };
+    let lines: Vec<Line> = if results.is_empty() {
+        vec![Line::from(Span::styled("No assertions run. Press f to edit them.", Style::default().fg(Color::DarkGray)))]
+    } else {
+        results.iter().map(|r| {
+            let (icon, color) = if r.passed { ("✔", Color::Green) } else { ("✘", Color::Red) };
+            Line::from(vec![
+                Span::styled(format!("{} {} ", icon, r.label), Style::default().fg(color)),
+                Span::styled(format!("({})", r.detail), Style::default().fg(Color::DarkGray)),
+            ])
+        }).collect()
+    };
+    f.render_widget(Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL).border_style(border)).wrap(Wrap { trim: true }), area);
+}
+
+fn draw_runner(f: &mut Frame, cr: &crate::runner::CollectionRun, next_concurrency: usize, area: Rect) {
+    let mode = if cr.concurrency <= 1 { "SEQUENTIAL".to_string() } else { format!("PARALLEL x{}", cr.concurrency) };
+    let elapsed = cr.finished.unwrap_or_else(|| cr.started.elapsed()).as_millis();
+    let (state, color) = match (cr.finished, cr.failed()) {
+        (None, _) => ("RUNNING", Color::Yellow),
+        (Some(_), 0) => ("PASSED", Color::Green),
+        (Some(_), _) => ("FAILED", Color::Red),
+    };
+    let title = format!(" 🏃 RUNNER: {} · {} · {}/{} · {} failed · {} ms ", cr.folder, mode, cr.completed(), cr.rows.len(), cr.failed(), elapsed);
+    let items: Vec<ListItem> = cr.rows.iter().map(|row| {
+        let line = match &row.result {
+            None => Line::from(vec![
+                Span::styled("  … ", Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{: <7} {}", row.request.method, row.request.name), Style::default().fg(Color::DarkGray)),
+            ]),
+            Some(run) => {
+                let (icon, c) = if run.passed() { ("  ✔ ", Color::Green) } else { ("  ✘ ", Color::Red) };
+                let status = run.status.map(|s| s.to_string()).unwrap_or_else(|| "---".to_string());
+                let passed = run.assertions.iter().filter(|a| a.passed).count();
+                let detail = match &run.error { Some(e) => e.clone(), None => format!("tests {}/{}", passed, run.assertions.len()) };
+                Line::from(vec![
+                    Span::styled(icon, Style::default().fg(c)),
+                    Span::styled(format!("{: <7} ", run.method), Style::default().fg(Color::Magenta)),
+                    Span::styled(format!("{: <28} ", row.request.name), Style::default().fg(Color::White)),
+                    Span::styled(format!("{: <4} ", status), Style::default().fg(c)),
+                    Span::styled(format!("{: >6} ms  ", run.elapsed_ms), Style::default().fg(Color::Cyan)),
+                    Span::styled(detail, Style::default().fg(Color::DarkGray)),
+                ])
+            }
+        };
+        ListItem::new(line)
+    }).collect();
+    let footer = format!(" [Enter] Open · [r] Re-run · [+/-] Parallel: {} · [Esc] Close ", next_concurrency);
+    let block = Block::default().title(Span::styled(title, Style::default().fg(color).add_modifier(Modifier::BOLD)))
+        .title(Title::from(Span::styled(format!(" {} ", state), Style::default().fg(Color::Black).bg(color))).alignment(Alignment::Right))
+        .title(Title::from(Span::styled(footer, Style::default().fg(Color::DarkGray))).position(ratatui::widgets::block::Position::Bottom))
+        .borders(Borders::ALL).border_style(Style::default().fg(color));
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(cr.selected));
+    f.render_stateful_widget(List::new(items).block(block).highlight_style(Style::default().bg(Color::Rgb(40, 40, 60))), area, &mut state);
+}
+
+/// Lista de eventos SSE: número, tipo e id en una línea y `data` debajo
+fn sse_events_text(events: &[crate::sse::SseEvent]) -> Text<'_> {
+    if events.is_empty() { return Text::styled("Waiting for events...", Style::default().fg(Color::DarkGray)); }
+    let mut lines = Vec::new();
+    for (i, e) in events.iter().enumerate() {
+        let mut head = vec![
+            Span::styled(format!("#{: <4}", i + 1), Style::default().fg(Color::DarkGray)),
+            Span::styled(e.name(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        ];
+        if let Some(id) = &e.id { head.push(Span::styled(format!("  id={}", id), Style::default().fg(Color::Yellow))); }
+        if let Some(retry) = e.retry { head.push(Span::styled(format!("  retry={}ms", retry), Style::default().fg(Color::DarkGray))); }
+        lines.push(Line::from(head));
+        lines.extend(e.data.lines().map(|l| Line::from(Span::styled(format!("      {}", l), Style::default().fg(Color::LightYellow)))));
+    }
+    Text::from(lines)
+}
+
+fn response_headers_text(resp: &ApiResponse) -> Text<'_> {
+    let lines: Vec<Line> = resp.headers.iter().map(|(k, v)| Line::from(vec![
+        Span::styled(k.as_str(), Style::default().fg(Color::LightBlue)),
+        Span::styled(": ", Style::default().fg(Color::White)),
+        Span::styled(v.as_str(), Style::default().fg(Color::LightYellow)),
+    ])).collect();
+    Text::from(lines)
+}
+
+fn response_timing_text(resp: &ApiResponse) -> Text<'_> {
+    let download = resp.elapsed.saturating_sub(resp.time_to_headers);
+    let rows = vec![
+        ("Status", resp.status_line()),
+        ("Version", resp.http_version.clone()),
+        ("URL", resp.final_url.clone()),
+        ("Remote", resp.remote_addr.clone().unwrap_or_else(|| "-".to_string())),
+        ("Headers", format!("{} ms", resp.time_to_headers.as_millis())),
+        ("Download", format!("{} ms", download.as_millis())),
+        ("Total", format!("{} ms", resp.elapsed.as_millis())),
+        ("Size", format!("{} ({} headers)", crate::response::format_size(resp.body.len()), resp.headers.len())),
+    ];
+    let lines: Vec<Line> = rows.into_iter().map(|(k, v)| Line::from(vec![
+        Span::styled(format!(" {: <9}", k), Style::default().fg(Color::Yellow)),
+        Span::styled("│ ", Style::default().fg(Color::DarkGray)),
+        Span::styled(v, Style::default().fg(Color::Gray)),
+    ])).collect();
+    Text::from(lines)
+}
+
+fn draw_json_tree(f: &mut Frame, tree: &mut crate::json_tree::JsonTree, block: Block, area: Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    tree.scroll_into_view(height);
+    let rows = tree.rows();
+    let lines: Vec<Line> = rows.iter().enumerate().skip(tree.offset).take(height).map(|(i, row)| {
+        let mut spans = vec![Span::raw("  ".repeat(row.depth))];
+        if row.is_container() {
+            let icon = if tree.expanded.contains(&row.pointer) { "▼ " } else { "▶ " };
+            spans.push(Span::styled(icon, Style::default().fg(Color::Magenta)));
+        } else {
+            spans.push(Span::raw("  "));
+        }
+        if let Some(label) = &row.label {
+            spans.push(Span::styled(label.clone(), Style::default().fg(Color::LightBlue)));
+            spans.push(Span::styled(": ", Style::default().fg(Color::White)));
+        }
+        let value_style = if row.is_container() { Style::default().fg(Color::DarkGray) } else { Style::default().fg(Color::LightYellow) };
+        spans.push(Span::styled(crate::json_tree::summarize(row.value), value_style));
+        let line = Line::from(spans);
+        if i == tree.selected { line.style(Style::default().bg(Color::Rgb(40, 40, 70)).add_modifier(Modifier::BOLD)) } else { line }
+    }).collect();
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
 fn configure_cursor(tab: &mut crate::app::RequestTab, input_mode: bool, focus: EditorFocus) {
-    let area = match focus { EditorFocus::Url => &mut tab.url_area, EditorFocus::Headers => &mut tab.headers_area, EditorFocus::Body => &mut tab.body_area, _ => return };
+    let area = match focus { EditorFocus::Url => &mut tab.url_area, EditorFocus::Params => &mut tab.params_area, EditorFocus::Headers => &mut tab.headers_area, EditorFocus::Body => &mut tab.body_area, EditorFocus::Variables => &mut tab.graphql_vars_area, EditorFocus::Auth => &mut tab.auth_area, EditorFocus::PreScript => &mut tab.pre_script_area, EditorFocus::PostScript => &mut tab.post_script_area, EditorFocus::Tests => &mut tab.tests_area, EditorFocus::Description => &mut tab.description_area, _ => return };
     if input_mode && tab.editor_focus == focus { area.set_cursor_style(Style::default().bg(Color::Yellow).fg(Color::Black)); }
     else { area.set_cursor_style(Style::default()); }
 }
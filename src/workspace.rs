@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use crate::collections::SCHEMA_VERSION;
+use crate::config::Config;
+
+/// Workspace por defecto, bajo el directorio actual
+pub const DEFAULT_DIR: &str = ".clicaude";
+/// Manifiesto en la raíz del workspace
+pub const MANIFEST_FILE: &str = "arthema.json";
+
+/// Lo que no se comparte con el equipo: historial y tokens OAuth de cada uno
+const GITIGNORE: &str = "history.jsonl\noauth_tokens.json\n";
+
+/// `arthema.json`: identifica el directorio como workspace y con qué esquema se escribió
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub schema_version: u64,
+}
+
+/// Workspace a abrir: el de `--workspace`, un `.clicaude` que ya exista en el directorio actual
+/// (el del proyecto), el último usado o `workspaces/default` en el directorio de configuración.
+/// Nunca crea `.clicaude` en el directorio desde el que se lanzó.
+pub fn resolve(config: &Config, flag: Option<&Path>) -> PathBuf {
+    if let Some(dir) = flag { return dir.to_path_buf(); }
+    if Path::new(DEFAULT_DIR).is_dir() { return PathBuf::from(DEFAULT_DIR); }
+    if let Some(dir) = config.workspace.as_ref().filter(|d| d.is_dir()) { return dir.clone(); }
+    Config::dir().map(|d| d.join("workspaces").join("default")).unwrap_or_else(|| PathBuf::from(DEFAULT_DIR))
+}
+
+/// Registra el workspace en la config con el nombre de su manifiesto (`nombre (2)`... si ya
+/// hay otro así) y lo deja como el último usado. Devuelve el nombre con que quedó.
+pub fn register(config: &mut Config, dir: &Path, name: &str) -> String {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    config.workspace = Some(dir.clone());
+    if let Some((existing, _)) = config.workspaces.iter().find(|(_, p)| **p == dir) { return existing.clone(); }
+    let name = (1..).map(|n| if n == 1 { name.to_string() } else { format!("{} ({})", name, n) }).find(|n| !config.workspaces.contains_key(n)).unwrap_or_default();
+    config.workspaces.insert(name.clone(), dir);
+    name
+}
+
+/// Abre el workspace y lo inicializa si hace falta (directorios, manifiesto y `.gitignore`).
+/// Falla si lo escribió una versión más nueva de Arthema.
+pub fn open(dir: &Path) -> Result<Manifest> {
+    fs::create_dir_all(dir.join("collections"))?;
+    let path = dir.join(MANIFEST_FILE);
+    let mut manifest = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str::<Manifest>(&content)?,
+        Err(_) => {
+            if !dir.join(".gitignore").exists() { fs::write(dir.join(".gitignore"), GITIGNORE)?; }
+            Manifest { name: default_name(dir), schema_version: 0 }
+        }
+    };
+    if manifest.schema_version > SCHEMA_VERSION {
+        anyhow::bail!("workspace {} uses collection schema v{}, newer than this Arthema (v{})", dir.display(), manifest.schema_version, SCHEMA_VERSION);
+    }
+    if manifest.schema_version < SCHEMA_VERSION {
+        manifest.schema_version = SCHEMA_VERSION;
+        fs::write(&path, format!("{}\n", serde_json::to_string_pretty(&manifest)?))?;
+    }
+    Ok(manifest)
+}
+
+/// `arthema migrate [--workspace <DIR>]`: reescribe en el esquema actual los archivos de
+/// colección que Arthema solo migra en memoria al cargarlos
+pub fn migrate_cli(args: &[String]) -> i32 {
+    let flag = match args {
+        [] => None,
+        [flag, path] if flag == "--workspace" || flag == "-w" => Some(PathBuf::from(path)),
+        _ => { eprintln!("Usage: arthema migrate [--workspace <DIR>]"); return 2; }
+    };
+    let dir = resolve(&Config::load(), flag.as_deref());
+    let mut collections = crate::collections::CollectionManager::open(&dir.to_string_lossy());
+    for e in &collections.load_errors { eprintln!("warning: skipped {}", e); }
+    if collections.manifest.is_none() { return 2; }
+    match collections.migrate() {
+        Ok(n) => { println!("Migrated {} request file(s) in {} to collection schema v{}.", n, dir.display(), SCHEMA_VERSION); 0 }
+        Err(e) => { eprintln!("error: {}", e); 1 }
+    }
+}
+
+/// Nombre del directorio; para `.clicaude` el del proyecto que lo contiene
+fn default_name(dir: &Path) -> String {
+    let dir = if dir.ends_with(DEFAULT_DIR) { dir.canonicalize().ok().and_then(|d| d.parent().map(Path::to_path_buf)).unwrap_or_default() } else { dir.to_path_buf() };
+    dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "workspace".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_initializes_and_rejects_newer_workspaces() {
+        let tmp = crate::test_util::TempDir::new("ws");
+        let dir = tmp.join("team-api");
+        let manifest = open(&dir).unwrap();
+        assert_eq!(manifest, Manifest { name: "team-api".to_string(), schema_version: SCHEMA_VERSION });
+        assert!(dir.join("collections").is_dir());
+        assert_eq!(fs::read_to_string(dir.join(".gitignore")).unwrap(), GITIGNORE);
+
+        fs::write(dir.join(MANIFEST_FILE), r#"{"name": "team-api", "schema_version": 99}"#).unwrap();
+        assert!(open(&dir).unwrap_err().to_string().contains("v99"));
+    }
+
+    #[test]
+    fn test_register_dedupes_names_and_paths() {
+        let mut config = Config::default();
+        let tmp = crate::test_util::TempDir::new("ws_register");
+        let (a, b) = (tmp.join("a"), tmp.join("b"));
+        assert_eq!(register(&mut config, &a, "api"), "api");
+        assert_eq!(register(&mut config, &b, "api"), "api (2)");
+        assert_eq!(register(&mut config, &a, "renamed"), "api");
+        assert_eq!((config.workspaces.len(), config.workspace.clone()), (2, Some(a.clone())));
+        assert_eq!(resolve(&config, Some(&b)), b);
+    }
+}
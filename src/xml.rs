@@ -0,0 +1,99 @@
+/// Trozos de un documento XML tal como aparecen en el texto
+#[derive(Debug, PartialEq)]
+pub enum Token<'a> {
+    /// `<a x="1">`
+    Open(&'a str),
+    /// `</a>`
+    Close(&'a str),
+    /// `<a/>`, `<?xml ..?>`, `<!-- .. -->`, `<![CDATA[..]]>`, `<!DOCTYPE ..>`
+    Single(&'a str),
+    Text(&'a str),
+}
+
+/// Separa etiquetas y texto. `None` si hay una etiqueta sin cerrar.
+pub fn tokenize(text: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            tokens.push(Token::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+        let end = if rest.starts_with("<!--") { rest.find("-->")? + 3 }
+            else if rest.starts_with("<![CDATA[") { rest.find("]]>")? + 3 }
+            else { tag_end(rest)? };
+        let tag = &rest[..end];
+        tokens.push(if tag.starts_with("</") { Token::Close(tag) }
+            else if tag.starts_with("<?") || tag.starts_with("<!") || tag.ends_with("/>") { Token::Single(tag) }
+            else { Token::Open(tag) });
+        rest = &rest[end..];
+    }
+    Some(tokens)
+}
+
+/// Fin de una etiqueta respetando `>` dentro de valores entre comillas
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn tag_name(tag: &str) -> &str {
+    tag.trim_start_matches(['<', '/', '?', '!']).split(|c: char| c.is_whitespace() || c == '>' || c == '/').next().unwrap_or("")
+}
+
+/// Indenta con dos espacios; un elemento con sólo texto queda en una línea.
+/// `None` si no parece XML o las etiquetas no cuadran.
+pub fn pretty(text: &str) -> Option<String> {
+    let text = text.trim();
+    if !text.starts_with('<') { return None; }
+    let tokens: Vec<Token> = tokenize(text)?.into_iter().filter(|t| !matches!(t, Token::Text(s) if s.trim().is_empty())).collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let pad = "  ".repeat(stack.len());
+        match &tokens[i] {
+            Token::Open(tag) => {
+                // <a>texto</a> en una sola línea
+                if let (Some(Token::Text(t)), Some(Token::Close(close))) = (tokens.get(i + 1), tokens.get(i + 2)) && tag_name(close) == tag_name(tag) {
+                    out.push(format!("{}{}{}{}", pad, tag, t.trim(), close));
+                    i += 3;
+                    continue;
+                }
+                out.push(format!("{}{}", pad, tag));
+                stack.push(tag_name(tag));
+            }
+            Token::Close(tag) => {
+                if stack.pop()? != tag_name(tag) { return None; }
+                out.push(format!("{}{}", "  ".repeat(stack.len()), tag));
+            }
+            Token::Single(tag) => out.push(format!("{}{}", pad, tag)),
+            Token::Text(t) => out.push(format!("{}{}", pad, t.trim())),
+        }
+        i += 1;
+    }
+    stack.is_empty().then(|| out.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretty_print() {
+        let xml = r#"<?xml version="1.0"?><users><user id="1" note="a>b"><name>Ana</name><tags/></user><!-- fin --></users>"#;
+        assert_eq!(pretty(xml).unwrap(), "<?xml version=\"1.0\"?>\n<users>\n  <user id=\"1\" note=\"a>b\">\n    <name>Ana</name>\n    <tags/>\n  </user>\n  <!-- fin -->\n</users>");
+        assert!(pretty("<a><b></a>").is_none());
+        assert!(pretty("{\"json\": true}").is_none());
+    }
+}